@@ -1,32 +1,79 @@
 use dotenv::dotenv;
+use std::time::Duration;
 use tactix::{
-  actors::{mid_price::MidPriceActor, moving_average::MovingAverageActor},
+  actors::{
+    mid_price::{MidPriceActor, MidPriceIndicator},
+    moving_average::MovingAverageActor,
+  },
+  algos::moving_average::Sma,
   binance_websocket::BinanceIngestor,
+  control::{feed::EventFeed, monitor::Monitor, ControlApi},
+  metrics::Metrics,
   policy_maker::PolicyMakerActor,
   trade::TradeActor,
   Actor,
 };
+use tracing_subscriber::EnvFilter;
 
 #[actix::main]
 async fn main() {
   dotenv().ok();
-  env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+  tracing_subscriber::fmt()
+    .with_env_filter(
+      EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+    )
+    .init();
 
-  let trade_actor = TradeActor::new().start();
+  let metrics = Metrics::new();
+  let monitor = Monitor::new().start();
+  let feed = EventFeed::new().with_metrics(&metrics).start();
+  let trade_actor = TradeActor::new()
+    .with_subscribers(vec![feed.clone().recipient()])
+    .with_metrics(&metrics)
+    .start();
 
-  let policy_maker_actor =
-    PolicyMakerActor::new(vec![trade_actor.recipient()]).start();
+  let policy_maker_actor = PolicyMakerActor::new(vec![
+    trade_actor.clone().recipient(),
+    feed.clone().recipient(),
+  ])
+  .with_metrics(&metrics)
+  .start();
 
-  let moving_avg_actor =
-    MovingAverageActor::new(3, vec![policy_maker_actor.clone().recipient()])
-      .start();
+  let moving_avg_actor = MovingAverageActor::new(
+    Sma::new(3).expect("non-zero period"),
+    vec![
+      policy_maker_actor.clone().recipient(),
+      monitor.clone().recipient(),
+    ],
+  )
+  .with_metrics(&metrics, "moving_average")
+  .start();
 
-  let midprice_actor = MidPriceActor::new(vec![
-    moving_avg_actor.clone().recipient(),
-    policy_maker_actor.clone().recipient(),
-  ])
+  let midprice_actor = MidPriceActor::new(
+    MidPriceIndicator::default(),
+    vec![
+      moving_avg_actor.clone().recipient(),
+      policy_maker_actor.clone().recipient(),
+      monitor.clone().recipient(),
+      feed.clone().recipient(),
+    ],
+  )
+  .with_metrics(&metrics, "mid_price")
   .start();
 
-  let st = BinanceIngestor::new(vec![midprice_actor.recipient()], vec![]);
+  let interval = Duration::from_secs(5);
+  metrics.watch_mailbox("trade", trade_actor.clone(), interval);
+  metrics.watch_mailbox("policy_maker", policy_maker_actor.clone(), interval);
+
+  let api = ControlApi::new(monitor, trade_actor, policy_maker_actor)
+    .with_actor("moving_average", moving_avg_actor)
+    .with_feed(feed)
+    .with_metrics(metrics.clone())
+    .bind(("127.0.0.1", 8080))
+    .expect("control API address");
+  actix::spawn(api);
+
+  let st = BinanceIngestor::new(vec![midprice_actor.recipient()], vec![])
+    .with_metrics(&metrics);
   actix::spawn(st.run()).await.unwrap();
 }
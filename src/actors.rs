@@ -1,3 +1,8 @@
+pub mod bar;
+#[cfg(test)]
+pub(crate) mod collector;
+pub mod indicator;
 pub mod mid_price;
 pub mod moving_average;
+pub mod returns;
 pub mod risk;
@@ -0,0 +1,151 @@
+use actix::{Actor, Context, Handler, Message, Recipient};
+use chrono::{DateTime, Duration, Utc};
+
+use crate::actors::mid_price::MidPrice;
+use crate::algos::indicators::HighLowClose;
+
+/// Open, high, low, close and volume over `[open_time, close_time)`.
+#[derive(Message, Debug, Clone, PartialEq)]
+#[rtype(result = "()")]
+pub struct Bar {
+  pub symbol: String,
+  pub open_time: DateTime<Utc>,
+  pub close_time: DateTime<Utc>,
+  pub open: f64,
+  pub high: f64,
+  pub low: f64,
+  pub close: f64,
+  pub volume: f64,
+}
+
+impl From<Bar> for f64 {
+  fn from(bar: Bar) -> Self {
+    bar.close
+  }
+}
+
+impl From<Bar> for HighLowClose {
+  fn from(bar: Bar) -> Self {
+    Self {
+      high: bar.high,
+      low: bar.low,
+      close: bar.close,
+    }
+  }
+}
+
+/// Aggregates mid prices into bars of a fixed `interval`, aligned to the
+/// epoch. A bar is published when the first price of a later interval
+/// arrives, intervals without prices produce no bar.
+pub struct BarActor {
+  interval: Duration,
+  current: Option<Bar>,
+  subscribers: Vec<Recipient<Bar>>,
+}
+
+impl BarActor {
+  pub fn new(
+    interval: Duration,
+    subscribers: Vec<Recipient<Bar>>,
+  ) -> Option<Self> {
+    if interval.num_milliseconds() < 1 {
+      None
+    } else {
+      Some(Self {
+        interval,
+        current: None,
+        subscribers,
+      })
+    }
+  }
+}
+
+/// Start of the epoch aligned `interval` containing `timestamp`.
+pub(crate) fn interval_start(
+  timestamp: DateTime<Utc>,
+  interval: Duration,
+) -> DateTime<Utc> {
+  let millis = timestamp.timestamp_millis();
+  let offset = millis.rem_euclid(interval.num_milliseconds());
+  timestamp - Duration::milliseconds(offset)
+}
+
+impl Actor for BarActor {
+  type Context = Context<Self>;
+}
+
+impl Handler<MidPrice> for BarActor {
+  type Result = ();
+
+  fn handle(&mut self, msg: MidPrice, _ctx: &mut Context<Self>) {
+    let open_time = interval_start(msg.timestamp, self.interval);
+    match &mut self.current {
+      Some(bar) if msg.timestamp < bar.close_time => {
+        bar.high = bar.high.max(msg.price);
+        bar.low = bar.low.min(msg.price);
+        bar.close = msg.price;
+      }
+      current => {
+        let next = Bar {
+          symbol: msg.symbol,
+          open_time,
+          close_time: open_time + self.interval,
+          open: msg.price,
+          high: msg.price,
+          low: msg.price,
+          close: msg.price,
+          volume: 0.,
+        };
+        if let Some(done) = current.replace(next) {
+          for s in &self.subscribers {
+            s.do_send(done.clone());
+          }
+        }
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use chrono::TimeZone;
+
+  #[actix_rt::test]
+  async fn aggregates_by_interval() {
+    let (collector, recipient) = collect();
+    let addr = BarActor::new(Duration::minutes(1), vec![recipient])
+      .unwrap()
+      .start();
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+    for (seconds, price) in [(10, 2.), (20, 3.), (30, 1.), (50, 2.5), (70, 4.)]
+    {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "BTCUSDT".to_owned(),
+          timestamp: start + Duration::seconds(seconds),
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(
+      received,
+      vec![Bar {
+        symbol: "BTCUSDT".to_owned(),
+        open_time: start,
+        close_time: start + Duration::minutes(1),
+        open: 2.,
+        high: 3.,
+        low: 1.,
+        close: 2.5,
+        volume: 0.,
+      }]
+    );
+  }
+}
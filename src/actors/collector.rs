@@ -0,0 +1,65 @@
+//! Test actor recording every message it receives.
+
+use std::marker::PhantomData;
+
+use actix::{Actor, Addr, Context, Handler, Message, MessageResult, Recipient};
+
+pub(crate) struct Collector<M> {
+  received: Vec<M>,
+}
+
+impl<M> Collector<M> {
+  pub(crate) fn new() -> Self {
+    Self { received: vec![] }
+  }
+}
+
+/// Starts a collector, returning it along with a recipient to subscribe.
+pub(crate) fn collect<M>() -> (Addr<Collector<M>>, Recipient<M>)
+where
+  M: Message + Send + Unpin + 'static,
+  M::Result: Default + Send,
+{
+  let addr = Collector::new().start();
+  let recipient = addr.clone().recipient::<M>();
+  (addr, recipient)
+}
+
+impl<M: Unpin + 'static> Actor for Collector<M> {
+  type Context = Context<Self>;
+}
+
+impl<M> Handler<M> for Collector<M>
+where
+  M: Message + Unpin + 'static,
+  M::Result: Default,
+{
+  type Result = MessageResult<M>;
+
+  fn handle(&mut self, msg: M, _ctx: &mut Context<Self>) -> Self::Result {
+    self.received.push(msg);
+    MessageResult(Default::default())
+  }
+}
+
+/// Drains everything received so far. Sent after the messages under test,
+/// it is handled after them.
+pub(crate) struct Take<M>(PhantomData<M>);
+
+impl<M> Take<M> {
+  pub(crate) fn new() -> Self {
+    Self(PhantomData)
+  }
+}
+
+impl<M: 'static> Message for Take<M> {
+  type Result = Vec<M>;
+}
+
+impl<M: Unpin + 'static> Handler<Take<M>> for Collector<M> {
+  type Result = MessageResult<Take<M>>;
+
+  fn handle(&mut self, _: Take<M>, _ctx: &mut Context<Self>) -> Self::Result {
+    MessageResult(std::mem::take(&mut self.received))
+  }
+}
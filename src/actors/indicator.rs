@@ -0,0 +1,281 @@
+use actix::{Actor, Context, Handler, Message, Recipient};
+
+use crate::actors::bar::Bar;
+use crate::actors::mid_price::MidPrice;
+use crate::actors::risk::information::BenchmarkReturn;
+use crate::actors::risk::sharpe::Return;
+use crate::actors::risk::Equity;
+use crate::algos::indicators::Indicator;
+use crate::binance_websocket::TickerMessage;
+use crate::correlation::{self, Correlated};
+use crate::metrics::{Export, Metrics, Ping};
+use crate::util::Double;
+
+/// Output of an [`IndicatorActor`] whose indicator has no dedicated message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorMessage<T>(pub T);
+
+impl<T: 'static> Message for IndicatorMessage<T> {
+  type Result = ();
+}
+
+impl<T> From<T> for IndicatorMessage<T> {
+  fn from(value: T) -> Self {
+    Self(value)
+  }
+}
+
+/// Resets the indicator, which has to warm up again before publishing.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Reset;
+
+/// Adds a subscriber to an [`IndicatorActor`] that is already running.
+pub struct Subscribe<O: Message + Send>(pub Recipient<O>)
+where
+  O::Result: Send;
+
+impl<O> Message for Subscribe<O>
+where
+  O: Message + Send + 'static,
+  O::Result: Send,
+{
+  type Result = ();
+}
+
+/// Feeds every input message it handles to `indicator` and, once the
+/// indicator is ready, publishes each new value to the subscribers as an
+/// `O`. Inputs are any message convertible into the indicator's input.
+pub struct IndicatorActor<I, O: Message + Send>
+where
+  O::Result: Send,
+{
+  indicator: I,
+  subscribers: Vec<Recipient<O>>,
+  instrumentation: Option<Instrumentation<O>>,
+}
+
+struct Instrumentation<O> {
+  metrics: Metrics,
+  actor: String,
+  export: fn(&O, &Metrics),
+}
+
+impl<I, O> IndicatorActor<I, O>
+where
+  I: Indicator,
+  O: Message + Send + From<I::Output> + Clone + Correlated,
+  O::Result: Send,
+{
+  pub fn new(indicator: I, subscribers: Vec<Recipient<O>>) -> Self {
+    Self {
+      indicator,
+      subscribers,
+      instrumentation: None,
+    }
+  }
+
+  /// Counts the inputs as messages of `actor` and records each output.
+  pub fn with_metrics(mut self, metrics: &Metrics, actor: &str) -> Self
+  where
+    O: Export,
+  {
+    self.instrumentation = Some(Instrumentation {
+      metrics: metrics.clone(),
+      actor: actor.to_owned(),
+      export: O::export,
+    });
+    self
+  }
+
+  /// Outputs keep the correlation of the input they derive from.
+  fn update(&mut self, input: impl Into<I::Input> + Correlated) {
+    let correlation = input.correlation();
+    let actor = self
+      .instrumentation
+      .as_ref()
+      .map_or(std::any::type_name::<I>(), |i| i.actor.as_str());
+    let _span = correlation::span(actor, correlation).entered();
+    if let Some(i) = &self.instrumentation {
+      i.metrics.handled(&i.actor);
+    }
+    match self.indicator.update(input.into()) {
+      Some(output) if self.indicator.is_ready() => {
+        let mut msg = O::from(output);
+        if let Some(correlation) = correlation {
+          msg.correlate(correlation);
+        }
+        if let Some(i) = &self.instrumentation {
+          (i.export)(&msg, &i.metrics);
+        }
+        for s in &self.subscribers {
+          s.do_send(msg.clone());
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+impl<I, O> Actor for IndicatorActor<I, O>
+where
+  I: Unpin + 'static,
+  O: Message + Send + Unpin + 'static,
+  O::Result: Send,
+{
+  type Context = Context<Self>;
+}
+
+impl<I, O> Handler<Reset> for IndicatorActor<I, O>
+where
+  I: Indicator + Unpin + 'static,
+  O: Message + Send + Unpin + 'static,
+  O::Result: Send,
+{
+  type Result = ();
+
+  fn handle(&mut self, _: Reset, _ctx: &mut Context<Self>) {
+    self.indicator.reset();
+  }
+}
+
+impl<I, O> Handler<Subscribe<O>> for IndicatorActor<I, O>
+where
+  I: Unpin + 'static,
+  O: Message + Send + Unpin + 'static,
+  O::Result: Send,
+{
+  type Result = ();
+
+  fn handle(&mut self, msg: Subscribe<O>, _ctx: &mut Context<Self>) {
+    self.subscribers.push(msg.0);
+  }
+}
+
+impl<I, O> Handler<Ping> for IndicatorActor<I, O>
+where
+  I: Unpin + 'static,
+  O: Message + Send + Unpin + 'static,
+  O::Result: Send,
+{
+  type Result = ();
+
+  fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+}
+
+macro_rules! input_handlers {
+  ($($input:ty),*) => {$(
+    impl<I, O> Handler<$input> for IndicatorActor<I, O>
+    where
+      I: Indicator + Unpin + 'static,
+      I::Input: From<$input>,
+      O: Message + Send + From<I::Output> + Clone + Correlated + Unpin + 'static,
+      O::Result: Send,
+    {
+      type Result = ();
+
+      fn handle(&mut self, msg: $input, _ctx: &mut Context<Self>) {
+        self.update(msg)
+      }
+    }
+  )*};
+}
+
+input_handlers!(
+  TickerMessage,
+  MidPrice,
+  Bar,
+  Double,
+  Return,
+  BenchmarkReturn,
+  Equity
+);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::actors::moving_average::MovingAverageMessage;
+  use crate::algos::indicators::atr::Atr;
+  use crate::algos::indicators::rsi::Rsi;
+  use crate::algos::moving_average::Sma;
+  use chrono::{Duration, Utc};
+
+  #[actix_rt::test]
+  async fn publishes_once_warm() {
+    let (collector, recipient) = collect::<IndicatorMessage<f64>>();
+    let addr =
+      IndicatorActor::new(Rsi::new(2).unwrap(), vec![recipient]).start();
+    for price in [1., 2., 3., 2.] {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "BTCUSDT".to_owned(),
+          timestamp: Utc::now(),
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(
+      received,
+      vec![IndicatorMessage(100.), IndicatorMessage(50.)]
+    );
+  }
+
+  #[actix_rt::test]
+  async fn consumes_bars() {
+    let (collector, recipient) = collect::<IndicatorMessage<f64>>();
+    let addr =
+      IndicatorActor::new(Atr::new(1).unwrap(), vec![recipient]).start();
+    let open_time = Utc::now();
+    addr
+      .send(Bar {
+        symbol: "BTCUSDT".to_owned(),
+        open_time,
+        close_time: open_time + Duration::minutes(1),
+        open: 1.,
+        high: 3.,
+        low: 1.,
+        close: 2.,
+        volume: 0.,
+      })
+      .await
+      .unwrap();
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(received, vec![IndicatorMessage(2.)]);
+  }
+
+  fn price(price: f64) -> MidPrice {
+    MidPrice {
+      price,
+      symbol: "BTCUSDT".to_owned(),
+      timestamp: Utc::now(),
+      correlation: None,
+    }
+  }
+
+  #[actix_rt::test]
+  async fn reset_and_subscribe() {
+    let (collector, recipient) = collect::<MovingAverageMessage>();
+    let addr = IndicatorActor::new(Sma::new(2).unwrap(), vec![]).start();
+
+    addr.send(price(1.)).await.unwrap();
+    addr.send(Subscribe(recipient)).await.unwrap();
+    addr.send(price(3.)).await.unwrap();
+    addr.send(Reset).await.unwrap();
+    // warming up again
+    addr.send(price(5.)).await.unwrap();
+    addr.send(price(7.)).await.unwrap();
+
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(
+      received,
+      vec![
+        MovingAverageMessage::from(2.),
+        MovingAverageMessage::from(6.)
+      ]
+    );
+  }
+}
@@ -1,74 +1,141 @@
-use crate::{Actor, Context, Handler, Message, Recipient};
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::correlation::{Correlated, Correlation};
+use crate::Message;
+use chrono::{DateTime, Utc};
 
-#[derive(Message)]
-#[rtype(result = "MidPriceResponse")]
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
 pub struct MidPrice {
   pub price: f64,
   pub symbol: String,
+  pub timestamp: DateTime<Utc>,
+  pub correlation: Option<Correlation>,
 }
 
-#[derive(Debug)]
-pub enum MidPriceResponse {
-  MovingAverage(f64),
-  Policy(f64),
+impl Correlated for MidPrice {
+  fn correlation(&self) -> Option<Correlation> {
+    self.correlation
+  }
+
+  fn correlate(&mut self, correlation: Correlation) {
+    self.correlation = Some(correlation);
+  }
+}
+
+impl From<MidPrice> for f64 {
+  fn from(msg: MidPrice) -> Self {
+    msg.price
+  }
+}
+
+impl From<MidPrice> for (DateTime<Utc>, f64) {
+  fn from(msg: MidPrice) -> Self {
+    (msg.timestamp, msg.price)
+  }
 }
 
 use crate::binance_websocket::TickerMessage;
+use crate::clock::{SharedClock, WallClock};
+use std::sync::Arc;
 
-pub struct MidPriceActor {
-  pub subscribers: Vec<Recipient<MidPrice>>,
+/// Mid point between the best bid and the best ask of a ticker, stamped
+/// with the ticker's event time or, for streams without one, the time of
+/// `clock`.
+#[derive(Clone)]
+pub struct MidPriceIndicator {
+  clock: SharedClock,
 }
 
-impl MidPriceActor {
-  pub fn new(subscribers: Vec<Recipient<MidPrice>>) -> Self {
-    Self { subscribers }
+impl MidPriceIndicator {
+  pub fn new(clock: SharedClock) -> Self {
+    Self { clock }
   }
 }
 
-impl Actor for MidPriceActor {
-  type Context = Context<Self>;
+impl Default for MidPriceIndicator {
+  fn default() -> Self {
+    Self::new(Arc::new(WallClock))
+  }
 }
 
-impl Handler<TickerMessage> for MidPriceActor {
-  type Result = f64;
-
-  fn handle(
-    &mut self,
-    msg: TickerMessage,
-    _ctx: &mut Self::Context,
-  ) -> Self::Result {
-    let TickerMessage {
-      best_bid_price,
-      best_ask_price,
-      ..
-    } = msg;
-    let price = (best_bid_price + best_ask_price) / 2f64;
-    for consumer in &self.subscribers {
-      consumer.do_send(MidPrice {
-        price,
-        symbol: msg.symbol.clone(),
-      });
-    }
-    price
+impl Indicator for MidPriceIndicator {
+  type Input = TickerMessage;
+  type Output = MidPrice;
+
+  fn update(&mut self, msg: TickerMessage) -> Option<MidPrice> {
+    Some(MidPrice {
+      price: (msg.best_bid_price + msg.best_ask_price) / 2f64,
+      correlation: msg.correlation(),
+      symbol: msg.symbol,
+      timestamp: msg.event_time.unwrap_or_else(|| self.clock.now()),
+    })
+  }
+
+  fn reset(&mut self) {}
+
+  fn is_ready(&self) -> bool {
+    true
   }
 }
 
+pub type MidPriceActor = IndicatorActor<MidPriceIndicator, MidPrice>;
+
 #[cfg(test)]
 mod tests {
   use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::clock::SimulatedClock;
+  use crate::Actor;
+  use chrono::{Duration, TimeZone};
+
+  #[test]
+  fn positive() {
+    let res = MidPriceIndicator::default()
+      .update(TickerMessage {
+        best_bid_price: 1.0,
+        best_ask_price: 1.5,
+        ..Default::default()
+      })
+      .unwrap();
+    assert_eq!(res.price, 1.25);
+  }
+
+  #[test]
+  fn timestamps() {
+    let now = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    let mut indicator =
+      MidPriceIndicator::new(Arc::new(SimulatedClock::new(now)));
+    let res = indicator.update(TickerMessage::default()).unwrap();
+    assert_eq!(res.timestamp, now);
+
+    let event_time = now - Duration::seconds(1);
+    let res = indicator
+      .update(TickerMessage {
+        event_time: Some(event_time),
+        ..Default::default()
+      })
+      .unwrap();
+    assert_eq!(res.timestamp, event_time);
+  }
 
   #[actix_rt::test]
-  async fn positive() {
-    let actor = MidPriceActor::new(vec![]);
-    let addr = actor.start();
-    let res = addr
+  async fn fan_out() {
+    let (collector, recipient) = collect::<MidPrice>();
+    let addr =
+      MidPriceActor::new(MidPriceIndicator::default(), vec![recipient]).start();
+    addr
       .send(TickerMessage {
+        symbol: "BTCUSDT".to_owned(),
         best_bid_price: 1.0,
-        best_ask_price: 1.5,
+        best_ask_price: 2.0,
         ..Default::default()
       })
       .await
       .unwrap();
-    assert_eq!(res, 1.25);
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(received.len(), 1);
+    assert_eq!(received[0].price, 1.5);
+    assert_eq!(received[0].symbol, "BTCUSDT");
   }
 }
@@ -1,151 +1,119 @@
-use actix::{Actor, Context, Handler, Message, MessageResult, Recipient};
+use actix::Message;
 
-#[derive(Message)]
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::moving_average::Sma;
+use crate::correlation::{Correlated, Correlation};
+
+#[derive(Message, Debug, Clone, PartialEq)]
 #[rtype(result = "f64")]
-pub struct MovingAverageMessage(pub f64);
+pub struct MovingAverageMessage(pub f64, pub Option<Correlation>);
 
-// Moving average is 0 if number of received messages
-// is not a multiple of the interval_length
-pub struct MovingAverageActor {
-  moving_average: f64,
-  ring_buffer: Vec<f64>,
-  interval_length: usize,
-  subscribers: Vec<Recipient<MovingAverageMessage>>,
+impl From<f64> for MovingAverageMessage {
+  fn from(average: f64) -> Self {
+    Self(average, None)
+  }
 }
 
-impl MovingAverageActor {
-  pub fn new(
-    interval_length: usize,
-    subscribers: Vec<Recipient<MovingAverageMessage>>,
-  ) -> Self {
-    Self {
-      moving_average: 0.,
-      ring_buffer: vec![0.; interval_length],
-      interval_length,
-      subscribers,
-    }
+impl Correlated for MovingAverageMessage {
+  fn correlation(&self) -> Option<Correlation> {
+    self.1
   }
-}
 
-impl Actor for MovingAverageActor {
-  type Context = Context<Self>;
+  fn correlate(&mut self, correlation: Correlation) {
+    self.1 = Some(correlation);
+  }
 }
 
-use crate::actors::mid_price::MidPrice;
-
-use super::mid_price::MidPriceResponse;
-
-impl Handler<MidPrice> for MovingAverageActor {
-  type Result = MessageResult<MidPrice>;
-
-  fn handle(
-    &mut self,
-    msg: MidPrice,
-    _ctx: &mut Context<Self>,
-  ) -> Self::Result {
-    let empty_buffer_length =
-      self.ring_buffer.iter().filter(|&n| *n == 0.).count();
-
-    match empty_buffer_length {
-      1 => {
-        self.ring_buffer[self.interval_length - empty_buffer_length] =
-          msg.price;
-        self.moving_average =
-          self.ring_buffer.iter().sum::<f64>() / self.interval_length as f64;
-
-        for s in &self.subscribers {
-          s.do_send(MovingAverageMessage(self.moving_average));
-        }
-
-        MessageResult(MidPriceResponse::MovingAverage(self.moving_average))
-      }
-      0 => {
-        self.ring_buffer.remove(0);
-        self.ring_buffer.push(msg.price);
-        self.moving_average =
-          self.ring_buffer.iter().sum::<f64>() / self.interval_length as f64;
+/// Publishes a moving average of the incoming mid prices, any of the
+/// smoothers in [`crate::algos::moving_average`]. Nothing is published until
+/// the average is warm.
+pub type MovingAverageActor<A = Sma> = IndicatorActor<A, MovingAverageMessage>;
 
-        for s in &self.subscribers {
-          s.do_send(MovingAverageMessage(self.moving_average));
-        }
-        MessageResult(MidPriceResponse::MovingAverage(self.moving_average))
-      }
-
-      _ => {
-        self.ring_buffer[self.interval_length - empty_buffer_length] =
-          msg.price;
-
-        MessageResult(MidPriceResponse::MovingAverage(0f64))
-      }
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::actors::mid_price::MidPrice;
+  use crate::algos::moving_average::TimeSma;
+  use crate::Actor;
+  use chrono::{Duration, Utc};
+
+  #[actix_rt::test]
+  async fn positive() {
+    let (collector, recipient) = collect();
+    let addr = MovingAverageActor::new(
+      Sma::new(3).expect("valid args"),
+      vec![recipient],
+    )
+    .start();
+    for price in [1., 2., 3., 4., 5., 6.] {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "".to_owned(),
+          timestamp: Utc::now(),
+          correlation: None,
+        })
+        .await
+        .unwrap();
     }
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(
+      received,
+      vec![2., 3., 4., 5.]
+        .into_iter()
+        .map(MovingAverageMessage::from)
+        .collect::<Vec<_>>()
+    );
   }
-}
-
-#[cfg(test)]
-use crate::assert_matches;
 
-#[actix_rt::test]
-async fn positive() {
-  let addr = MovingAverageActor::new(3, vec![]).start();
-  let res = addr
-    .send(MidPrice {
-      price: 1.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
+  #[actix_rt::test]
+  async fn zero_price() {
+    let (collector, recipient) = collect();
+    let addr = MovingAverageActor::new(
+      Sma::new(2).expect("valid args"),
+      vec![recipient],
+    )
+    .start();
+    for price in [0., 0., 2.] {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "".to_owned(),
+          timestamp: Utc::now(),
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(
+      received,
+      vec![
+        MovingAverageMessage::from(0.),
+        MovingAverageMessage::from(1.)
+      ]
+    );
+  }
 
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 0.)
-  });
-  let res = addr
-    .send(MidPrice {
-      price: 2.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 0.)
-  });
-  let res = addr
-    .send(MidPrice {
-      price: 3.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 2.)
-  });
-  let res = addr
-    .send(MidPrice {
-      price: 4.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 3.)
-  });
-  let res = addr
-    .send(MidPrice {
-      price: 5.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 4.)
-  });
-  let res = addr
-    .send(MidPrice {
-      price: 6.,
-      symbol: "".to_owned(),
-    })
-    .await
-    .unwrap();
-  assert_matches!(res, MidPriceResponse::MovingAverage(f) => {
-    assert_eq!(f, 5.)
-  });
+  #[actix_rt::test]
+  async fn time_windowed() {
+    let (collector, recipient) = collect();
+    let average = TimeSma::new(Duration::seconds(30)).unwrap();
+    let addr = MovingAverageActor::new(average, vec![recipient]).start();
+    let start = Utc::now();
+    for (s, price) in [(0, 1.), (10, 2.), (30, 3.)] {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "".to_owned(),
+          timestamp: start + Duration::seconds(s),
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(received, vec![MovingAverageMessage::from(2.5)]);
+  }
 }
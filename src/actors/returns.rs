@@ -0,0 +1,183 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::actors::bar::interval_start;
+use crate::actors::indicator::IndicatorActor;
+use crate::actors::risk::sharpe::Return;
+use crate::algos::indicators::Indicator;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReturnKind {
+  /// `new / old - 1`
+  #[default]
+  Simple,
+  /// `ln(new / old)`
+  Log,
+}
+
+impl ReturnKind {
+  /// `None` unless `old` is positive.
+  fn between(self, old: f64, new: f64) -> Option<f64> {
+    (old > 0.).then(|| match self {
+      Self::Simple => new / old - 1.,
+      Self::Log => (new / old).ln(),
+    })
+  }
+}
+
+/// Returns of a timestamped price or equity series, either between
+/// consecutive values or between the last values of consecutive epoch
+/// aligned intervals. A sampled return is published when the first value of
+/// a later interval arrives, and spans any intervals without values.
+#[derive(Debug, Clone)]
+pub struct Returns {
+  kind: ReturnKind,
+  interval: Option<Duration>,
+  /// Start of the interval `latest` belongs to.
+  current: Option<DateTime<Utc>>,
+  latest: Option<f64>,
+  /// Value the next return is measured from.
+  previous: Option<f64>,
+}
+
+impl Returns {
+  /// Returns between every value.
+  pub fn new(kind: ReturnKind) -> Self {
+    Self {
+      kind,
+      interval: None,
+      current: None,
+      latest: None,
+      previous: None,
+    }
+  }
+
+  /// Returns between the values closing every `interval`.
+  pub fn sampled(kind: ReturnKind, interval: Duration) -> Option<Self> {
+    if interval.num_milliseconds() < 1 {
+      None
+    } else {
+      Some(Self {
+        interval: Some(interval),
+        ..Self::new(kind)
+      })
+    }
+  }
+
+  /// Value of the last finished sample, if `time` finishes one.
+  fn sample(&mut self, time: DateTime<Utc>, value: f64) -> Option<f64> {
+    let Some(interval) = self.interval else {
+      return Some(value);
+    };
+    let start = interval_start(time, interval);
+    match self.current {
+      Some(current) if start <= current => {
+        self.latest = Some(value);
+        None
+      }
+      _ => {
+        self.current = Some(start);
+        self.latest.replace(value)
+      }
+    }
+  }
+}
+
+impl Indicator for Returns {
+  type Input = (DateTime<Utc>, f64);
+  type Output = f64;
+
+  fn update(&mut self, (time, value): Self::Input) -> Option<f64> {
+    let close = self.sample(time, value)?;
+    let old = self.previous.replace(close)?;
+    self.kind.between(old, close)
+  }
+
+  fn reset(&mut self) {
+    *self = Self {
+      interval: self.interval,
+      ..Self::new(self.kind)
+    };
+  }
+
+  fn is_ready(&self) -> bool {
+    self.previous.is_some()
+  }
+}
+
+/// Publishes the [`Return`]s of the
+/// [`MidPrice`](crate::actors::mid_price::MidPrice)s or
+/// [`Equity`](crate::actors::risk::Equity) updates it receives.
+pub type ReturnsActor = IndicatorActor<Returns, Return>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::actors::mid_price::MidPrice;
+  use crate::Actor;
+  use chrono::TimeZone;
+
+  fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("a return");
+    assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+  }
+
+  #[test]
+  fn every_tick() {
+    let now = Utc::now();
+    let mut simple = Returns::new(ReturnKind::Simple);
+    let mut log = Returns::new(ReturnKind::Log);
+    assert_eq!(simple.update((now, 100.)), None);
+    assert_eq!(log.update((now, 100.)), None);
+
+    assert_close(simple.update((now, 110.)), 0.1);
+    assert_close(simple.update((now, 99.)), -0.1);
+    assert_close(log.update((now, 110.)), 1.1f64.ln());
+    assert_close(log.update((now, 99.)), 0.9f64.ln());
+  }
+
+  #[test]
+  fn non_positive_values() {
+    let now = Utc::now();
+    let mut returns = Returns::new(ReturnKind::Log);
+    returns.update((now, 0.));
+    assert_eq!(returns.update((now, 1.)), None);
+    assert_eq!(returns.update((now, 2.)), Some(2f64.ln()));
+  }
+
+  #[actix_rt::test]
+  async fn sampled_by_interval() {
+    let (collector, recipient) = collect::<Return>();
+    let returns =
+      Returns::sampled(ReturnKind::Simple, Duration::minutes(1)).unwrap();
+    let addr = ReturnsActor::new(returns, vec![recipient]).start();
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+    for (seconds, price) in [
+      (10, 1.),
+      (50, 2.),
+      (70, 3.),
+      (110, 4.),
+      (130, 5.),
+      (250, 6.),
+    ] {
+      addr
+        .send(MidPrice {
+          price,
+          symbol: "BTCUSDT".to_owned(),
+          timestamp: start + Duration::seconds(seconds),
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(received, vec![Return(1.), Return(0.25)]);
+  }
+
+  #[test]
+  fn invalid_interval() {
+    assert!(Returns::sampled(ReturnKind::Log, Duration::zero()).is_none());
+  }
+}
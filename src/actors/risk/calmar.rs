@@ -0,0 +1,112 @@
+use super::periods_per_year;
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::Message;
+use chrono::Duration;
+
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct CalmarRatio(pub f64);
+
+impl From<f64> for CalmarRatio {
+  fn from(ratio: f64) -> Self {
+    Self(ratio)
+  }
+}
+
+/// Compound annual growth rate of an equity multiplied by `growth` over
+/// `years`, divided by its max percentage `drawdown`. `None` without a
+/// drawdown or time, for a negative `growth`, or when not finite.
+pub fn calmar_ratio(growth: f64, years: f64, drawdown: f64) -> Option<f64> {
+  if growth < 0. || years <= 0. || drawdown <= 0. {
+    return None;
+  }
+  let ratio = (growth.powf(1. / years) - 1.) / drawdown;
+  ratio.is_finite().then_some(ratio)
+}
+
+/// Calmar ratio of every return seen: the compound annual growth rate of
+/// the equity the returns describe, divided by its max percentage drawdown.
+/// Nothing is published before the first drawdown.
+#[derive(Debug, Clone)]
+pub struct Calmar {
+  periods_per_year: f64,
+  count: u32,
+  equity: f64,
+  peak: f64,
+  max_drawdown: f64,
+}
+
+impl Calmar {
+  /// Returns are sampled every `interval`.
+  pub fn new(interval: Duration) -> Option<Self> {
+    Some(Self {
+      periods_per_year: periods_per_year(interval)?,
+      count: 0,
+      equity: 1.,
+      peak: 1.,
+      max_drawdown: 0.,
+    })
+  }
+}
+
+impl Indicator for Calmar {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, new: f64) -> Option<f64> {
+    self.count += 1;
+    self.equity *= 1. + new;
+    self.peak = self.peak.max(self.equity);
+    self.max_drawdown =
+      self.max_drawdown.max((self.peak - self.equity) / self.peak);
+
+    if !self.is_ready() {
+      return None;
+    }
+    let years = self.count as f64 / self.periods_per_year;
+    calmar_ratio(self.equity, years, self.max_drawdown)
+  }
+
+  fn reset(&mut self) {
+    self.count = 0;
+    self.equity = 1.;
+    self.peak = 1.;
+    self.max_drawdown = 0.;
+  }
+
+  fn is_ready(&self) -> bool {
+    self.max_drawdown > 0.
+  }
+}
+
+/// Publishes the Calmar ratio of the [`super::sharpe::Return`]s it receives.
+pub type CalmarActor = IndicatorActor<Calmar, CalmarRatio>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn growth_over_drawdown() {
+    let mut calmar = Calmar::new(Duration::days(1)).expect("valid args");
+    assert_eq!(calmar.update(0.01), None);
+    assert!(calmar.update(-0.02).is_some());
+    let res = calmar.update(0.01).unwrap();
+    assert!((res - -1.8040919254373402).abs() < 1e-9);
+  }
+
+  #[test]
+  fn ratio() {
+    assert!((calmar_ratio(1.21, 2., 0.5).unwrap() - 0.2).abs() < 1e-12);
+    assert_eq!(calmar_ratio(0., 1., 0.5), Some(-2.));
+    assert_eq!(calmar_ratio(-0.5, 1., 0.5), None);
+    assert_eq!(calmar_ratio(1.1, 0., 0.5), None);
+    assert_eq!(calmar_ratio(1.1, 1., 0.), None);
+  }
+
+  #[test]
+  fn invalid_interval() {
+    assert!(Calmar::new(Duration::zero()).is_none());
+  }
+}
@@ -0,0 +1,165 @@
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::Message;
+use chrono::{DateTime, Duration, Utc};
+
+/// State of the drawdown after each value.
+#[derive(Message, Debug, Clone, PartialEq)]
+#[rtype(result = "()")]
+pub struct DrawdownReport {
+  pub timestamp: DateTime<Utc>,
+  /// Decline from the running peak.
+  pub current: f64,
+  /// `current` as a fraction of the peak, zero while the peak isn't positive.
+  pub current_percent: f64,
+  pub max: f64,
+  /// Largest fractional decline, possibly from another episode than `max`.
+  pub max_percent: f64,
+  /// Peak and trough of the `max` drawdown, the running peak before any.
+  pub peak_time: DateTime<Utc>,
+  pub trough_time: DateTime<Utc>,
+  /// Time since the running peak, zero at a new high.
+  pub duration: Duration,
+  pub max_duration: Duration,
+  /// Time the `max` drawdown took from its trough back to its peak, `None`
+  /// while it hasn't recovered or there's been no drawdown.
+  pub recovery: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Episode {
+  peak: f64,
+  peak_time: DateTime<Utc>,
+  trough_time: DateTime<Utc>,
+}
+
+/// Peak to trough declines of a timestamped value series.
+#[derive(Debug, Clone, Default)]
+pub struct Drawdown {
+  peak: Option<(DateTime<Utc>, f64)>,
+  max: f64,
+  max_percent: f64,
+  worst: Option<Episode>,
+  recovery: Option<Duration>,
+  max_duration: Duration,
+}
+
+impl Drawdown {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+impl Indicator for Drawdown {
+  type Input = (DateTime<Utc>, f64);
+  type Output = DrawdownReport;
+
+  fn update(&mut self, (time, value): Self::Input) -> Option<DrawdownReport> {
+    let (peak_time, peak) = match self.peak {
+      Some(peak) if value < peak.1 => peak,
+      _ => {
+        if let (Some(worst), None) = (self.worst, self.recovery) {
+          if value >= worst.peak {
+            self.recovery = Some(time - worst.trough_time);
+          }
+        }
+        *self.peak.insert((time, value))
+      }
+    };
+
+    let current = peak - value;
+    let current_percent = if peak > 0. { current / peak } else { 0. };
+    if current > self.max {
+      self.max = current;
+      self.worst = Some(Episode {
+        peak,
+        peak_time,
+        trough_time: time,
+      });
+      self.recovery = None;
+    }
+    self.max_percent = self.max_percent.max(current_percent);
+    let duration = time - peak_time;
+    self.max_duration = self.max_duration.max(duration);
+
+    let (peak_time, trough_time) = self
+      .worst
+      .map_or((peak_time, peak_time), |w| (w.peak_time, w.trough_time));
+    Some(DrawdownReport {
+      timestamp: time,
+      current,
+      current_percent,
+      max: self.max,
+      max_percent: self.max_percent,
+      peak_time,
+      trough_time,
+      duration,
+      max_duration: self.max_duration,
+      recovery: self.recovery,
+    })
+  }
+
+  fn reset(&mut self) {
+    *self = Self::new();
+  }
+
+  fn is_ready(&self) -> bool {
+    true
+  }
+}
+
+/// Publishes a [`DrawdownReport`] for every [`super::Equity`] or
+/// [`crate::actors::mid_price::MidPrice`] it receives.
+pub type DrawdownActor = IndicatorActor<Drawdown, DrawdownReport>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn report() {
+    let start = Utc::now();
+    let at = |days| start + Duration::days(days);
+    let mut drawdown = Drawdown::new();
+    let reports: Vec<_> = [10., 12., 9., 11., 6., 12., 13., 12.]
+      .into_iter()
+      .enumerate()
+      .map(|(day, value)| drawdown.update((at(day as i64), value)).unwrap())
+      .collect();
+
+    assert_eq!(reports[1].current, 0.);
+    assert_eq!(reports[1].peak_time, at(1));
+    assert_eq!(reports[2].current, 3.);
+    assert_eq!(reports[2].current_percent, 0.25);
+    assert_eq!(reports[3].max, 3.);
+
+    let worst = &reports[4];
+    assert_eq!((worst.current, worst.max), (6., 6.));
+    assert_eq!(worst.max_percent, 0.5);
+    assert_eq!((worst.peak_time, worst.trough_time), (at(1), at(4)));
+    assert_eq!(worst.duration, Duration::days(3));
+    assert_eq!(worst.recovery, None);
+
+    let recovered = &reports[5];
+    assert_eq!(recovered.current, 0.);
+    assert_eq!(recovered.duration, Duration::zero());
+    assert_eq!(recovered.max_duration, Duration::days(3));
+    assert_eq!(recovered.recovery, Some(Duration::days(1)));
+
+    let last = &reports[7];
+    assert_eq!((last.current, last.max), (1., 6.));
+    assert_eq!(last.peak_time, at(1));
+    assert_eq!(last.recovery, Some(Duration::days(1)));
+  }
+
+  #[test]
+  fn reset() {
+    let now = Utc::now();
+    let mut drawdown = Drawdown::new();
+    drawdown.update((now, 2.));
+    drawdown.update((now, 1.));
+    drawdown.reset();
+    let res = drawdown.update((now, 1.)).unwrap();
+    assert_eq!((res.current, res.max), (0., 0.));
+  }
+}
@@ -0,0 +1,174 @@
+use std::collections::VecDeque;
+
+use super::periods_per_year;
+use super::sharpe::Return;
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::single_pass::RollingVariance;
+use crate::Message;
+use chrono::Duration;
+
+/// Return of the benchmark over the same period as the matching [`Return`].
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct BenchmarkReturn(pub f64);
+
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct InformationRatio(pub f64);
+
+impl From<f64> for InformationRatio {
+  fn from(ratio: f64) -> Self {
+    Self(ratio)
+  }
+}
+
+/// Either side of the pair an [`Information`] ratio compares.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PairedReturn {
+  Portfolio(f64),
+  Benchmark(f64),
+}
+
+impl From<Return> for PairedReturn {
+  fn from(msg: Return) -> Self {
+    Self::Portfolio(msg.0)
+  }
+}
+
+impl From<BenchmarkReturn> for PairedReturn {
+  fn from(msg: BenchmarkReturn) -> Self {
+    Self::Benchmark(msg.0)
+  }
+}
+
+/// Information ratio over the last `window_size` periods: the mean active
+/// return, portfolio minus benchmark, divided by its sample standard
+/// deviation, the tracking error. Portfolio and benchmark returns arrive
+/// separately and are paired in arrival order. At most `window_size`
+/// unpaired returns are kept, the oldest dropped first, so a stream that
+/// runs ahead of the other doesn't grow without bound.
+#[derive(Debug, Clone)]
+pub struct Information {
+  portfolio: VecDeque<f64>,
+  benchmark: VecDeque<f64>,
+  capacity: usize,
+  active: RollingVariance<f64>,
+  annualization: f64,
+}
+
+impl Information {
+  /// `window_size` must be at least two.
+  pub fn new(window_size: u32) -> Option<Self> {
+    if window_size < 2 {
+      None
+    } else {
+      Some(Self {
+        portfolio: VecDeque::new(),
+        benchmark: VecDeque::new(),
+        capacity: window_size as usize,
+        active: RollingVariance::new(window_size as usize)?,
+        annualization: 1.,
+      })
+    }
+  }
+
+  /// Annualizes the ratio for returns sampled every `interval`.
+  pub fn annualized(mut self, interval: Duration) -> Option<Self> {
+    self.annualization = periods_per_year(interval)?.sqrt();
+    Some(self)
+  }
+}
+
+impl Indicator for Information {
+  type Input = PairedReturn;
+  type Output = f64;
+
+  fn update(&mut self, input: PairedReturn) -> Option<f64> {
+    let (pending, r) = match input {
+      PairedReturn::Portfolio(r) => (&mut self.portfolio, r),
+      PairedReturn::Benchmark(r) => (&mut self.benchmark, r),
+    };
+    if pending.len() == self.capacity {
+      pending.pop_front();
+    }
+    pending.push_back(r);
+    if self.portfolio.is_empty() || self.benchmark.is_empty() {
+      return None;
+    }
+    let active = self.portfolio.pop_front()? - self.benchmark.pop_front()?;
+    self.active.push(active);
+
+    if !self.is_ready() {
+      return None;
+    }
+    let stats = self.active.stats();
+    let mean = stats.mean()?;
+    let tracking_error = stats.sample_variance()?.sqrt();
+    (tracking_error > 0.).then(|| mean / tracking_error * self.annualization)
+  }
+
+  fn reset(&mut self) {
+    self.portfolio.clear();
+    self.benchmark.clear();
+    self.active.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.active.is_full()
+  }
+}
+
+/// Publishes the information ratio of the [`Return`]s it receives against
+/// the [`BenchmarkReturn`]s it receives.
+pub type InformationActor = IndicatorActor<Information, InformationRatio>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::Actor;
+
+  #[test]
+  fn pairs_in_arrival_order() {
+    let mut information = Information::new(3).expect("valid args");
+    assert_eq!(information.update(Return(0.02).into()), None);
+    assert_eq!(information.update(Return(0.01).into()), None);
+    assert_eq!(information.update(BenchmarkReturn(0.01).into()), None);
+    assert_eq!(information.update(BenchmarkReturn(0.015).into()), None);
+    assert_eq!(information.update(BenchmarkReturn(0.01).into()), None);
+    let res = information.update(Return(0.03).into()).unwrap();
+    assert!((res - 0.662266178532522).abs() < 1e-9);
+  }
+
+  #[test]
+  fn drops_unpaired_returns_beyond_window() {
+    let mut information = Information::new(2).expect("valid args");
+    for r in [0.5, 0.4, 0.02, 0.03] {
+      assert_eq!(information.update(Return(r).into()), None);
+    }
+    assert_eq!(information.portfolio, [0.02, 0.03]);
+    assert_eq!(information.update(BenchmarkReturn(0.01).into()), None);
+    let res = information.update(BenchmarkReturn(0.01).into()).unwrap();
+    assert!((res - 0.015 / (0.01 / 2f64.sqrt())).abs() < 1e-9);
+    assert!(information.portfolio.is_empty());
+    assert!(information.benchmark.is_empty());
+  }
+
+  #[actix_rt::test]
+  async fn actor_consumes_both_streams() {
+    let (collector, recipient) = collect::<InformationRatio>();
+    let addr = InformationActor::new(
+      Information::new(2).expect("valid args"),
+      vec![recipient],
+    )
+    .start();
+    for (r, b) in [(0.02, 0.01), (0.03, 0.01)] {
+      addr.send(Return(r)).await.unwrap();
+      addr.send(BenchmarkReturn(b)).await.unwrap();
+    }
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(received.len(), 1);
+    assert!((received[0].0 - 0.015 / (0.01 / 2f64.sqrt())).abs() < 1e-9);
+  }
+}
@@ -1,49 +1,34 @@
+pub mod calmar;
+pub mod drawdown;
+pub mod information;
+pub mod omega;
 pub mod sharpe;
+pub mod sortino;
+pub mod value_at_risk;
 
-use actix::{Actor, Context, Handler, Recipient};
+use crate::Message;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 
-use crate::util::Double;
+/// Days per year used to annualize, crypto markets trade every day.
+const DAYS_PER_YEAR: f64 = 365.;
 
-pub struct Drawdown {
-  peak: f64,
-  trough: f64,
-  max_drawdown: f64,
-  subscribers: Vec<Recipient<Double>>,
+/// Number of `interval`s in a year, `None` for non-positive intervals.
+pub(crate) fn periods_per_year(interval: Duration) -> Option<f64> {
+  let millis = Some(interval.num_milliseconds()).filter(|&ms| ms > 0)?;
+  Some(DAYS_PER_YEAR * 86_400_000. / millis as f64)
 }
 
-impl Drawdown {
-  pub fn new(subscribers: Vec<Recipient<Double>>) -> Self {
-    Self {
-      peak: std::f64::NEG_INFINITY,
-      trough: std::f64::INFINITY,
-      max_drawdown: 0.,
-      subscribers,
-    }
-  }
-}
-
-impl Actor for Drawdown {
-  type Context = Context<Self>;
+/// Value of a portfolio or strategy at `timestamp`.
+#[derive(Message, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct Equity {
+  pub value: f64,
+  pub timestamp: DateTime<Utc>,
 }
 
-impl Handler<Double> for Drawdown {
-  type Result = f64;
-
-  fn handle(&mut self, msg: Double, _ctx: &mut Context<Self>) -> Self::Result {
-    if msg.0 > self.peak {
-      self.peak = msg.0;
-      self.trough = self.peak;
-    } else if msg.0 < self.trough {
-      self.trough = msg.0;
-
-      let drawdown = self.peak - self.trough;
-      self.max_drawdown = self.max_drawdown.max(drawdown);
-    }
-
-    for s in &self.subscribers {
-      s.do_send(Double(self.max_drawdown));
-    }
-
-    self.max_drawdown
+impl From<Equity> for (DateTime<Utc>, f64) {
+  fn from(msg: Equity) -> Self {
+    (msg.timestamp, msg.value)
   }
 }
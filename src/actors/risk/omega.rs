@@ -0,0 +1,92 @@
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::ring_buffer::RollingSum;
+use crate::Message;
+
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct OmegaRatio(pub f64);
+
+impl From<f64> for OmegaRatio {
+  fn from(ratio: f64) -> Self {
+    Self(ratio)
+  }
+}
+
+/// Omega ratio over the last `window_size` returns: the sum of gains above
+/// a threshold divided by the sum of losses below it.
+#[derive(Debug, Clone)]
+pub struct Omega {
+  gains: RollingSum,
+  losses: RollingSum,
+  threshold: f64,
+}
+
+impl Omega {
+  pub fn new(window_size: u32) -> Option<Self> {
+    Some(Self {
+      gains: RollingSum::new(window_size as usize)?,
+      losses: RollingSum::new(window_size as usize)?,
+      threshold: 0.,
+    })
+  }
+
+  /// Return per period separating gains from losses, zero by default.
+  pub fn with_threshold(mut self, threshold: f64) -> Self {
+    self.threshold = threshold;
+    self
+  }
+}
+
+impl Indicator for Omega {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, new: f64) -> Option<f64> {
+    let excess = new - self.threshold;
+    self.gains.push(excess.max(0.));
+    self.losses.push((-excess).max(0.));
+
+    if !self.is_ready() {
+      return None;
+    }
+    let losses = self.losses.sum();
+    (losses > 0.).then(|| self.gains.sum() / losses)
+  }
+
+  fn reset(&mut self) {
+    self.gains.clear();
+    self.losses.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.gains.is_full()
+  }
+}
+
+/// Publishes the Omega ratio of the [`super::sharpe::Return`]s it receives.
+pub type OmegaActor = IndicatorActor<Omega, OmegaRatio>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn gains_over_losses() {
+    let mut omega = Omega::new(4).expect("valid args");
+    for r in [0.02, -0.01, 0.03] {
+      assert_eq!(omega.update(r), None);
+    }
+    let res = omega.update(-0.02).unwrap();
+    assert!((res - 5. / 3.).abs() < 1e-9);
+  }
+
+  #[test]
+  fn threshold() {
+    let mut omega = Omega::new(2).expect("valid args").with_threshold(0.01);
+    omega.update(0.02);
+    assert_eq!(omega.update(0.01), None);
+    let res = omega.update(0.);
+    assert!((res.unwrap() - 0.).abs() < 1e-12);
+  }
+}
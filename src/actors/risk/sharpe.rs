@@ -1,136 +1,200 @@
-use crate::algos::single_pass::{mean, mean_centered_sum_squared, sum};
-use crate::{Actor, Context, Handler, Message, MessageResult, Recipient};
-use core::marker::PhantomData;
+use super::periods_per_year;
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::single_pass::{RollingVariance, Welford};
+use crate::Message;
+use chrono::Duration;
 
-#[derive(Message)]
-#[rtype(result = "ReturnResponse")]
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
 pub struct Return(pub f64);
 
-#[derive(Debug)]
-pub enum ReturnResponse {
-  SharpeRatio(Option<f64>),
+impl From<Return> for f64 {
+  fn from(msg: Return) -> Self {
+    msg.0
+  }
+}
+
+impl From<f64> for Return {
+  fn from(value: f64) -> Self {
+    Self(value)
+  }
 }
 
-#[derive(Message)]
-#[rtype(result = "Option<f64>")]
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
 pub struct SharpeRatio(pub f64);
 
-///Computes the mean average return
-///Computes the standard deviation return
-///Calculates the sharpe ratio
+impl From<f64> for SharpeRatio {
+  fn from(ratio: f64) -> Self {
+    Self(ratio)
+  }
+}
+
+#[derive(Debug, Clone)]
+enum Window {
+  Rolling(RollingVariance<f64>),
+  Expanding(Welford<f64>),
+}
+
+/// Sharpe ratio of a return series: the mean excess return over the
+/// risk-free rate, divided by the sample standard deviation of the excess
+/// returns, either over a sliding window or over every return seen
+/// (expanding). Optionally annualized by `sqrt(periods per year)`.
+#[derive(Debug, Clone)]
 pub struct Sharpe {
-  mean: f64,
-  s_squared: f64,
-  cold_count: u32,
-  _p: PhantomData<f64>,
-  init_buffer: Option<Vec<f64>>,
-
-  subscribers: Vec<Recipient<SharpeRatio>>,
-  window_size: u32,
+  window: Window,
+  risk_free_rate: f64,
+  annualization: f64,
 }
 
 impl Sharpe {
-  pub fn new(
-    subscribers: Vec<Recipient<SharpeRatio>>,
-    window_size: u32,
-  ) -> Option<Self> {
-    if window_size < 1 {
+  /// Sharpe ratio over the last `window_size` returns, at least two.
+  pub fn new(window_size: u32) -> Option<Self> {
+    if window_size < 2 {
       None
     } else {
-      Some(Sharpe {
-        mean: 0.,
-        s_squared: 0.,
-        cold_count: 0,
-        _p: PhantomData,
-        init_buffer: None,
-        subscribers,
-        window_size,
-      })
+      Some(Self::with_window(Window::Rolling(RollingVariance::new(
+        window_size as usize,
+      )?)))
     }
   }
 
-  pub fn update(&mut self, new: f64) -> Option<f64> {
-    match self.cold_count {
-      count if count > self.window_size => {
-        let mean_1 = self.mean;
-        self.mean = mean(self.mean, new, self.window_size);
-        self.s_squared += (new - mean_1) * (new - self.mean);
-        Some(
-          self.mean
-            / (f64::sqrt(self.s_squared) / ((self.window_size - 1) as f64)),
-        )
-      }
-      count if count == self.window_size => {
-        let x = self.init_buffer.take().expect("returns");
-        self.mean = sum(x.iter().copied()) / count as f64;
-        self.s_squared = mean_centered_sum_squared(x.into_iter(), self.mean);
-        self.cold_count = count + 1;
-        Some(
-          self.mean
-            / (f64::sqrt(self.s_squared) / ((self.window_size - 1) as f64)),
-        )
-      }
-      //stdev
-      count => {
-        let mut buffer = self
-          .init_buffer
-          .take()
-          .unwrap_or_else(|| Vec::with_capacity(self.window_size as usize));
-        buffer.push(new);
-        self.init_buffer.replace(buffer);
-        self.cold_count = count + 1;
-        None
-      }
+  /// Sharpe ratio over every return seen so far.
+  pub fn expanding() -> Self {
+    Self::with_window(Window::Expanding(Welford::default()))
+  }
+
+  fn with_window(window: Window) -> Self {
+    Self {
+      window,
+      risk_free_rate: 0.,
+      annualization: 1.,
     }
   }
-}
 
-impl Actor for Sharpe {
-  type Context = Context<Self>;
+  /// Risk-free return per period, in the same unit as the returns.
+  pub fn with_risk_free_rate(mut self, risk_free_rate: f64) -> Self {
+    self.risk_free_rate = risk_free_rate;
+    self
+  }
+
+  /// Annualizes the ratio for returns sampled every `interval`.
+  pub fn annualized(mut self, interval: Duration) -> Option<Self> {
+    self.annualization = periods_per_year(interval)?.sqrt();
+    Some(self)
+  }
+
+  fn stats(&self) -> &Welford<f64> {
+    match &self.window {
+      Window::Rolling(rolling) => rolling.stats(),
+      Window::Expanding(welford) => welford,
+    }
+  }
 }
 
-impl Handler<Return> for Sharpe {
-  //a sharpe ratio
-  type Result = MessageResult<Return>;
+impl Indicator for Sharpe {
+  type Input = f64;
+  type Output = f64;
 
-  fn handle(&mut self, msg: Return, _ctx: &mut Self::Context) -> Self::Result {
-    if let Some(ratio) = self.update(msg.0) {
-      for s in &self.subscribers {
-        s.do_send(SharpeRatio(ratio));
+  fn update(&mut self, new: f64) -> Option<f64> {
+    let excess = new - self.risk_free_rate;
+    match &mut self.window {
+      Window::Rolling(rolling) => {
+        rolling.push(excess);
       }
-      return MessageResult(ReturnResponse::SharpeRatio(Some(ratio)));
+      Window::Expanding(welford) => welford.push(excess),
+    }
+
+    if !self.is_ready() {
+      return None;
+    }
+    let stats = self.stats();
+    let mean = stats.mean()?;
+    let std = stats.sample_variance()?.sqrt();
+    (std > 0.).then(|| mean / std * self.annualization)
+  }
+
+  fn reset(&mut self) {
+    match &mut self.window {
+      Window::Rolling(rolling) => rolling.clear(),
+      Window::Expanding(welford) => *welford = Welford::default(),
+    }
+  }
+
+  fn is_ready(&self) -> bool {
+    match &self.window {
+      Window::Rolling(rolling) => rolling.is_full(),
+      Window::Expanding(welford) => welford.count() > 1,
     }
-    MessageResult(ReturnResponse::SharpeRatio(None))
   }
 }
 
-//test mean of 0;
-//test window_size upper bound
+/// Publishes the Sharpe ratio of the [`Return`]s it receives.
+pub type SharpeActor = IndicatorActor<Sharpe, SharpeRatio>;
 
 #[cfg(test)]
 pub mod tests {
-  use crate::assert_matches;
-
   use super::*;
 
-  #[actix_rt::test]
-  async fn divisor_is_one() {
-    let sharpe_actor = Sharpe::new(vec![], 1).expect("valid args");
-    let addr1 = sharpe_actor.start();
-    assert_matches!(addr1.send(Return(1.)).await.unwrap(),ReturnResponse::SharpeRatio(s) =>{
-      assert_eq!(s,None);
-    });
-  }
-
-  #[actix_rt::test]
-  async fn positive() {
-    let sharpe_actor = Sharpe::new(vec![], 1).expect("valid args");
-    let addr1 = sharpe_actor.start();
-    assert_matches!(addr1.send(Return(1.)).await.unwrap(),ReturnResponse::SharpeRatio(s) =>{
-      assert_eq!(s,None);
-    });
-    assert_matches!(addr1.send(Return(2.)).await.unwrap(),ReturnResponse::SharpeRatio(s) =>{
-      assert_eq!(s,Some(3.));
-    });
+  fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("a ratio");
+    assert!(
+      (actual - expected).abs() < 1e-9,
+      "{actual} is not close to {expected}"
+    );
+  }
+
+  #[test]
+  fn window_lower_bound() {
+    assert!(Sharpe::new(0).is_none());
+    assert!(Sharpe::new(1).is_none());
+    assert!(Sharpe::new(2).is_some());
+  }
+
+  #[test]
+  fn rolling() {
+    let mut sharpe = Sharpe::new(4).expect("valid args");
+    assert_eq!(sharpe.update(0.01), None);
+    assert_eq!(sharpe.update(0.02), None);
+    assert_eq!(sharpe.update(-0.01), None);
+    assert_close(sharpe.update(0.03), 0.7319250547114);
+    // the first return leaves the window
+    assert_close(sharpe.update(0.005), 0.6428571428571429);
+  }
+
+  #[test]
+  fn expanding() {
+    let mut sharpe = Sharpe::expanding();
+    assert_eq!(sharpe.update(0.01), None);
+    for r in [0.02, -0.01, 0.03] {
+      sharpe.update(r);
+    }
+    assert_close(sharpe.update(0.005), 0.7253185207353657);
+  }
+
+  #[test]
+  fn risk_free_and_annualized() {
+    let mut sharpe = Sharpe::new(4)
+      .expect("valid args")
+      .with_risk_free_rate(0.001)
+      .annualized(Duration::days(1))
+      .expect("valid interval");
+    let res = [0.01, 0.02, -0.01, 0.03].map(|r| sharpe.update(r));
+    assert_close(res[3], 12.864735853154102);
+  }
+
+  #[test]
+  fn mean_of_zero() {
+    let mut sharpe = Sharpe::new(2).expect("valid args");
+    sharpe.update(-1.);
+    assert_eq!(sharpe.update(1.), Some(0.));
+  }
+
+  #[test]
+  fn flat_returns_have_no_ratio() {
+    let mut sharpe = Sharpe::new(2).expect("valid args");
+    sharpe.update(1.);
+    assert_eq!(sharpe.update(1.), None);
   }
 }
@@ -0,0 +1,103 @@
+use super::periods_per_year;
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::ring_buffer::RollingSum;
+use crate::Message;
+use chrono::Duration;
+
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct SortinoRatio(pub f64);
+
+impl From<f64> for SortinoRatio {
+  fn from(ratio: f64) -> Self {
+    Self(ratio)
+  }
+}
+
+/// Sortino ratio over the last `window_size` returns: the mean excess
+/// return over a target, divided by the downside deviation, the root mean
+/// square of the returns falling short of the target.
+#[derive(Debug, Clone)]
+pub struct Sortino {
+  excess: RollingSum,
+  downside: RollingSum,
+  target: f64,
+  annualization: f64,
+}
+
+impl Sortino {
+  pub fn new(window_size: u32) -> Option<Self> {
+    Some(Self {
+      excess: RollingSum::new(window_size as usize)?,
+      downside: RollingSum::new(window_size as usize)?,
+      target: 0.,
+      annualization: 1.,
+    })
+  }
+
+  /// Minimum acceptable return per period, zero by default.
+  pub fn with_target(mut self, target: f64) -> Self {
+    self.target = target;
+    self
+  }
+
+  /// Annualizes the ratio for returns sampled every `interval`.
+  pub fn annualized(mut self, interval: Duration) -> Option<Self> {
+    self.annualization = periods_per_year(interval)?.sqrt();
+    Some(self)
+  }
+}
+
+impl Indicator for Sortino {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, new: f64) -> Option<f64> {
+    let excess = new - self.target;
+    self.excess.push(excess);
+    self.downside.push(excess.min(0.).powi(2));
+
+    let mean = self.excess.mean()?;
+    let downside_deviation = self.downside.mean()?.max(0.).sqrt();
+    (downside_deviation > 0.)
+      .then(|| mean / downside_deviation * self.annualization)
+  }
+
+  fn reset(&mut self) {
+    self.excess.clear();
+    self.downside.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.excess.is_full()
+  }
+}
+
+/// Publishes the Sortino ratio of the [`super::sharpe::Return`]s it
+/// receives.
+pub type SortinoActor = IndicatorActor<Sortino, SortinoRatio>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn downside_deviation() {
+    let mut sortino = Sortino::new(4).expect("valid args");
+    for r in [0.02, -0.01, 0.03] {
+      assert_eq!(sortino.update(r), None);
+    }
+    let res = sortino.update(-0.02).unwrap();
+    assert!((res - 0.44721359549995787).abs() < 1e-9);
+    let res = sortino.update(0.01).unwrap();
+    assert!((res - 0.22360679774997894).abs() < 1e-9);
+  }
+
+  #[test]
+  fn no_downside_has_no_ratio() {
+    let mut sortino = Sortino::new(2).expect("valid args").with_target(-1.);
+    sortino.update(0.01);
+    assert_eq!(sortino.update(0.02), None);
+  }
+}
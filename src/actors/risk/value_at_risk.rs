@@ -0,0 +1,236 @@
+use crate::actors::indicator::IndicatorActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::normal;
+use crate::algos::ring_buffer::RingBuffer;
+use crate::algos::single_pass::RollingVariance;
+use crate::Message;
+
+/// Value-at-Risk and Expected Shortfall of one period's return at a given
+/// confidence, both as positive losses.
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
+pub struct TailRisk {
+  pub value_at_risk: f64,
+  pub expected_shortfall: f64,
+}
+
+fn valid_confidence(confidence: f64) -> Option<f64> {
+  (confidence > 0. && confidence < 1.).then_some(confidence)
+}
+
+/// Tail risk of a normal distribution with the given moments.
+fn gaussian(mean: f64, std: f64, confidence: f64) -> Option<TailRisk> {
+  let z = normal::quantile(confidence)?;
+  Some(TailRisk {
+    value_at_risk: z * std - mean,
+    expected_shortfall: std * normal::pdf(z) / (1. - confidence) - mean,
+  })
+}
+
+/// Historical simulation over the last `window_size` returns: VaR is the
+/// loss exceeded by the worst `1 - confidence` of them, ES the mean loss of
+/// those worst returns.
+#[derive(Debug, Clone)]
+pub struct HistoricalVar {
+  window: RingBuffer<f64>,
+  confidence: f64,
+}
+
+impl HistoricalVar {
+  /// `confidence` must be in `(0, 1)`, e.g. `0.99`.
+  pub fn new(window_size: usize, confidence: f64) -> Option<Self> {
+    Some(Self {
+      window: RingBuffer::new(window_size)?,
+      confidence: valid_confidence(confidence)?,
+    })
+  }
+}
+
+impl Indicator for HistoricalVar {
+  type Input = f64;
+  type Output = TailRisk;
+
+  fn update(&mut self, new: f64) -> Option<TailRisk> {
+    self.window.push(new);
+    if !self.is_ready() {
+      return None;
+    }
+
+    let mut sorted: Vec<f64> = self.window.iter().copied().collect();
+    sorted.sort_by(f64::total_cmp);
+    let n = sorted.len() as f64;
+    // guards against 1 - confidence rounding up past an integer
+    let tail = ((1. - self.confidence) * n - 1e-9).ceil().max(1.) as usize;
+    let worst = &sorted[..tail];
+    Some(TailRisk {
+      value_at_risk: -worst[tail - 1],
+      expected_shortfall: -worst.iter().sum::<f64>() / tail as f64,
+    })
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+/// Gaussian tail risk from the mean and sample standard deviation of the
+/// last `window_size` returns.
+#[derive(Debug, Clone)]
+pub struct ParametricVar {
+  window: RollingVariance<f64>,
+  confidence: f64,
+}
+
+impl ParametricVar {
+  /// `window_size` must be at least two, `confidence` in `(0, 1)`.
+  pub fn new(window_size: usize, confidence: f64) -> Option<Self> {
+    if window_size < 2 {
+      None
+    } else {
+      Some(Self {
+        window: RollingVariance::new(window_size)?,
+        confidence: valid_confidence(confidence)?,
+      })
+    }
+  }
+}
+
+impl Indicator for ParametricVar {
+  type Input = f64;
+  type Output = TailRisk;
+
+  fn update(&mut self, new: f64) -> Option<TailRisk> {
+    self.window.push(new);
+    if !self.is_ready() {
+      return None;
+    }
+    let stats = self.window.stats();
+    gaussian(
+      stats.mean()?,
+      stats.sample_variance()?.sqrt(),
+      self.confidence,
+    )
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+/// RiskMetrics style Gaussian tail risk: zero mean and the exponentially
+/// weighted variance `λ σ² + (1 - λ) r²`, seeded with the first squared
+/// return.
+#[derive(Debug, Clone)]
+pub struct EwmaVar {
+  lambda: f64,
+  variance: Option<f64>,
+  confidence: f64,
+}
+
+impl EwmaVar {
+  /// `lambda` and `confidence` must be in `(0, 1)`, RiskMetrics uses a
+  /// `lambda` of `0.94` for daily returns.
+  pub fn new(lambda: f64, confidence: f64) -> Option<Self> {
+    Some(Self {
+      lambda: valid_confidence(lambda)?,
+      variance: None,
+      confidence: valid_confidence(confidence)?,
+    })
+  }
+}
+
+impl Indicator for EwmaVar {
+  type Input = f64;
+  type Output = TailRisk;
+
+  fn update(&mut self, new: f64) -> Option<TailRisk> {
+    let variance = match self.variance {
+      Some(last) => self.lambda * last + (1. - self.lambda) * new * new,
+      None => new * new,
+    };
+    self.variance = Some(variance);
+    gaussian(0., variance.sqrt(), self.confidence)
+  }
+
+  fn reset(&mut self) {
+    self.variance = None;
+  }
+
+  fn is_ready(&self) -> bool {
+    self.variance.is_some()
+  }
+}
+
+/// Publishes tail risk of the [`super::sharpe::Return`]s it receives.
+pub type ValueAtRiskActor<V> = IndicatorActor<V, TailRisk>;
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RETURNS: [f64; 10] = [
+    0.01, -0.02, 0.015, -0.05, 0.03, -0.01, 0.02, -0.03, 0.005, 0.,
+  ];
+
+  fn last(
+    indicator: &mut impl Indicator<Input = f64, Output = TailRisk>,
+  ) -> TailRisk {
+    RETURNS
+      .iter()
+      .map(|&r| indicator.update(r))
+      .last()
+      .flatten()
+      .expect("warm")
+  }
+
+  fn assert_close(actual: f64, expected: f64) {
+    assert!(
+      (actual - expected).abs() < 1e-9,
+      "{actual} is not close to {expected}"
+    );
+  }
+
+  #[test]
+  fn invalid_confidence() {
+    assert!(HistoricalVar::new(10, 1.).is_none());
+    assert!(ParametricVar::new(10, 0.).is_none());
+    assert!(ParametricVar::new(1, 0.95).is_none());
+    assert!(EwmaVar::new(0.94, 1.5).is_none());
+  }
+
+  #[test]
+  fn historical() {
+    let mut var = HistoricalVar::new(10, 0.8).unwrap();
+    assert_eq!(var.update(0.), None);
+    let res = last(&mut var);
+    assert_close(res.value_at_risk, 0.03);
+    assert_close(res.expected_shortfall, 0.04);
+
+    let mut var = HistoricalVar::new(10, 0.7).unwrap();
+    let res = last(&mut var);
+    assert_close(res.value_at_risk, 0.02);
+    assert_close(res.expected_shortfall, 0.1 / 3.);
+  }
+
+  #[test]
+  fn parametric() {
+    let res = last(&mut ParametricVar::new(10, 0.95).unwrap());
+    assert_close(res.value_at_risk, 0.04351373876622451);
+    assert_close(res.expected_shortfall, 0.05380586288275674);
+  }
+
+  #[test]
+  fn ewma() {
+    let res = last(&mut EwmaVar::new(0.94, 0.95).unwrap());
+    assert_close(res.value_at_risk, 0.028372546295287293);
+    assert_close(res.expected_shortfall, 0.03558031770483688);
+  }
+}
@@ -1 +1,5 @@
+pub mod indicators;
+pub mod moving_average;
+pub mod normal;
+pub mod ring_buffer;
 pub mod single_pass;
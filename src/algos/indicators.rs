@@ -0,0 +1,31 @@
+pub mod atr;
+pub mod bollinger;
+pub mod macd;
+pub mod rate_of_change;
+pub mod rsi;
+pub mod stochastic;
+pub mod z_score;
+
+/// Incremental computation fed one input at a time, in O(1) per update.
+pub trait Indicator {
+  type Input;
+  type Output;
+
+  /// Feeds the next input, returning the new value once enough inputs have
+  /// been seen.
+  fn update(&mut self, input: Self::Input) -> Option<Self::Output>;
+
+  /// Forgets every input seen so far.
+  fn reset(&mut self);
+
+  /// Whether enough inputs have been seen for `update` to return values.
+  fn is_ready(&self) -> bool;
+}
+
+/// The part of a bar that range based indicators look at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HighLowClose {
+  pub high: f64,
+  pub low: f64,
+  pub close: f64,
+}
@@ -0,0 +1,79 @@
+use super::{HighLowClose, Indicator};
+
+/// Average true range with Wilder smoothing, seeded with the plain average
+/// of the first `period` true ranges.
+#[derive(Debug, Clone)]
+pub struct Atr {
+  period: usize,
+  count: usize,
+  previous_close: Option<f64>,
+  value: f64,
+}
+
+impl Atr {
+  pub fn new(period: usize) -> Option<Self> {
+    if period < 1 {
+      None
+    } else {
+      Some(Self {
+        period,
+        count: 0,
+        previous_close: None,
+        value: 0.,
+      })
+    }
+  }
+}
+
+impl Indicator for Atr {
+  type Input = HighLowClose;
+  type Output = f64;
+
+  fn update(&mut self, bar: HighLowClose) -> Option<f64> {
+    let range = bar.high - bar.low;
+    let true_range = match self.previous_close.replace(bar.close) {
+      Some(close) => range
+        .max((bar.high - close).abs())
+        .max((bar.low - close).abs()),
+      None => range,
+    };
+    let period = self.period as f64;
+
+    if self.count < self.period {
+      self.value += true_range / period;
+      self.count += 1;
+      (self.count == self.period).then_some(self.value)
+    } else {
+      self.value = (self.value * (period - 1.) + true_range) / period;
+      Some(self.value)
+    }
+  }
+
+  fn reset(&mut self) {
+    self.count = 0;
+    self.previous_close = None;
+    self.value = 0.;
+  }
+
+  fn is_ready(&self) -> bool {
+    self.count == self.period
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn bar(high: f64, low: f64, close: f64) -> HighLowClose {
+    HighLowClose { high, low, close }
+  }
+
+  #[test]
+  fn gaps_count_towards_the_range() {
+    let mut atr = Atr::new(2).unwrap();
+    assert_eq!(atr.update(bar(2., 1., 2.)), None);
+    // gap up: the true range reaches back to the previous close
+    assert_eq!(atr.update(bar(5., 4., 4.)), Some(2.));
+    assert_eq!(atr.update(bar(4., 3., 3.)), Some(1.5));
+  }
+}
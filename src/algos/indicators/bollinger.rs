@@ -0,0 +1,72 @@
+use super::Indicator;
+use crate::algos::single_pass::RollingVariance;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bands {
+  pub lower: f64,
+  pub middle: f64,
+  pub upper: f64,
+}
+
+/// Bollinger bands: the simple average over `period` prices, plus and minus
+/// `width` population standard deviations.
+#[derive(Debug, Clone)]
+pub struct BollingerBands {
+  window: RollingVariance<f64>,
+  width: f64,
+}
+
+impl BollingerBands {
+  pub fn new(period: usize, width: f64) -> Option<Self> {
+    Some(Self {
+      window: RollingVariance::new(period)?,
+      width,
+    })
+  }
+}
+
+impl Indicator for BollingerBands {
+  type Input = f64;
+  type Output = Bands;
+
+  fn update(&mut self, price: f64) -> Option<Bands> {
+    self.window.push(price);
+    if !self.window.is_full() {
+      return None;
+    }
+    let stats = self.window.stats();
+    let middle = stats.mean()?;
+    let offset = self.width * stats.variance()?.sqrt();
+    Some(Bands {
+      lower: middle - offset,
+      middle,
+      upper: middle + offset,
+    })
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn bands() {
+    let mut bands = BollingerBands::new(4, 2.).unwrap();
+    for price in [2., 4., 4.] {
+      assert_eq!(bands.update(price), None);
+    }
+    // mean 4, population standard deviation sqrt(2)
+    let res = bands.update(6.).unwrap();
+    assert_eq!(res.middle, 4.);
+    assert!((res.upper - (4. + 2. * 2f64.sqrt())).abs() < 1e-9);
+    assert!((res.lower - (4. - 2. * 2f64.sqrt())).abs() < 1e-9);
+  }
+}
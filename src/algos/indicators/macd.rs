@@ -0,0 +1,103 @@
+use super::Indicator;
+use crate::algos::moving_average::Ema;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MacdOutput {
+  pub macd: f64,
+  pub signal: f64,
+  pub histogram: f64,
+}
+
+/// Moving average convergence divergence: the spread between a fast and a
+/// slow EMA, smoothed by a signal EMA.
+#[derive(Debug, Clone)]
+pub struct Macd {
+  fast: Ema,
+  slow: Ema,
+  signal: Ema,
+}
+
+impl Macd {
+  /// `fast` must be shorter than `slow`, all periods non-zero.
+  pub fn new(fast: usize, slow: usize, signal: usize) -> Option<Self> {
+    if fast >= slow {
+      None
+    } else {
+      Some(Self {
+        fast: Ema::new(fast)?,
+        slow: Ema::new(slow)?,
+        signal: Ema::new(signal)?,
+      })
+    }
+  }
+}
+
+impl Default for Macd {
+  /// The usual 12/26/9 configuration.
+  fn default() -> Self {
+    Self::new(12, 26, 9).expect("valid periods")
+  }
+}
+
+impl Indicator for Macd {
+  type Input = f64;
+  type Output = MacdOutput;
+
+  fn update(&mut self, price: f64) -> Option<MacdOutput> {
+    let fast = self.fast.push(price);
+    let slow = self.slow.push(price)?;
+    let macd = fast? - slow;
+    let signal = self.signal.push(macd)?;
+    Some(MacdOutput {
+      macd,
+      signal,
+      histogram: macd - signal,
+    })
+  }
+
+  fn reset(&mut self) {
+    self.fast.reset();
+    self.slow.reset();
+    self.signal.reset();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.signal.is_ready()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn invalid_periods() {
+    assert!(Macd::new(3, 3, 2).is_none());
+    assert!(Macd::new(0, 3, 2).is_none());
+    assert!(Macd::new(2, 3, 0).is_none());
+  }
+
+  #[test]
+  fn constant_price_has_no_spread() {
+    let mut macd = Macd::new(2, 3, 2).unwrap();
+    let res: Vec<_> = (0..5).map(|_| macd.update(7.)).collect();
+    assert_eq!(&res[..3], &[None, None, None]);
+    assert_eq!(
+      res[3],
+      Some(MacdOutput {
+        macd: 0.,
+        signal: 0.,
+        histogram: 0.
+      })
+    );
+  }
+
+  #[test]
+  fn rising_price_has_positive_spread() {
+    let mut macd = Macd::default();
+    let last = (0..40).filter_map(|i| macd.update(f64::from(i))).last();
+    let last = last.expect("warm");
+    assert!(last.macd > 0.);
+    assert!(last.signal > 0.);
+  }
+}
@@ -0,0 +1,59 @@
+use super::Indicator;
+use crate::algos::ring_buffer::RingBuffer;
+
+/// Percentage change over the last `period` values. Skipped while the
+/// reference value is zero.
+#[derive(Debug, Clone)]
+pub struct RateOfChange {
+  window: RingBuffer<f64>,
+}
+
+impl RateOfChange {
+  pub fn new(period: usize) -> Option<Self> {
+    if period < 1 {
+      None
+    } else {
+      Some(Self {
+        window: RingBuffer::new(period + 1)?,
+      })
+    }
+  }
+}
+
+impl Indicator for RateOfChange {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.window.push(value);
+    if !self.window.is_full() {
+      return None;
+    }
+    let reference = *self.window.oldest()?;
+    (reference != 0.).then(|| 100. * (value - reference) / reference)
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rate_of_change() {
+    assert!(RateOfChange::new(0).is_none());
+    let mut roc = RateOfChange::new(2).unwrap();
+    assert_eq!(roc.update(0.), None);
+    assert_eq!(roc.update(4.), None);
+    assert_eq!(roc.update(5.), None);
+    assert_eq!(roc.update(6.), Some(50.));
+    assert_eq!(roc.update(4.), Some(-20.));
+  }
+}
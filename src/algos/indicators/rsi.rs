@@ -0,0 +1,102 @@
+use super::Indicator;
+
+/// Relative strength index with Wilder smoothing, in `[0, 100]`.
+#[derive(Debug, Clone)]
+pub struct Rsi {
+  period: usize,
+  count: usize,
+  last: Option<f64>,
+  average_gain: f64,
+  average_loss: f64,
+}
+
+impl Rsi {
+  pub fn new(period: usize) -> Option<Self> {
+    if period < 1 {
+      None
+    } else {
+      Some(Self {
+        period,
+        count: 0,
+        last: None,
+        average_gain: 0.,
+        average_loss: 0.,
+      })
+    }
+  }
+}
+
+impl Indicator for Rsi {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, price: f64) -> Option<f64> {
+    let last = self.last.replace(price)?;
+    let change = price - last;
+    let (gain, loss) = (change.max(0.), (-change).max(0.));
+    let period = self.period as f64;
+
+    if self.count < self.period {
+      // seed with the plain average of the first `period` changes
+      self.average_gain += gain / period;
+      self.average_loss += loss / period;
+      self.count += 1;
+      if self.count < self.period {
+        return None;
+      }
+    } else {
+      self.average_gain = (self.average_gain * (period - 1.) + gain) / period;
+      self.average_loss = (self.average_loss * (period - 1.) + loss) / period;
+    }
+
+    Some(if self.average_loss > 0. {
+      100. - 100. / (1. + self.average_gain / self.average_loss)
+    } else if self.average_gain > 0. {
+      100.
+    } else {
+      50.
+    })
+  }
+
+  fn reset(&mut self) {
+    self.count = 0;
+    self.last = None;
+    self.average_gain = 0.;
+    self.average_loss = 0.;
+  }
+
+  fn is_ready(&self) -> bool {
+    self.count == self.period
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn warm_up_and_extremes() {
+    let mut rsi = Rsi::new(2).unwrap();
+    assert_eq!(rsi.update(1.), None);
+    assert_eq!(rsi.update(2.), None);
+    assert_eq!(rsi.update(3.), Some(100.));
+    assert_eq!(rsi.update(3.), Some(100.));
+
+    let mut rsi = Rsi::new(2).unwrap();
+    for price in [3., 2.] {
+      rsi.update(price);
+    }
+    assert_eq!(rsi.update(1.), Some(0.));
+  }
+
+  #[test]
+  fn wilder_smoothing() {
+    let mut rsi = Rsi::new(2).unwrap();
+    for price in [10., 12., 11.] {
+      rsi.update(price);
+    }
+    // seed: gain 1, loss 0.5; then a gain of 2 -> gain 1.5, loss 0.25
+    let value = rsi.update(13.).unwrap();
+    assert!((value - (100. - 100. / 7.)).abs() < 1e-9);
+  }
+}
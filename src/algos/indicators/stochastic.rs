@@ -0,0 +1,117 @@
+use std::collections::VecDeque;
+
+use super::{HighLowClose, Indicator};
+use crate::algos::moving_average::Sma;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StochasticOutput {
+  pub k: f64,
+  pub d: f64,
+}
+
+/// Stochastic oscillator: where the close sits within the high-low range of
+/// the last `k_period` bars (%K), and its `d_period` simple average (%D).
+#[derive(Debug, Clone)]
+pub struct Stochastic {
+  k_period: usize,
+  index: usize,
+  // monotonic deques of (index, value), the front is the window extremum
+  highs: VecDeque<(usize, f64)>,
+  lows: VecDeque<(usize, f64)>,
+  d: Sma,
+}
+
+impl Stochastic {
+  pub fn new(k_period: usize, d_period: usize) -> Option<Self> {
+    if k_period < 1 {
+      None
+    } else {
+      Some(Self {
+        k_period,
+        index: 0,
+        highs: VecDeque::with_capacity(k_period),
+        lows: VecDeque::with_capacity(k_period),
+        d: Sma::new(d_period)?,
+      })
+    }
+  }
+}
+
+fn push_extremum(
+  deque: &mut VecDeque<(usize, f64)>,
+  index: usize,
+  value: f64,
+  dominates: impl Fn(f64, f64) -> bool,
+) {
+  while matches!(deque.back(), Some(&(_, v)) if dominates(value, v)) {
+    deque.pop_back();
+  }
+  deque.push_back((index, value));
+}
+
+impl Indicator for Stochastic {
+  type Input = HighLowClose;
+  type Output = StochasticOutput;
+
+  fn update(&mut self, bar: HighLowClose) -> Option<StochasticOutput> {
+    let index = self.index;
+    self.index += 1;
+
+    push_extremum(&mut self.highs, index, bar.high, |new, old| new >= old);
+    push_extremum(&mut self.lows, index, bar.low, |new, old| new <= old);
+    for deque in [&mut self.highs, &mut self.lows] {
+      while matches!(deque.front(), Some(&(i, _)) if i + self.k_period <= index)
+      {
+        deque.pop_front();
+      }
+    }
+
+    if self.index < self.k_period {
+      return None;
+    }
+
+    let high = self.highs.front()?.1;
+    let low = self.lows.front()?.1;
+    let k = if high > low {
+      100. * (bar.close - low) / (high - low)
+    } else {
+      50.
+    };
+    let d = self.d.push(k)?;
+    Some(StochasticOutput { k, d })
+  }
+
+  fn reset(&mut self) {
+    self.index = 0;
+    self.highs.clear();
+    self.lows.clear();
+    self.d.reset();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.d.is_ready()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn bar(high: f64, low: f64, close: f64) -> HighLowClose {
+    HighLowClose { high, low, close }
+  }
+
+  #[test]
+  fn oscillator() {
+    let mut stochastic = Stochastic::new(3, 2).unwrap();
+    assert_eq!(stochastic.update(bar(10., 5., 8.)), None);
+    assert_eq!(stochastic.update(bar(9., 6., 7.)), None);
+    // %K 20, %D not warm yet
+    assert_eq!(stochastic.update(bar(8., 7., 6.)), None);
+    // the first bar left the window: range [6, 9]
+    assert_eq!(
+      stochastic.update(bar(8., 7., 9.)),
+      Some(StochasticOutput { k: 100., d: 60. })
+    );
+  }
+}
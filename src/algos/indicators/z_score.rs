@@ -0,0 +1,55 @@
+use super::Indicator;
+use crate::algos::single_pass::RollingVariance;
+
+/// Distance of the latest value from the mean of the last `period` values,
+/// in population standard deviations. Zero when the window is flat.
+#[derive(Debug, Clone)]
+pub struct ZScore {
+  window: RollingVariance<f64>,
+}
+
+impl ZScore {
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      window: RollingVariance::new(period)?,
+    })
+  }
+}
+
+impl Indicator for ZScore {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.window.push(value);
+    if !self.window.is_full() {
+      return None;
+    }
+    let stats = self.window.stats();
+    let mean = stats.mean()?;
+    let std = stats.variance()?.sqrt();
+    Some(if std > 0. { (value - mean) / std } else { 0. })
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn z_score() {
+    let mut z = ZScore::new(2).unwrap();
+    assert_eq!(z.update(1.), None);
+    assert_eq!(z.update(3.), Some(1.));
+    assert_eq!(z.update(1.), Some(-1.));
+    assert_eq!(z.update(1.), Some(0.));
+  }
+}
@@ -0,0 +1,465 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::algos::indicators::Indicator;
+use crate::algos::ring_buffer::{RingBuffer, RollingSum};
+use crate::algos::single_pass::KahanSum;
+
+/// Simple moving average over the last `period` observations.
+#[derive(Debug, Clone)]
+pub struct Sma {
+  window: RollingSum,
+}
+
+impl Sma {
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      window: RollingSum::new(period)?,
+    })
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    self.window.push(value);
+    self.window.mean()
+  }
+}
+
+impl Indicator for Sma {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+/// Exponential moving average with smoothing `2 / (period + 1)`, seeded with
+/// the simple average of the first `period` observations.
+#[derive(Debug, Clone)]
+pub struct Ema {
+  alpha: f64,
+  period: usize,
+  count: usize,
+  seed: KahanSum,
+  value: Option<f64>,
+}
+
+impl Ema {
+  pub fn new(period: usize) -> Option<Self> {
+    if period < 1 {
+      None
+    } else {
+      Some(Self {
+        alpha: 2. / (period as f64 + 1.),
+        period,
+        count: 0,
+        seed: KahanSum::default(),
+        value: None,
+      })
+    }
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    self.value = match self.value {
+      Some(last) => Some(last + self.alpha * (value - last)),
+      None => {
+        self.seed.add(value);
+        self.count += 1;
+        (self.count == self.period)
+          .then(|| self.seed.value() / self.period as f64)
+      }
+    };
+    self.value
+  }
+
+  pub fn value(&self) -> Option<f64> {
+    self.value
+  }
+}
+
+impl Indicator for Ema {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.count = 0;
+    self.seed = KahanSum::default();
+    self.value = None;
+  }
+
+  fn is_ready(&self) -> bool {
+    self.value.is_some()
+  }
+}
+
+/// Linearly weighted moving average, the newest observation weighing
+/// `period` and the oldest `1`.
+#[derive(Debug, Clone)]
+pub struct Wma {
+  window: RingBuffer<f64>,
+  sum: KahanSum,
+  weighted_sum: KahanSum,
+}
+
+impl Wma {
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      window: RingBuffer::new(period)?,
+      sum: KahanSum::default(),
+      weighted_sum: KahanSum::default(),
+    })
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    let n = self.window.capacity() as f64;
+    if self.window.is_full() {
+      // shifting every weight down by one subtracts the plain sum
+      self.weighted_sum.add(n * value);
+      self.weighted_sum.sub(self.sum.value());
+      self.sum.add(value);
+      if let Some(evicted) = self.window.push(value) {
+        self.sum.sub(evicted);
+      }
+    } else {
+      self.sum.add(value);
+      self.window.push(value);
+      self.weighted_sum.add(self.window.len() as f64 * value);
+    }
+
+    self
+      .window
+      .is_full()
+      .then(|| self.weighted_sum.value() / (n * (n + 1.) / 2.))
+  }
+}
+
+impl Indicator for Wma {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.window.clear();
+    self.sum = KahanSum::default();
+    self.weighted_sum = KahanSum::default();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.window.is_full()
+  }
+}
+
+/// Double exponential moving average, `2 * EMA - EMA(EMA)`.
+#[derive(Debug, Clone)]
+pub struct Dema {
+  ema: Ema,
+  ema_of_ema: Ema,
+}
+
+impl Dema {
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      ema: Ema::new(period)?,
+      ema_of_ema: Ema::new(period)?,
+    })
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    let ema = self.ema.push(value)?;
+    let ema_of_ema = self.ema_of_ema.push(ema)?;
+    Some(2. * ema - ema_of_ema)
+  }
+}
+
+impl Indicator for Dema {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.ema.reset();
+    self.ema_of_ema.reset();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.ema_of_ema.is_ready()
+  }
+}
+
+/// Triple exponential moving average, `3 * EMA - 3 * EMA² + EMA³`.
+#[derive(Debug, Clone)]
+pub struct Tema {
+  ema: Ema,
+  ema2: Ema,
+  ema3: Ema,
+}
+
+impl Tema {
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      ema: Ema::new(period)?,
+      ema2: Ema::new(period)?,
+      ema3: Ema::new(period)?,
+    })
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    let ema = self.ema.push(value)?;
+    let ema2 = self.ema2.push(ema)?;
+    let ema3 = self.ema3.push(ema2)?;
+    Some(3. * ema - 3. * ema2 + ema3)
+  }
+}
+
+impl Indicator for Tema {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.ema.reset();
+    self.ema2.reset();
+    self.ema3.reset();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.ema3.is_ready()
+  }
+}
+
+/// Hull moving average, `WMA(2 * WMA(n / 2) - WMA(n))` over `sqrt(n)`
+/// observations.
+#[derive(Debug, Clone)]
+pub struct Hma {
+  half: Wma,
+  full: Wma,
+  smoothing: Wma,
+}
+
+impl Hma {
+  /// `period` must be at least 2 so that the half window is not empty.
+  pub fn new(period: usize) -> Option<Self> {
+    Some(Self {
+      half: Wma::new(period / 2)?,
+      full: Wma::new(period)?,
+      smoothing: Wma::new((period as f64).sqrt() as usize)?,
+    })
+  }
+
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    let half = self.half.push(value);
+    let full = self.full.push(value)?;
+    self.smoothing.push(2. * half? - full)
+  }
+}
+
+impl Indicator for Hma {
+  type Input = f64;
+  type Output = f64;
+
+  fn update(&mut self, value: f64) -> Option<f64> {
+    self.push(value)
+  }
+
+  fn reset(&mut self) {
+    self.half.reset();
+    self.full.reset();
+    self.smoothing.reset();
+  }
+
+  fn is_ready(&self) -> bool {
+    self.smoothing.is_ready()
+  }
+}
+
+/// Simple average of every observation within the trailing `window` of
+/// time, regardless of how many ticks arrived in it. Warm once observations
+/// span a full window.
+#[derive(Debug, Clone)]
+pub struct TimeSma {
+  window: Duration,
+  samples: VecDeque<(DateTime<Utc>, f64)>,
+  sum: KahanSum,
+  first: Option<DateTime<Utc>>,
+  last: Option<DateTime<Utc>>,
+}
+
+impl TimeSma {
+  pub fn new(window: Duration) -> Option<Self> {
+    if window <= Duration::zero() {
+      None
+    } else {
+      Some(Self {
+        window,
+        samples: VecDeque::new(),
+        sum: KahanSum::default(),
+        first: None,
+        last: None,
+      })
+    }
+  }
+}
+
+impl Indicator for TimeSma {
+  type Input = (DateTime<Utc>, f64);
+  type Output = f64;
+
+  fn update(&mut self, (timestamp, value): Self::Input) -> Option<f64> {
+    self.first.get_or_insert(timestamp);
+    self.last = Some(timestamp);
+
+    self.samples.push_back((timestamp, value));
+    self.sum.add(value);
+
+    let cutoff = timestamp - self.window;
+    while let Some(&(t, old)) = self.samples.front() {
+      if t > cutoff {
+        break;
+      }
+      self.samples.pop_front();
+      self.sum.sub(old);
+    }
+
+    self
+      .is_ready()
+      .then(|| self.sum.value() / self.samples.len() as f64)
+  }
+
+  fn reset(&mut self) {
+    self.samples.clear();
+    self.sum = KahanSum::default();
+    self.first = None;
+    self.last = None;
+  }
+
+  fn is_ready(&self) -> bool {
+    matches!((self.first, self.last), (Some(first), Some(last)) if last - first >= self.window)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn feed(
+    average: &mut impl Indicator<Input = f64, Output = f64>,
+    values: &[f64],
+  ) -> Vec<Option<f64>> {
+    values.iter().map(|&v| average.update(v)).collect()
+  }
+
+  fn assert_close(actual: Option<f64>, expected: f64) {
+    let actual = actual.expect("warm");
+    assert!(
+      (actual - expected).abs() < 1e-9,
+      "{actual} is not close to {expected}"
+    );
+  }
+
+  #[test]
+  fn invalid_periods() {
+    assert!(Sma::new(0).is_none());
+    assert!(Ema::new(0).is_none());
+    assert!(Wma::new(0).is_none());
+    assert!(Hma::new(1).is_none());
+    assert!(TimeSma::new(Duration::zero()).is_none());
+  }
+
+  #[test]
+  fn sma() {
+    let mut sma = Sma::new(3).unwrap();
+    let res = feed(&mut sma, &[1., 2., 3., 4.]);
+    assert_eq!(res, vec![None, None, Some(2.), Some(3.)]);
+  }
+
+  #[test]
+  fn ema_seeded_with_sma() {
+    let mut ema = Ema::new(3).unwrap();
+    let res = feed(&mut ema, &[1., 2., 3., 4., 5.]);
+    assert_eq!(&res[..2], &[None, None]);
+    assert_close(res[2], 2.);
+    assert_close(res[3], 3.);
+    assert_close(res[4], 4.);
+  }
+
+  #[test]
+  fn reset_warms_up_again() {
+    let mut tema = Tema::new(2).unwrap();
+    feed(&mut tema, &[1., 2., 3., 4.]);
+    assert!(tema.is_ready());
+    tema.reset();
+    assert!(!tema.is_ready());
+    let res = feed(&mut tema, &[5., 5., 5., 5.]);
+    assert_eq!(&res[..3], &[None, None, None]);
+    assert_close(res[3], 5.);
+  }
+
+  #[test]
+  fn wma() {
+    let mut wma = Wma::new(3).unwrap();
+    let res = feed(&mut wma, &[1., 2., 3., 6., 0.]);
+    assert_eq!(&res[..2], &[None, None]);
+    assert_close(res[2], (1. + 4. + 9.) / 6.);
+    assert_close(res[3], (2. + 6. + 18.) / 6.);
+    assert_close(res[4], (3. + 12. + 0.) / 6.);
+  }
+
+  #[test]
+  fn dema_and_tema_track_a_line_without_lag() {
+    let line: Vec<f64> = (0..20).map(f64::from).collect();
+    let mut dema = Dema::new(3).unwrap();
+    let mut tema = Tema::new(3).unwrap();
+    let dema_res = feed(&mut dema, &line);
+    let tema_res = feed(&mut tema, &line);
+    assert_eq!(dema_res.iter().position(Option::is_some), Some(4));
+    assert_eq!(tema_res.iter().position(Option::is_some), Some(6));
+    // a linear trend is the fixed point of the lag correction
+    assert_close(dema_res[19], 19.);
+    assert_close(tema_res[19], 19.);
+  }
+
+  #[test]
+  fn hma_tracks_a_line_without_lag() {
+    let line: Vec<f64> = (0..20).map(f64::from).collect();
+    let mut hma = Hma::new(4).unwrap();
+    let res = feed(&mut hma, &line);
+    assert_eq!(res.iter().position(Option::is_some), Some(4));
+    assert_close(res[19], 19.);
+  }
+
+  #[test]
+  fn time_sma_ignores_tick_count() {
+    let mut sma = TimeSma::new(Duration::seconds(30)).unwrap();
+    let start = Utc::now();
+    let at = |s| start + Duration::seconds(s);
+
+    assert_eq!(sma.update((at(0), 1.)), None);
+    assert_eq!(sma.update((at(10), 2.)), None);
+    assert_eq!(sma.update((at(11), 3.)), None);
+    // the first sample is exactly one window old and drops out
+    assert_eq!(sma.update((at(30), 7.)), Some(4.));
+    assert_eq!(sma.update((at(45), 5.)), Some(6.));
+  }
+}
@@ -0,0 +1,183 @@
+use crate::algos::single_pass::KahanSum;
+
+/// Fixed-capacity FIFO buffer. Once full, every push overwrites the oldest
+/// value in O(1) and hands it back to the caller.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+  buffer: Vec<T>,
+  capacity: usize,
+  // index of the oldest value once the buffer is full
+  head: usize,
+}
+
+impl<T> RingBuffer<T> {
+  pub fn new(capacity: usize) -> Option<Self> {
+    if capacity < 1 {
+      None
+    } else {
+      Some(Self {
+        buffer: Vec::with_capacity(capacity),
+        capacity,
+        head: 0,
+      })
+    }
+  }
+
+  /// Appends `value`, returning the evicted oldest value if the buffer was
+  /// already full.
+  pub fn push(&mut self, value: T) -> Option<T> {
+    if self.buffer.len() < self.capacity {
+      self.buffer.push(value);
+      None
+    } else {
+      let evicted = std::mem::replace(&mut self.buffer[self.head], value);
+      self.head = (self.head + 1) % self.capacity;
+      Some(evicted)
+    }
+  }
+
+  pub fn len(&self) -> usize {
+    self.buffer.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.buffer.is_empty()
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.buffer.len() == self.capacity
+  }
+
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  pub fn oldest(&self) -> Option<&T> {
+    self.buffer.get(self.head)
+  }
+
+  pub fn newest(&self) -> Option<&T> {
+    match self.head {
+      0 => self.buffer.last(),
+      head => self.buffer.get(head - 1),
+    }
+  }
+
+  /// Iterates from the oldest to the newest value.
+  pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+    let (newer, older) = self.buffer.split_at(self.head);
+    older.iter().chain(newer.iter())
+  }
+
+  pub fn clear(&mut self) {
+    self.buffer.clear();
+    self.head = 0;
+  }
+}
+
+/// Sliding window over the last `capacity` values keeping a compensated
+/// running sum, so windowed statistics update in O(1) per tick.
+#[derive(Debug, Clone)]
+pub struct RollingSum {
+  window: RingBuffer<f64>,
+  sum: KahanSum,
+}
+
+impl RollingSum {
+  pub fn new(capacity: usize) -> Option<Self> {
+    Some(Self {
+      window: RingBuffer::new(capacity)?,
+      sum: KahanSum::default(),
+    })
+  }
+
+  /// Adds `value` to the window, returning the value that fell out of it.
+  pub fn push(&mut self, value: f64) -> Option<f64> {
+    self.sum.add(value);
+    let evicted = self.window.push(value);
+    if let Some(old) = evicted {
+      self.sum.sub(old);
+    }
+    evicted
+  }
+
+  pub fn sum(&self) -> f64 {
+    self.sum.value()
+  }
+
+  /// Mean of the window, `None` until the window has been filled once.
+  pub fn mean(&self) -> Option<f64> {
+    self
+      .is_full()
+      .then(|| self.sum() / self.window.capacity() as f64)
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.window.is_full()
+  }
+
+  pub fn len(&self) -> usize {
+    self.window.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.window.is_empty()
+  }
+
+  pub fn window(&self) -> &RingBuffer<f64> {
+    &self.window
+  }
+
+  pub fn clear(&mut self) {
+    self.window.clear();
+    self.sum = KahanSum::default();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn zero_capacity() {
+    assert!(RingBuffer::<f64>::new(0).is_none());
+    assert!(RollingSum::new(0).is_none());
+  }
+
+  #[test]
+  fn evicts_oldest() {
+    let mut buffer = RingBuffer::new(3).unwrap();
+    assert_eq!(buffer.push(1), None);
+    assert_eq!(buffer.push(2), None);
+    assert!(!buffer.is_full());
+    assert_eq!(buffer.push(3), None);
+    assert!(buffer.is_full());
+    assert_eq!(buffer.push(4), Some(1));
+    assert_eq!(buffer.push(5), Some(2));
+    assert_eq!(buffer.iter().copied().collect::<Vec<_>>(), vec![3, 4, 5]);
+    assert_eq!(buffer.oldest(), Some(&3));
+    assert_eq!(buffer.newest(), Some(&5));
+    assert_eq!(buffer.len(), 3);
+  }
+
+  #[test]
+  fn rolling_mean_warm_up() {
+    let mut window = RollingSum::new(2).unwrap();
+    window.push(0.);
+    assert_eq!(window.mean(), None);
+    window.push(0.);
+    assert_eq!(window.mean(), Some(0.));
+    window.push(4.);
+    assert_eq!(window.mean(), Some(2.));
+  }
+
+  #[test]
+  fn compensated_sum_does_not_drift() {
+    let mut window = RollingSum::new(10).unwrap();
+    for i in 0..100_000 {
+      window.push(0.1 + (i % 7) as f64 * 1e6);
+    }
+    let exact: f64 = window.window().iter().sum();
+    assert!((window.sum() - exact).abs() < 1e-6);
+  }
+}
@@ -1,5 +1,7 @@
 use core::ops::{Add, Div, Mul, Sub};
 
+use crate::algos::ring_buffer::RingBuffer;
+
 pub fn mean<T>(last: T, new: impl Into<T>, size: u32) -> T
 where
   T: Add<Output = T> + Sub<Output = T> + Div<Output = T> + Copy + From<u32>,
@@ -7,9 +9,9 @@ where
   last + (new.into() - last) / size.into()
 }
 
-pub fn sum<T: Add<T, Output = T>>(values: impl Iterator<Item = T>) -> T
+pub fn sum<T>(values: impl Iterator<Item = T>) -> T
 where
-  T: Zero,
+  T: Add<T, Output = T> + Zero,
 {
   values.fold(Zero::zero(), |acc, x| acc + x)
 }
@@ -39,3 +41,469 @@ impl Zero for f64 {
     0f64
   }
 }
+
+/// Running sum using Kahan compensated summation, so that values added and
+/// removed over a long stream do not accumulate rounding error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KahanSum {
+  sum: f64,
+  compensation: f64,
+}
+
+impl KahanSum {
+  pub fn add(&mut self, value: f64) {
+    let y = value - self.compensation;
+    let t = self.sum + y;
+    self.compensation = (t - self.sum) - y;
+    self.sum = t;
+  }
+
+  pub fn sub(&mut self, value: f64) {
+    self.add(-value);
+  }
+
+  pub fn value(&self) -> f64 {
+    self.sum
+  }
+}
+
+/// Arithmetic needed by the online estimators below.
+pub trait Real:
+  Zero
+  + Copy
+  + PartialOrd
+  + Add<Output = Self>
+  + Sub<Output = Self>
+  + Mul<Output = Self>
+  + Div<Output = Self>
+  + From<u32>
+{
+  fn sqrt(self) -> Self;
+}
+
+impl Real for f64 {
+  fn sqrt(self) -> Self {
+    f64::sqrt(self)
+  }
+}
+
+fn non_negative<T: Real>(value: T) -> T {
+  if value < T::zero() {
+    T::zero()
+  } else {
+    value
+  }
+}
+
+/// Welford's numerically stable online mean and variance. Observations can
+/// also be removed again, in reverse of adding them.
+#[derive(Debug, Clone, Copy)]
+pub struct Welford<T> {
+  count: u32,
+  mean: T,
+  m2: T,
+}
+
+impl<T: Real> Default for Welford<T> {
+  fn default() -> Self {
+    Self {
+      count: 0,
+      mean: T::zero(),
+      m2: T::zero(),
+    }
+  }
+}
+
+impl<T: Real> Welford<T> {
+  pub fn push(&mut self, x: T) {
+    self.count += 1;
+    let last = self.mean;
+    self.mean = mean(last, x, self.count);
+    self.m2 = self.m2 + (x - last) * (x - self.mean);
+  }
+
+  pub fn remove(&mut self, x: T) {
+    match self.count {
+      0 => {}
+      1 => *self = Self::default(),
+      count => {
+        self.count = count - 1;
+        let last = self.mean;
+        self.mean = last - (x - last) / self.count.into();
+        self.m2 = non_negative(self.m2 - (x - last) * (x - self.mean));
+      }
+    }
+  }
+
+  pub fn count(&self) -> u32 {
+    self.count
+  }
+
+  pub fn mean(&self) -> Option<T> {
+    (self.count > 0).then_some(self.mean)
+  }
+
+  /// Sum of squared deviations from the mean.
+  pub fn sum_squares(&self) -> T {
+    self.m2
+  }
+
+  /// Population variance.
+  pub fn variance(&self) -> Option<T> {
+    (self.count > 0).then(|| self.m2 / self.count.into())
+  }
+
+  /// Unbiased sample variance, dividing by `n - 1`.
+  pub fn sample_variance(&self) -> Option<T> {
+    (self.count > 1).then(|| self.m2 / (self.count - 1).into())
+  }
+}
+
+/// Variance over the last `capacity` observations, updated in O(1) by
+/// removing the evicted observation from a [`Welford`] estimator.
+#[derive(Debug, Clone)]
+pub struct RollingVariance<T> {
+  window: RingBuffer<T>,
+  welford: Welford<T>,
+}
+
+impl<T: Real> RollingVariance<T> {
+  pub fn new(capacity: usize) -> Option<Self> {
+    Some(Self {
+      window: RingBuffer::new(capacity)?,
+      welford: Welford::default(),
+    })
+  }
+
+  pub fn push(&mut self, x: T) -> Option<T> {
+    let evicted = self.window.push(x);
+    if let Some(old) = evicted {
+      self.welford.remove(old);
+    }
+    self.welford.push(x);
+    evicted
+  }
+
+  pub fn is_full(&self) -> bool {
+    self.window.is_full()
+  }
+
+  pub fn clear(&mut self) {
+    self.window.clear();
+    self.welford = Welford::default();
+  }
+
+  /// Statistics of the observations currently in the window.
+  pub fn stats(&self) -> &Welford<T> {
+    &self.welford
+  }
+
+  pub fn window(&self) -> &RingBuffer<T> {
+    &self.window
+  }
+}
+
+/// Online covariance and Pearson correlation of paired observations.
+#[derive(Debug, Clone, Copy)]
+pub struct Covariance<T> {
+  x: Welford<T>,
+  y: Welford<T>,
+  c: T,
+}
+
+impl<T: Real> Default for Covariance<T> {
+  fn default() -> Self {
+    Self {
+      x: Welford::default(),
+      y: Welford::default(),
+      c: T::zero(),
+    }
+  }
+}
+
+impl<T: Real> Covariance<T> {
+  pub fn push(&mut self, x: T, y: T) {
+    let last_x = self.x.mean().unwrap_or_else(T::zero);
+    self.x.push(x);
+    self.y.push(y);
+    let mean_y = self.y.mean().unwrap_or_else(T::zero);
+    self.c = self.c + (x - last_x) * (y - mean_y);
+  }
+
+  pub fn count(&self) -> u32 {
+    self.x.count()
+  }
+
+  pub fn x(&self) -> &Welford<T> {
+    &self.x
+  }
+
+  pub fn y(&self) -> &Welford<T> {
+    &self.y
+  }
+
+  /// Population covariance.
+  pub fn covariance(&self) -> Option<T> {
+    (self.count() > 0).then(|| self.c / self.count().into())
+  }
+
+  pub fn sample_covariance(&self) -> Option<T> {
+    (self.count() > 1).then(|| self.c / (self.count() - 1).into())
+  }
+
+  /// `None` until two observations arrived or while either side is flat.
+  pub fn correlation(&self) -> Option<T> {
+    let denominator = (self.x.sum_squares() * self.y.sum_squares()).sqrt();
+    (self.count() > 1 && denominator > T::zero()).then(|| self.c / denominator)
+  }
+}
+
+/// Online mean, variance, skewness and excess kurtosis.
+#[derive(Debug, Clone, Copy)]
+pub struct Moments<T> {
+  count: u32,
+  mean: T,
+  m2: T,
+  m3: T,
+  m4: T,
+}
+
+impl<T: Real> Default for Moments<T> {
+  fn default() -> Self {
+    Self {
+      count: 0,
+      mean: T::zero(),
+      m2: T::zero(),
+      m3: T::zero(),
+      m4: T::zero(),
+    }
+  }
+}
+
+impl<T: Real> Moments<T> {
+  pub fn push(&mut self, x: T) {
+    let n1: T = self.count.into();
+    self.count += 1;
+    let n: T = self.count.into();
+    let delta = x - self.mean;
+    let delta_n = delta / n;
+    let delta_n2 = delta_n * delta_n;
+    let term1 = delta * delta_n * n1;
+    let (three, four, six) = (T::from(3), T::from(4), T::from(6));
+
+    self.mean = self.mean + delta_n;
+    self.m4 = self.m4
+      + term1 * delta_n2 * (n * n - three * n + three)
+      + six * delta_n2 * self.m2
+      - four * delta_n * self.m3;
+    self.m3 =
+      self.m3 + term1 * delta_n * (n - T::from(2)) - three * delta_n * self.m2;
+    self.m2 = self.m2 + term1;
+  }
+
+  pub fn count(&self) -> u32 {
+    self.count
+  }
+
+  pub fn mean(&self) -> Option<T> {
+    (self.count > 0).then_some(self.mean)
+  }
+
+  pub fn variance(&self) -> Option<T> {
+    (self.count > 0).then(|| self.m2 / self.count.into())
+  }
+
+  /// Population skewness, `None` while the observations are flat.
+  pub fn skewness(&self) -> Option<T> {
+    let n: T = self.count.into();
+    (self.m2 > T::zero())
+      .then(|| n.sqrt() * self.m3 / (self.m2 * self.m2.sqrt()))
+  }
+
+  /// Population excess kurtosis, `None` while the observations are flat.
+  pub fn kurtosis(&self) -> Option<T> {
+    let n: T = self.count.into();
+    (self.m2 > T::zero())
+      .then(|| n * self.m4 / (self.m2 * self.m2) - T::from(3))
+  }
+}
+
+/// Exponentially weighted mean and variance, weighing the newest
+/// observation by `alpha`.
+#[derive(Debug, Clone, Copy)]
+pub struct EwVariance<T> {
+  alpha: T,
+  mean: Option<T>,
+  variance: T,
+}
+
+impl<T: Real> EwVariance<T> {
+  /// `alpha` must be in `(0, 1]`.
+  pub fn new(alpha: T) -> Option<Self> {
+    (alpha > T::zero() && alpha <= T::from(1)).then(|| Self {
+      alpha,
+      mean: None,
+      variance: T::zero(),
+    })
+  }
+
+  pub fn push(&mut self, x: T) {
+    match self.mean {
+      None => self.mean = Some(x),
+      Some(last) => {
+        let diff = x - last;
+        let increment = self.alpha * diff;
+        self.mean = Some(last + increment);
+        self.variance =
+          (T::from(1) - self.alpha) * (self.variance + diff * increment);
+      }
+    }
+  }
+
+  pub fn mean(&self) -> Option<T> {
+    self.mean
+  }
+
+  pub fn variance(&self) -> Option<T> {
+    self.mean.map(|_| self.variance)
+  }
+
+  pub fn reset(&mut self) {
+    self.mean = None;
+    self.variance = T::zero();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use proptest::prelude::*;
+
+  fn naive_mean(values: &[f64]) -> f64 {
+    sum(values.iter().copied()) / values.len() as f64
+  }
+
+  fn naive_variance(values: &[f64]) -> f64 {
+    mean_centered_sum_squared(values.iter().copied(), naive_mean(values))
+      / values.len() as f64
+  }
+
+  fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-6 * (1. + a.abs().max(b.abs()))
+  }
+
+  fn values() -> impl Strategy<Value = Vec<f64>> {
+    prop::collection::vec(-1e3..1e3f64, 2..200)
+  }
+
+  proptest! {
+    #[test]
+    fn welford_matches_two_pass(values in values()) {
+      let mut welford = Welford::default();
+      values.iter().for_each(|&x| welford.push(x));
+      prop_assert!(close(welford.mean().unwrap(), naive_mean(&values)));
+      prop_assert!(close(welford.variance().unwrap(), naive_variance(&values)));
+    }
+
+    #[test]
+    fn rolling_variance_matches_two_pass(
+      values in values(),
+      capacity in 1..20usize,
+    ) {
+      let mut rolling = RollingVariance::new(capacity).unwrap();
+      for (i, &x) in values.iter().enumerate() {
+        rolling.push(x);
+        let window = &values[(i + 1).saturating_sub(capacity)..=i];
+        let stats = rolling.stats();
+        prop_assert!(close(stats.mean().unwrap(), naive_mean(window)));
+        prop_assert!(close(stats.variance().unwrap(), naive_variance(window)));
+      }
+    }
+
+    #[test]
+    fn covariance_matches_two_pass(
+      pairs in prop::collection::vec((-1e3..1e3f64, -1e3..1e3f64), 2..200),
+    ) {
+      let mut covariance = Covariance::default();
+      pairs.iter().for_each(|&(x, y)| covariance.push(x, y));
+      let xs: Vec<_> = pairs.iter().map(|p| p.0).collect();
+      let ys: Vec<_> = pairs.iter().map(|p| p.1).collect();
+      let (mean_x, mean_y) = (naive_mean(&xs), naive_mean(&ys));
+      let naive = pairs
+        .iter()
+        .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>()
+        / pairs.len() as f64;
+      prop_assert!(close(covariance.covariance().unwrap(), naive));
+      let correlation = naive
+        / (naive_variance(&xs) * naive_variance(&ys)).sqrt();
+      prop_assert!(close(covariance.correlation().unwrap(), correlation));
+    }
+
+    #[test]
+    fn moments_match_two_pass(values in values()) {
+      let mut moments = Moments::default();
+      values.iter().for_each(|&x| moments.push(x));
+      let n = values.len() as f64;
+      let mean = naive_mean(&values);
+      let central = |p| values.iter().map(|x| (x - mean).powi(p)).sum::<f64>() / n;
+      let (m2, m3, m4) = (central(2), central(3), central(4));
+      prop_assert!(close(moments.variance().unwrap(), m2));
+      prop_assert!(close(moments.skewness().unwrap(), m3 / m2.powf(1.5)));
+      prop_assert!(close(moments.kurtosis().unwrap(), m4 / (m2 * m2) - 3.));
+    }
+
+    #[test]
+    fn ew_variance_matches_weighted_two_pass(
+      values in values(),
+      alpha in 0.01..1f64,
+    ) {
+      let mut ew = EwVariance::new(alpha).unwrap();
+      values.iter().for_each(|&x| ew.push(x));
+      // the same recursion unrolled into explicit weights
+      let n = values.len();
+      let weights: Vec<f64> = (0..n)
+        .map(|i| match i {
+          0 => (1. - alpha).powi(n as i32 - 1),
+          i => alpha * (1. - alpha).powi((n - 1 - i) as i32),
+        })
+        .collect();
+      let mean: f64 = values.iter().zip(&weights).map(|(x, w)| x * w).sum();
+      let variance: f64 = values
+        .iter()
+        .zip(&weights)
+        .map(|(x, w)| w * (x - mean).powi(2))
+        .sum();
+      prop_assert!(close(ew.mean().unwrap(), mean));
+      prop_assert!(close(ew.variance().unwrap(), variance));
+    }
+  }
+
+  #[test]
+  fn welford_remove_undoes_push() {
+    let mut welford = Welford::default();
+    for x in [1., 2., 3., 10.] {
+      welford.push(x);
+    }
+    welford.remove(10.);
+    assert_eq!(welford.mean(), Some(2.));
+    assert_eq!(welford.sample_variance(), Some(1.));
+    welford.remove(1.);
+    welford.remove(2.);
+    welford.remove(3.);
+    assert_eq!(welford.mean(), None);
+  }
+
+  #[test]
+  fn flat_observations() {
+    let mut moments = Moments::default();
+    let mut covariance = Covariance::default();
+    for _ in 0..3 {
+      moments.push(1.);
+      covariance.push(1., 2.);
+    }
+    assert_eq!(moments.skewness(), None);
+    assert_eq!(covariance.correlation(), None);
+    assert!(EwVariance::new(0.).is_none());
+  }
+}
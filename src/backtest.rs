@@ -0,0 +1,220 @@
+//! Replays recorded market data through the live actor graph, with a paper
+//! exchange in place of [`crate::trade::TradeActor`].
+
+pub mod exchange;
+pub mod report;
+pub mod sweep;
+
+use std::sync::Arc;
+
+use actix::{Actor, Context, Handler, Message};
+use chrono::{DateTime, Utc};
+
+use crate::actors::bar::Bar;
+use crate::actors::indicator::IndicatorActor;
+use crate::actors::mid_price::{MidPriceActor, MidPriceIndicator};
+use crate::actors::moving_average::MovingAverageActor;
+use crate::algos::indicators::Indicator;
+use crate::algos::moving_average::Sma;
+use crate::binance_websocket::TickerMessage;
+use crate::clock::SimulatedClock;
+use crate::policy_maker::{PolicyMakerActor, PolicyParameters};
+use exchange::{GetReport, PaperExchange};
+use report::BacktestReport;
+
+/// Answered once every message queued before it has been handled, so a
+/// driver can wait for an actor to go idle.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Flush;
+
+impl<I, O> Handler<Flush> for IndicatorActor<I, O>
+where
+  I: Indicator + Unpin + 'static,
+  O: Message + Send + Unpin + 'static,
+  O::Result: Send,
+{
+  type Result = ();
+
+  fn handle(&mut self, _: Flush, _ctx: &mut Context<Self>) {}
+}
+
+impl Handler<Flush> for PolicyMakerActor {
+  type Result = ();
+
+  fn handle(&mut self, _: Flush, _ctx: &mut Context<Self>) {}
+}
+
+/// Runs the moving average strategy of `examples/main.rs` over recorded
+/// data. Every event is pushed through the whole graph before the next one
+/// is replayed, so runs over the same data give the same report.
+#[derive(Debug, Clone)]
+pub struct Backtest {
+  window: usize,
+  parameters: PolicyParameters,
+  initial_cash: f64,
+  fee_rate: f64,
+}
+
+impl Backtest {
+  /// `window` is the period of the moving average, at least one.
+  pub fn new(window: usize) -> Option<Self> {
+    if window == 0 {
+      None
+    } else {
+      Some(Self {
+        window,
+        parameters: PolicyParameters::default(),
+        initial_cash: 10_000.,
+        fee_rate: 0.,
+      })
+    }
+  }
+
+  pub fn with_parameters(mut self, parameters: PolicyParameters) -> Self {
+    self.parameters = parameters;
+    self
+  }
+
+  pub fn with_initial_cash(mut self, initial_cash: f64) -> Self {
+    self.initial_cash = initial_cash;
+    self
+  }
+
+  /// Fee charged per fill, as a fraction of its notional.
+  pub fn with_fee_rate(mut self, fee_rate: f64) -> Self {
+    self.fee_rate = fee_rate;
+    self
+  }
+
+  /// Replays tickers with the time they were received. Must be awaited
+  /// within a running actix system.
+  pub async fn run(
+    &self,
+    tickers: impl IntoIterator<Item = (DateTime<Utc>, TickerMessage)>,
+  ) -> BacktestReport {
+    let clock = SimulatedClock::default();
+    let exchange = PaperExchange::new(
+      Arc::new(clock.clone()),
+      self.initial_cash,
+      self.fee_rate,
+    )
+    .start();
+    let policy_maker =
+      PolicyMakerActor::new(vec![exchange.clone().recipient()])
+        .with_parameters(self.parameters)
+        .start();
+    let moving_average = MovingAverageActor::new(
+      Sma::new(self.window).expect("validated in new"),
+      vec![policy_maker.clone().recipient()],
+    )
+    .start();
+    let mid_price = MidPriceActor::new(
+      MidPriceIndicator::new(Arc::new(clock.clone())),
+      vec![
+        moving_average.clone().recipient(),
+        policy_maker.clone().recipient(),
+        exchange.clone().recipient(),
+      ],
+    )
+    .start();
+
+    for (time, ticker) in tickers {
+      clock.set(time);
+      // mailboxes are FIFO, flushing in graph order settles the event
+      mid_price
+        .send(ticker)
+        .await
+        .expect("backtest actor stopped");
+      moving_average
+        .send(Flush)
+        .await
+        .expect("backtest actor stopped");
+      policy_maker
+        .send(Flush)
+        .await
+        .expect("backtest actor stopped");
+      exchange.send(Flush).await.expect("backtest actor stopped");
+    }
+
+    exchange
+      .send(GetReport)
+      .await
+      .expect("backtest actor stopped")
+  }
+
+  /// Replays bars as tickers quoting their close at their close time.
+  pub async fn run_bars(
+    &self,
+    bars: impl IntoIterator<Item = Bar>,
+  ) -> BacktestReport {
+    self
+      .run(bars.into_iter().map(|bar| {
+        let ticker = TickerMessage {
+          symbol: bar.symbol,
+          best_bid_price: bar.close,
+          best_ask_price: bar.close,
+          ..Default::default()
+        };
+        (bar.close_time, ticker)
+      }))
+      .await
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::mock::{self, PRICES};
+  use chrono::Duration;
+
+  fn tickers() -> Vec<(DateTime<Utc>, TickerMessage)> {
+    mock::tickers(&PRICES)
+      .into_iter()
+      .map(|ticker| (ticker.event_time.unwrap(), ticker))
+      .collect()
+  }
+
+  #[actix_rt::test]
+  async fn deterministic_report() {
+    let backtest = Backtest::new(3).unwrap().with_initial_cash(1_000.);
+    let tickers = tickers();
+    let report = backtest.run(tickers.clone()).await;
+    assert_eq!(report, backtest.run(tickers.clone()).await);
+
+    assert_eq!(report.equity.len(), tickers.len());
+    assert!(!report.fills.is_empty());
+    let times: Vec<_> = report.equity.iter().map(|e| e.timestamp).collect();
+    assert_eq!(times, tickers.iter().map(|t| t.0).collect::<Vec<_>>());
+
+    // without fees the pnl is the cash flows plus the marked position
+    let (cash, position) = report.fills.iter().fold((0., 0.), |(c, p), f| {
+      (c + f.cash_flow(), p + f.signed_quantity())
+    });
+    let expected = cash + position * PRICES[PRICES.len() - 1];
+    assert!((report.pnl - expected).abs() < 1e-9);
+    assert!(report.max_drawdown >= 0.);
+  }
+
+  #[actix_rt::test]
+  async fn bars() {
+    let bars = tickers().into_iter().map(|(time, ticker)| Bar {
+      symbol: ticker.symbol,
+      open_time: time - Duration::seconds(1),
+      close_time: time,
+      open: ticker.best_bid_price,
+      high: ticker.best_ask_price,
+      low: ticker.best_bid_price,
+      close: ticker.best_bid_price + 0.5,
+      volume: 0.,
+    });
+    let backtest = Backtest::new(3).unwrap();
+    let report = backtest.run_bars(bars).await;
+    assert_eq!(report, backtest.run(tickers()).await);
+  }
+
+  #[test]
+  fn zero_window() {
+    assert!(Backtest::new(0).is_none());
+  }
+}
@@ -0,0 +1,288 @@
+use std::collections::BTreeMap;
+
+use actix::{Actor, Context, Handler, Message, MessageResult, Recipient};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::actors::indicator::Subscribe;
+use crate::actors::mid_price::MidPrice;
+use crate::actors::risk::Equity;
+use crate::backtest::report::{BacktestReport, Decisions};
+use crate::backtest::Flush;
+use crate::clock::SharedClock;
+use crate::policy_maker::PolicyDecision;
+use crate::trade::RiskLimits;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+  Buy,
+  Sell,
+}
+
+/// An executed order.
+#[derive(Message, Serialize, Debug, Clone, PartialEq)]
+#[rtype(result = "()")]
+pub struct Fill {
+  pub symbol: String,
+  pub side: Side,
+  pub quantity: f64,
+  pub price: f64,
+  pub fee: f64,
+  pub timestamp: DateTime<Utc>,
+}
+
+impl Fill {
+  /// Cash received, negative when buying.
+  pub fn cash_flow(&self) -> f64 {
+    let notional = self.quantity * self.price;
+    match self.side {
+      Side::Buy => -notional - self.fee,
+      Side::Sell => notional - self.fee,
+    }
+  }
+
+  /// Change of the position, negative when selling.
+  pub fn signed_quantity(&self) -> f64 {
+    match self.side {
+      Side::Buy => self.quantity,
+      Side::Sell => -self.quantity,
+    }
+  }
+}
+
+/// Summarizes the run so far.
+#[derive(Message)]
+#[rtype(result = "BacktestReport")]
+pub struct GetReport;
+
+/// Stands in for [`crate::trade::TradeActor`] in backtests: fills every buy
+/// and sell decision in full at its price, charging `fee_rate` of the
+/// notional, and marks the account to the mid prices it receives, publishing
+/// every mark as [`Equity`] to the nodes subscribed to it. Positions may go
+/// short.
+pub struct PaperExchange {
+  clock: SharedClock,
+  fee_rate: f64,
+  initial_cash: f64,
+  cash: f64,
+  positions: BTreeMap<String, f64>,
+  prices: BTreeMap<String, f64>,
+  decisions: Decisions,
+  fills: Vec<Fill>,
+  equity: Vec<Equity>,
+  limits: RiskLimits,
+  subscribers: Vec<Recipient<Equity>>,
+}
+
+impl PaperExchange {
+  pub fn new(clock: SharedClock, initial_cash: f64, fee_rate: f64) -> Self {
+    Self {
+      clock,
+      fee_rate,
+      initial_cash,
+      cash: initial_cash,
+      positions: BTreeMap::new(),
+      prices: BTreeMap::new(),
+      decisions: Decisions::default(),
+      fills: vec![],
+      equity: vec![],
+      limits: RiskLimits::default(),
+      subscribers: vec![],
+    }
+  }
+
+  /// Leaves the decisions exceeding `limits` unfilled.
+  pub fn with_limits(mut self, limits: RiskLimits) -> Self {
+    self.limits = limits;
+    self
+  }
+
+  /// Cash plus every position at its last mid price.
+  pub fn equity(&self) -> f64 {
+    self
+      .positions
+      .iter()
+      .fold(self.cash, |equity, (symbol, quantity)| {
+        let price = self.prices.get(symbol).copied().unwrap_or_default();
+        equity + quantity * price
+      })
+  }
+
+  fn fill(&mut self, symbol: String, side: Side, quantity: f64, price: f64) {
+    let position = self.positions.get(&symbol).copied().unwrap_or_default();
+    let signed = match side {
+      Side::Buy => quantity,
+      Side::Sell => -quantity,
+    };
+    if !self.limits.allow(position, signed) {
+      tracing::warn!(symbol, ?side, quantity, "Order exceeds the risk limits");
+      return;
+    }
+    let fill = Fill {
+      symbol,
+      side,
+      quantity,
+      price,
+      fee: quantity * price * self.fee_rate,
+      timestamp: self.clock.now(),
+    };
+    tracing::debug!(?fill, "Paper fill");
+    self.cash += fill.cash_flow();
+    *self.positions.entry(fill.symbol.clone()).or_default() +=
+      fill.signed_quantity();
+    self.fills.push(fill);
+  }
+}
+
+impl Actor for PaperExchange {
+  type Context = Context<Self>;
+}
+
+impl Handler<MidPrice> for PaperExchange {
+  type Result = ();
+
+  fn handle(&mut self, msg: MidPrice, _ctx: &mut Context<Self>) {
+    self.prices.insert(msg.symbol, msg.price);
+    let equity = Equity {
+      value: self.equity(),
+      timestamp: self.clock.now(),
+    };
+    for s in &self.subscribers {
+      s.do_send(equity);
+    }
+    self.equity.push(equity);
+  }
+}
+
+impl Handler<PolicyDecision> for PaperExchange {
+  type Result = ();
+
+  fn handle(&mut self, msg: PolicyDecision, _ctx: &mut Context<Self>) {
+    match msg {
+      PolicyDecision::BuyAction(buy) => {
+        self.decisions.buy += 1;
+        self.fill(buy.symbol, Side::Buy, buy.quantity, buy.price)
+      }
+      PolicyDecision::SellAction(sell) => {
+        self.decisions.sell += 1;
+        self.fill(sell.symbol, Side::Sell, sell.quantity, sell.price)
+      }
+      PolicyDecision::HoldAction(_) => self.decisions.hold += 1,
+    }
+  }
+}
+
+impl Handler<Subscribe<Equity>> for PaperExchange {
+  type Result = ();
+
+  fn handle(&mut self, msg: Subscribe<Equity>, _ctx: &mut Context<Self>) {
+    self.subscribers.push(msg.0);
+  }
+}
+
+impl Handler<Flush> for PaperExchange {
+  type Result = ();
+
+  fn handle(&mut self, _: Flush, _ctx: &mut Context<Self>) {}
+}
+
+impl Handler<GetReport> for PaperExchange {
+  type Result = MessageResult<GetReport>;
+
+  fn handle(&mut self, _: GetReport, _ctx: &mut Context<Self>) -> Self::Result {
+    MessageResult(BacktestReport::new(
+      self.initial_cash,
+      self.decisions,
+      self.fills.clone(),
+      self.equity.clone(),
+    ))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::clock::{Clock, SimulatedClock};
+  use crate::trade::{Buy, Sell};
+  use std::sync::Arc;
+
+  fn price(price: f64) -> MidPrice {
+    MidPrice {
+      price,
+      symbol: "BTCUSDT".to_owned(),
+      timestamp: Utc::now(),
+      correlation: None,
+    }
+  }
+
+  #[actix_rt::test]
+  async fn fills_and_marks() {
+    let clock = SimulatedClock::new(Utc::now());
+    let addr = PaperExchange::new(Arc::new(clock.clone()), 100., 0.01).start();
+    let (collector, recipient) = collect::<Equity>();
+    addr.send(Subscribe(recipient)).await.unwrap();
+    let start = clock.now();
+
+    addr.send(price(10.)).await.unwrap();
+    addr
+      .send(PolicyDecision::BuyAction(Buy {
+        symbol: "BTCUSDT".to_owned(),
+        quantity: 2.,
+        price: 10.,
+        timestamp: start,
+        correlation: None,
+      }))
+      .await
+      .unwrap();
+    addr.send(price(12.)).await.unwrap();
+    addr
+      .send(PolicyDecision::SellAction(Sell {
+        symbol: "BTCUSDT".to_owned(),
+        quantity: 3.,
+        price: 12.,
+        timestamp: start,
+        correlation: None,
+      }))
+      .await
+      .unwrap();
+    addr.send(price(11.)).await.unwrap();
+
+    let report = addr.send(GetReport).await.unwrap();
+    let fees: Vec<_> = report.fills.iter().map(|f| f.fee).collect();
+    assert!((fees[0] - 0.2).abs() < 1e-9 && (fees[1] - 0.36).abs() < 1e-9);
+    // 100 - 20.2 + 35.64 cash, short 1 at 11
+    let equity: Vec<_> = report.equity.iter().map(|e| e.value).collect();
+    assert_eq!(equity[..2], [100., 103.8]);
+    assert!((equity[2] - 104.44).abs() < 1e-9);
+    assert_eq!(collector.send(Take::new()).await.unwrap(), report.equity);
+  }
+
+  #[actix_rt::test]
+  async fn leaves_orders_beyond_limits_unfilled() {
+    let limits = RiskLimits {
+      max_order_quantity: Some(2.),
+      max_position: Some(3.),
+    };
+    let addr =
+      PaperExchange::new(Arc::new(SimulatedClock::default()), 100., 0.)
+        .with_limits(limits)
+        .start();
+    for quantity in [3., 2., 2., 1.] {
+      addr
+        .send(PolicyDecision::BuyAction(Buy {
+          symbol: "BTCUSDT".to_owned(),
+          quantity,
+          price: 10.,
+          timestamp: Utc::now(),
+          correlation: None,
+        }))
+        .await
+        .unwrap();
+    }
+
+    let report = addr.send(GetReport).await.unwrap();
+    let filled: Vec<_> = report.fills.iter().map(|f| f.quantity).collect();
+    assert_eq!(filled, [2., 1.]);
+  }
+}
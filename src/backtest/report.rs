@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+use crate::actors::returns::{ReturnKind, Returns};
+use crate::actors::risk::drawdown::Drawdown;
+use crate::actors::risk::sharpe::Sharpe;
+use crate::actors::risk::Equity;
+use crate::algos::indicators::Indicator;
+use crate::backtest::exchange::{Fill, Side};
+
+/// Quantities below this are treated as flat.
+const EPSILON: f64 = 1e-12;
+
+/// Number of decisions of each kind the policy maker sent.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Decisions {
+  pub buy: usize,
+  pub sell: usize,
+  pub hold: usize,
+}
+
+/// A position opened by one fill and closed by another, matched first in
+/// first out. Fees of both fills are included pro rata.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RoundTrip {
+  pub symbol: String,
+  /// Side of the opening fill, buying opens a long position.
+  pub side: Side,
+  pub quantity: f64,
+  pub entry_time: DateTime<Utc>,
+  pub entry_price: f64,
+  pub exit_time: DateTime<Utc>,
+  pub exit_price: f64,
+  pub fees: f64,
+  pub pnl: f64,
+}
+
+impl RoundTrip {
+  pub fn holding_time(&self) -> Duration {
+    self.exit_time - self.entry_time
+  }
+}
+
+struct Lot {
+  side: Side,
+  quantity: f64,
+  price: f64,
+  fee_per_unit: f64,
+  time: DateTime<Utc>,
+}
+
+/// Matches closing fills against the open lots of their symbol.
+fn round_trips(fills: &[Fill]) -> Vec<RoundTrip> {
+  let mut open: BTreeMap<&str, VecDeque<Lot>> = BTreeMap::new();
+  let mut trips = vec![];
+  for fill in fills {
+    let lots = open.entry(&fill.symbol).or_default();
+    let fee_per_unit = fill.fee / fill.quantity;
+    let mut quantity = fill.quantity;
+    while quantity > EPSILON {
+      let Some(lot) = lots.front_mut().filter(|lot| lot.side != fill.side)
+      else {
+        break;
+      };
+      let matched = quantity.min(lot.quantity);
+      let gross = match lot.side {
+        Side::Buy => (fill.price - lot.price) * matched,
+        Side::Sell => (lot.price - fill.price) * matched,
+      };
+      let fees = (lot.fee_per_unit + fee_per_unit) * matched;
+      trips.push(RoundTrip {
+        symbol: fill.symbol.clone(),
+        side: lot.side,
+        quantity: matched,
+        entry_time: lot.time,
+        entry_price: lot.price,
+        exit_time: fill.timestamp,
+        exit_price: fill.price,
+        fees,
+        pnl: gross - fees,
+      });
+      quantity -= matched;
+      lot.quantity -= matched;
+      if lot.quantity <= EPSILON {
+        lots.pop_front();
+      }
+    }
+    if quantity > EPSILON {
+      lots.push_back(Lot {
+        side: fill.side,
+        quantity,
+        price: fill.price,
+        fee_per_unit,
+        time: fill.timestamp,
+      });
+    }
+  }
+  trips
+}
+
+/// Fraction of the time between the first and the last equity point spent
+/// with any open position.
+fn exposure(fills: &[Fill], equity: &[Equity]) -> f64 {
+  let mut positions: BTreeMap<&str, f64> = BTreeMap::new();
+  let mut fills = fills.iter().peekable();
+  let mut exposed = Duration::zero();
+  for window in equity.windows(2) {
+    while let Some(fill) =
+      fills.next_if(|fill| fill.timestamp <= window[0].timestamp)
+    {
+      *positions.entry(&fill.symbol).or_default() += fill.signed_quantity();
+    }
+    if positions.values().any(|position| position.abs() > EPSILON) {
+      exposed += window[1].timestamp - window[0].timestamp;
+    }
+  }
+  let total = match (equity.first(), equity.last()) {
+    (Some(first), Some(last)) => last.timestamp - first.timestamp,
+    _ => Duration::zero(),
+  };
+  if total > Duration::zero() {
+    exposed.num_microseconds().unwrap_or(i64::MAX) as f64
+      / total.num_microseconds().unwrap_or(i64::MAX) as f64
+  } else {
+    0.
+  }
+}
+
+fn seconds(duration: Duration) -> f64 {
+  duration.num_milliseconds() as f64 / 1_000.
+}
+
+/// Outcome of a backtest, computed from the fills and the equity curve.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct BacktestReport {
+  pub initial_equity: f64,
+  pub final_equity: f64,
+  pub pnl: f64,
+  pub fees: f64,
+  /// Notional traded over the initial equity.
+  pub turnover: f64,
+  /// Fraction of the time with an open position.
+  pub exposure: f64,
+  /// Fraction of the round trips with a positive pnl.
+  pub win_rate: Option<f64>,
+  /// Gross profit over gross loss of the round trips.
+  pub profit_factor: Option<f64>,
+  pub average_holding_seconds: Option<f64>,
+  /// Sharpe ratio of the returns between equity points, not annualized.
+  pub sharpe: Option<f64>,
+  pub max_drawdown: f64,
+  pub max_drawdown_percent: f64,
+  pub longest_drawdown_seconds: f64,
+  pub decisions: Decisions,
+  pub fills: Vec<Fill>,
+  pub round_trips: Vec<RoundTrip>,
+  pub equity: Vec<Equity>,
+}
+
+impl BacktestReport {
+  pub fn new(
+    initial_equity: f64,
+    decisions: Decisions,
+    fills: Vec<Fill>,
+    equity: Vec<Equity>,
+  ) -> Self {
+    let mut returns = Returns::new(ReturnKind::Simple);
+    let mut sharpe = Sharpe::expanding();
+    let mut drawdown = Drawdown::new();
+    let mut ratio = None;
+    let mut last_drawdown = None;
+    for point in &equity {
+      if let Some(ret) = returns.update((*point).into()) {
+        ratio = sharpe.update(ret);
+      }
+      last_drawdown = drawdown.update((*point).into());
+    }
+
+    let round_trips = round_trips(&fills);
+    let profit: f64 = round_trips.iter().map(|t| t.pnl.max(0.)).sum();
+    let loss: f64 = round_trips.iter().map(|t| (-t.pnl).max(0.)).sum();
+    let count = round_trips.len();
+    let wins = round_trips.iter().filter(|t| t.pnl > 0.).count();
+    let holding: Duration =
+      round_trips.iter().map(RoundTrip::holding_time).sum();
+    let notional: f64 = fills.iter().map(|f| f.quantity * f.price).sum();
+
+    let final_equity = equity.last().map_or(initial_equity, |e| e.value);
+    Self {
+      initial_equity,
+      final_equity,
+      pnl: final_equity - initial_equity,
+      fees: fills.iter().map(|f| f.fee).sum(),
+      turnover: notional / initial_equity,
+      exposure: exposure(&fills, &equity),
+      win_rate: (count > 0).then(|| wins as f64 / count as f64),
+      profit_factor: (loss > 0.).then(|| profit / loss),
+      average_holding_seconds: (count > 0)
+        .then(|| seconds(holding) / count as f64),
+      sharpe: ratio,
+      max_drawdown: last_drawdown.as_ref().map_or(0., |d| d.max),
+      max_drawdown_percent: last_drawdown
+        .as_ref()
+        .map_or(0., |d| d.max_percent),
+      longest_drawdown_seconds: last_drawdown
+        .map_or(0., |d| seconds(d.max_duration)),
+      decisions,
+      fills,
+      round_trips,
+      equity,
+    }
+  }
+
+  pub fn to_json(&self) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(self)
+  }
+
+  pub fn write_equity_csv(&self, writer: impl io::Write) -> csv::Result<()> {
+    write_csv(writer, &self.equity)
+  }
+
+  pub fn write_fills_csv(&self, writer: impl io::Write) -> csv::Result<()> {
+    write_csv(writer, &self.fills)
+  }
+
+  pub fn write_round_trips_csv(
+    &self,
+    writer: impl io::Write,
+  ) -> csv::Result<()> {
+    write_csv(writer, &self.round_trips)
+  }
+}
+
+fn write_csv<T: Serialize>(
+  writer: impl io::Write,
+  rows: &[T],
+) -> csv::Result<()> {
+  let mut writer = csv::Writer::from_writer(writer);
+  for row in rows {
+    writer.serialize(row)?;
+  }
+  writer.flush()?;
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  fn start() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap()
+  }
+
+  fn fill(minute: i64, side: Side, quantity: f64, price: f64) -> Fill {
+    Fill {
+      symbol: "BTCUSDT".to_owned(),
+      side,
+      quantity,
+      price,
+      fee: quantity * price * 0.001,
+      timestamp: start() + Duration::minutes(minute),
+    }
+  }
+
+  fn report() -> BacktestReport {
+    let fills = vec![
+      fill(1, Side::Buy, 2., 100.),
+      fill(3, Side::Sell, 1., 110.),
+      fill(5, Side::Sell, 2., 90.),
+      fill(7, Side::Buy, 1., 95.),
+    ];
+    let equity = [1_000., 1_000., 1_010., 1_019.6, 1_000., 978.5, 990., 985.]
+      .into_iter()
+      .enumerate()
+      .map(|(minute, value)| Equity {
+        value,
+        timestamp: start() + Duration::minutes(minute as i64),
+      })
+      .collect();
+    let decisions = Decisions {
+      buy: 2,
+      sell: 2,
+      hold: 3,
+    };
+    BacktestReport::new(1_000., decisions, fills, equity)
+  }
+
+  fn assert_close(actual: f64, expected: f64) {
+    assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+  }
+
+  #[test]
+  fn round_trips_first_in_first_out() {
+    let report = report();
+    let trips = &report.round_trips;
+    assert_eq!(trips.len(), 3);
+    // long 1 @ 100 -> 110, fees 0.1 + 0.11
+    assert_close(trips[0].pnl, 9.79);
+    // long 1 @ 100 -> 90, fees 0.1 + 0.09
+    assert_close(trips[1].pnl, -10.19);
+    assert_eq!(trips[1].holding_time(), Duration::minutes(4));
+    // short 1 @ 90 -> 95, fees 0.09 + 0.095
+    assert_eq!(trips[2].side, Side::Sell);
+    assert_close(trips[2].pnl, -5.185);
+
+    assert_eq!(report.win_rate, Some(1. / 3.));
+    assert_close(report.profit_factor.unwrap(), 9.79 / 15.375);
+    assert_eq!(report.average_holding_seconds, Some(160.));
+  }
+
+  #[test]
+  fn summary() {
+    let report = report();
+    assert_close(report.fees, 0.2 + 0.11 + 0.18 + 0.095);
+    assert_close(report.turnover, 585. / 1_000.);
+    // flat only before the first fill
+    assert_close(report.exposure, 6. / 7.);
+    assert_close(report.pnl, -15.);
+    assert_close(report.max_drawdown, 41.1);
+    assert_eq!(report.longest_drawdown_seconds, 240.);
+    assert!(report.sharpe.is_some());
+  }
+
+  #[test]
+  fn exports() {
+    let report = report();
+    let json: serde_json::Value =
+      serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["decisions"]["hold"], 3);
+    assert_eq!(json["round_trips"].as_array().unwrap().len(), 3);
+
+    let mut csv = vec![];
+    report.write_round_trips_csv(&mut csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+      lines.next(),
+      Some("symbol,side,quantity,entry_time,entry_price,exit_time,exit_price,fees,pnl")
+    );
+    assert_eq!(lines.count(), 3);
+
+    let mut csv = vec![];
+    report.write_equity_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 9);
+  }
+}
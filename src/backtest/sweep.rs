@@ -0,0 +1,302 @@
+//! Runs the backtest over a grid of parameters, optionally walking forward
+//! through the data, and ranks the results.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use chrono::{DateTime, Utc};
+
+use crate::actors::risk::calmar::calmar_ratio;
+use crate::backtest::report::BacktestReport;
+use crate::backtest::Backtest;
+use crate::binance_websocket::TickerMessage;
+use crate::policy_maker::PolicyParameters;
+
+/// What results are ranked by, higher is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objective {
+  Pnl,
+  Sharpe,
+  /// Annualized growth over the max percentage drawdown, annualized over
+  /// the time the equity curve spans.
+  Calmar,
+}
+
+impl Objective {
+  /// `None` when the report doesn't have enough data for the objective, or
+  /// the score isn't finite.
+  pub fn score(self, report: &BacktestReport) -> Option<f64> {
+    let score = match self {
+      Self::Pnl => Some(report.pnl),
+      Self::Sharpe => report.sharpe,
+      Self::Calmar => {
+        let (first, last) = (report.equity.first()?, report.equity.last()?);
+        let years = (last.timestamp - first.timestamp).num_milliseconds()
+          as f64
+          / (365. * 86_400_000.);
+        calmar_ratio(
+          report.final_equity / report.initial_equity,
+          years,
+          report.max_drawdown_percent,
+        )
+      }
+    };
+    score.filter(|score| score.is_finite())
+  }
+}
+
+/// Every combination of the listed values is a candidate.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+  windows: Vec<usize>,
+  thresholds: Vec<f64>,
+  quantities: Vec<f64>,
+}
+
+impl Grid {
+  /// `None` if a list is empty or a window is zero.
+  pub fn new(
+    windows: Vec<usize>,
+    thresholds: Vec<f64>,
+    quantities: Vec<f64>,
+  ) -> Option<Self> {
+    let valid = !windows.is_empty()
+      && !thresholds.is_empty()
+      && !quantities.is_empty()
+      && !windows.contains(&0);
+    valid.then_some(Self {
+      windows,
+      thresholds,
+      quantities,
+    })
+  }
+
+  pub fn candidates(&self) -> Vec<Candidate> {
+    let mut candidates = vec![];
+    for &window in &self.windows {
+      for &threshold in &self.thresholds {
+        for &quantity in &self.quantities {
+          candidates.push(Candidate {
+            window,
+            parameters: PolicyParameters {
+              quantity,
+              threshold,
+            },
+          });
+        }
+      }
+    }
+    candidates
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+  pub window: usize,
+  pub parameters: PolicyParameters,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ranked {
+  pub candidate: Candidate,
+  pub score: Option<f64>,
+  pub report: BacktestReport,
+}
+
+/// Best candidate of an in-sample window and how it did on the following
+/// out-of-sample window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fold {
+  pub in_sample: Ranked,
+  pub out_of_sample: Ranked,
+}
+
+/// Runs backtests with the settings of `base` for every candidate of
+/// `grid`, each on its own thread with its own actix system.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+  base: Backtest,
+  grid: Grid,
+  objective: Objective,
+  threads: usize,
+}
+
+impl Sweep {
+  pub fn new(base: Backtest, grid: Grid, objective: Objective) -> Self {
+    Self {
+      base,
+      grid,
+      objective,
+      threads: thread::available_parallelism().map_or(1, |n| n.get()),
+    }
+  }
+
+  /// Number of backtests run at once, at least one.
+  pub fn with_threads(mut self, threads: usize) -> Self {
+    self.threads = threads.max(1);
+    self
+  }
+
+  /// Every candidate, best first. Candidates without a score come last.
+  /// Blocks until all backtests are done.
+  pub fn run(&self, data: &[(DateTime<Utc>, TickerMessage)]) -> Vec<Ranked> {
+    let candidates = self.grid.candidates();
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![]);
+    thread::scope(|scope| {
+      for _ in 0..self.threads.min(candidates.len()) {
+        scope.spawn(|| loop {
+          let i = next.fetch_add(1, Ordering::SeqCst);
+          let Some(&candidate) = candidates.get(i) else {
+            break;
+          };
+          let ranked = self.evaluate(candidate, data);
+          results
+            .lock()
+            .expect("no panics holding it")
+            .push((i, ranked));
+        });
+      }
+    });
+
+    let mut results = results.into_inner().expect("no panics holding it");
+    // ties keep the grid order
+    results.sort_by_key(|(i, _)| *i);
+    let mut results: Vec<_> = results.into_iter().map(|(_, r)| r).collect();
+    results.sort_by(|a, b| match (a.score, b.score) {
+      (Some(a), Some(b)) => b.total_cmp(&a),
+      (a, b) => b.is_some().cmp(&a.is_some()),
+    });
+    results
+  }
+
+  /// Sweeps `in_sample` events, then runs the best candidate on the next
+  /// `out_of_sample` events, moving forward by `out_of_sample` until the
+  /// data runs out. `None` if a window is empty.
+  pub fn walk_forward(
+    &self,
+    data: &[(DateTime<Utc>, TickerMessage)],
+    in_sample: usize,
+    out_of_sample: usize,
+  ) -> Option<Vec<Fold>> {
+    if in_sample == 0 || out_of_sample == 0 {
+      return None;
+    }
+    let mut folds = vec![];
+    let mut start = 0;
+    while start + in_sample + out_of_sample <= data.len() {
+      let split = start + in_sample;
+      let end = split + out_of_sample;
+      let best = self.run(&data[start..split]).into_iter().next()?;
+      folds.push(Fold {
+        out_of_sample: self.evaluate(best.candidate, &data[split..end]),
+        in_sample: best,
+      });
+      start += out_of_sample;
+    }
+    Some(folds)
+  }
+
+  fn evaluate(
+    &self,
+    candidate: Candidate,
+    data: &[(DateTime<Utc>, TickerMessage)],
+  ) -> Ranked {
+    let backtest = Backtest {
+      window: candidate.window,
+      parameters: candidate.parameters,
+      ..self.base.clone()
+    };
+    let report = actix::System::new().block_on(backtest.run(data.to_vec()));
+    Ranked {
+      candidate,
+      score: self.objective.score(&report),
+      report,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::risk::Equity;
+  use crate::backtest::report::Decisions;
+  use chrono::{Duration, TimeZone};
+
+  fn data() -> Vec<(DateTime<Utc>, TickerMessage)> {
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    (0..60)
+      .map(|i| {
+        let price = 100. + 10. * (i as f64 / 5.).sin() + i as f64 * 0.1;
+        let ticker = TickerMessage {
+          update_id: i,
+          symbol: "BTCUSDT".to_owned(),
+          best_bid_price: price,
+          best_ask_price: price,
+          ..Default::default()
+        };
+        (start + Duration::hours(i as i64), ticker)
+      })
+      .collect()
+  }
+
+  fn sweep(objective: Objective) -> Sweep {
+    let grid = Grid::new(vec![2, 3, 5], vec![0., 0.01], vec![1.]).unwrap();
+    Sweep::new(Backtest::new(1).unwrap(), grid, objective).with_threads(4)
+  }
+
+  #[test]
+  fn ranks_every_candidate() {
+    let ranked = sweep(Objective::Pnl).run(&data());
+    assert_eq!(ranked.len(), 6);
+    let scores: Vec<_> = ranked.iter().map(|r| r.score.unwrap()).collect();
+    assert!(scores.windows(2).all(|w| w[0] >= w[1]));
+    assert_eq!(scores[0], ranked[0].report.pnl);
+
+    let again = sweep(Objective::Pnl).with_threads(1).run(&data());
+    assert_eq!(ranked, again);
+  }
+
+  #[test]
+  fn objectives() {
+    let ranked = sweep(Objective::Sharpe).run(&data());
+    assert_eq!(ranked[0].score, ranked[0].report.sharpe);
+    let ranked = sweep(Objective::Calmar).run(&data());
+    assert!(ranked
+      .iter()
+      .all(|r| r.score.is_none() || r.report.max_drawdown_percent > 0.));
+  }
+
+  #[test]
+  fn no_score_for_negative_equity() {
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    let equity = [(0, 1_000.), (1, -500.)].map(|(days, value)| Equity {
+      value,
+      timestamp: start + Duration::days(days),
+    });
+    let report =
+      BacktestReport::new(1_000., Decisions::default(), vec![], equity.into());
+    assert!(report.max_drawdown_percent > 0.);
+    assert_eq!(Objective::Calmar.score(&report), None);
+  }
+
+  #[test]
+  fn walk_forward() {
+    let sweep = sweep(Objective::Pnl);
+    let folds = sweep.walk_forward(&data(), 30, 10).unwrap();
+    assert_eq!(folds.len(), 3);
+    for fold in &folds {
+      assert_eq!(fold.in_sample.report.equity.len(), 30);
+      assert_eq!(fold.out_of_sample.report.equity.len(), 10);
+      assert_eq!(fold.in_sample.candidate, fold.out_of_sample.candidate);
+    }
+    assert!(sweep.walk_forward(&data(), 0, 10).is_none());
+  }
+
+  #[test]
+  fn invalid_grid() {
+    assert!(Grid::new(vec![], vec![0.], vec![1.]).is_none());
+    assert!(Grid::new(vec![0, 3], vec![0.], vec![1.]).is_none());
+  }
+}
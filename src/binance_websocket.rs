@@ -1,4 +1,9 @@
-use crate::util::{deserialize_from_str, tls_web_client};
+use crate::correlation::{Correlated, Correlation};
+use crate::metrics::Metrics;
+use crate::util::{
+  deserialize_from_str, deserialize_millis, serialize_millis,
+  serialize_to_string, tls_web_client,
+};
 use actix::{Message, Recipient};
 use actix_codec::Framed;
 use awc::error::WsClientError;
@@ -6,42 +11,82 @@ use awc::ws;
 use awc::ws::Codec;
 use awc::BoxedSocket;
 use awc::Client;
+use chrono::{DateTime, Utc};
 use futures_util::StreamExt;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json;
+use std::time::{Duration, Instant};
 
+/// Payload of the combined stream endpoint.
 #[derive(Deserialize)]
-struct BinanceMessage {
-  data: BinanceMessageContent,
+pub(crate) struct BinanceMessage {
+  pub(crate) data: BinanceMessageContent,
 }
 
 #[derive(Deserialize)]
 #[serde(untagged)]
-enum BinanceMessageContent {
+pub(crate) enum BinanceMessageContent {
   BookTicker(TickerMessage),
   UserDataAccountUpdate(AccountUpdateMessage),
 }
 
 #[allow(non_snake_case)]
-#[derive(Message, Deserialize, Debug, Clone, Default)]
-#[rtype(result = "f64")]
+#[derive(Message, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[rtype(result = "()")]
 pub struct TickerMessage {
   #[serde(alias = "u")]
   pub update_id: u64,
   #[serde(alias = "s")]
   pub symbol: String,
-  #[serde(deserialize_with = "deserialize_from_str", alias = "b")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str",
+    alias = "b"
+  )]
   pub best_bid_price: f64,
-  #[serde(deserialize_with = "deserialize_from_str", alias = "B")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str",
+    alias = "B"
+  )]
   pub best_bid_qty: f64,
-  #[serde(deserialize_with = "deserialize_from_str", alias = "a")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str",
+    alias = "a"
+  )]
   pub best_ask_price: f64,
-  #[serde(deserialize_with = "deserialize_from_str", alias = "A")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str",
+    alias = "A"
+  )]
   pub best_ask_qty: f64,
+  /// Time the exchange generated the update, not sent on every stream.
+  #[serde(
+    default,
+    serialize_with = "serialize_millis",
+    deserialize_with = "deserialize_millis",
+    alias = "E"
+  )]
+  pub event_time: Option<DateTime<Utc>>,
+  /// When the ingestor received the update, not recorded.
+  #[serde(skip)]
+  pub received: Option<Instant>,
+}
+
+impl Correlated for TickerMessage {
+  /// Tickers not received live, e.g. replayed ones, count as received now.
+  fn correlation(&self) -> Option<Correlation> {
+    Some(Correlation {
+      id: self.update_id,
+      received: self.received.unwrap_or_else(Instant::now),
+    })
+  }
 }
 
 #[allow(non_snake_case)]
-#[derive(Message, Deserialize, Debug, Clone, Default)]
+#[derive(Message, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 #[rtype(result = "()")]
 pub struct AccountUpdateMessage {
   pub e: String,
@@ -50,22 +95,41 @@ pub struct AccountUpdateMessage {
   pub B: Vec<Balance>,
 }
 
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
 pub struct Balance {
   pub a: String,
-  #[serde(deserialize_with = "deserialize_from_str")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str"
+  )]
   pub f: f64,
-  #[serde(deserialize_with = "deserialize_from_str")]
+  #[serde(
+    serialize_with = "serialize_to_string",
+    deserialize_with = "deserialize_from_str"
+  )]
   pub l: f64,
 }
 
-#[derive(Default)]
+/// Combined stream of the testnet the ingestor connects to by default.
+pub const TESTNET_STREAM_URL: &str =
+  "wss://testnet.binance.vision/stream?streams=btcusdt@bookTicker";
+
 pub struct BinanceIngestor {
   client: Client,
+  url: String,
+  max_reconnects: u32,
+  reconnect_delay: Duration,
+  metrics: Option<Metrics>,
   book_ticker_recipients: Vec<Recipient<TickerMessage>>,
   user_data_account_update_recipients: Vec<Recipient<AccountUpdateMessage>>,
 }
 
+impl Default for BinanceIngestor {
+  fn default() -> Self {
+    Self::new(vec![], vec![])
+  }
+}
+
 impl BinanceIngestor {
   pub fn new(
     book_ticker_recipients: Vec<Recipient<TickerMessage>>,
@@ -73,11 +137,39 @@ impl BinanceIngestor {
   ) -> Self {
     Self {
       client: tls_web_client(),
+      url: TESTNET_STREAM_URL.to_owned(),
+      max_reconnects: 0,
+      reconnect_delay: Duration::from_secs(1),
+      metrics: None,
       book_ticker_recipients,
       user_data_account_update_recipients,
     }
   }
 
+  /// Combined stream endpoint to connect to, e.g. a local mock server.
+  pub fn with_url(mut self, url: impl Into<String>) -> Self {
+    self.url = url.into();
+    self
+  }
+
+  /// Connects again up to `max_reconnects` times, waiting `delay` before
+  /// each attempt, when the connection fails or the stream ends.
+  pub fn with_reconnects(
+    mut self,
+    max_reconnects: u32,
+    delay: Duration,
+  ) -> Self {
+    self.max_reconnects = max_reconnects;
+    self.reconnect_delay = delay;
+    self
+  }
+
+  /// Counts the stream messages, reconnects and deserialize errors.
+  pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+    self.metrics = Some(metrics.clone());
+    self
+  }
+
   async fn get_stream(
     &self,
   ) -> Result<Framed<BoxedSocket, Codec>, WsClientError> {
@@ -91,31 +183,72 @@ impl BinanceIngestor {
 
     self
       .client
-      .ws(
-        "wss://testnet.binance.vision/stream?streams=btcusdt@bookTicker"
-          .to_string(),
-      )
+      .ws(self.url.as_str())
       .connect()
       .await
       .map(|x| x.1)
   }
 
+  /// Streams until the connection ends and no reconnects are left.
   pub async fn run(self) {
-    let mut ws = self.get_stream().await.unwrap();
+    let mut attempts = 0;
+    loop {
+      match self.get_stream().await {
+        Ok(ws) => self.forward(ws).await,
+        Err(e) => tracing::error!(
+          url = self.url,
+          error = ?e,
+          "Binance ingestor couldn't connect"
+        ),
+      }
+      if attempts == self.max_reconnects {
+        break;
+      }
+      attempts += 1;
+      if let Some(metrics) = &self.metrics {
+        metrics.reconnected();
+      }
+      tracing::warn!(
+        url = self.url,
+        attempt = attempts,
+        max_reconnects = self.max_reconnects,
+        "Binance ingestor reconnecting"
+      );
+      actix_rt::time::sleep(self.reconnect_delay).await;
+    }
+  }
 
+  async fn forward(&self, mut ws: Framed<BoxedSocket, Codec>) {
     while let Some(msg) = ws.next().await {
+      if let Ok(ws::Frame::Close(reason)) = msg {
+        tracing::info!(?reason, "Binance stream closed");
+        break;
+      }
       if let Ok(ws::Frame::Text(txt)) = msg {
-        match serde_json::from_slice::<BinanceMessage>(&txt) {
+        let received = Instant::now();
+        let parsed = serde_json::from_slice::<BinanceMessage>(&txt);
+        if let Some(metrics) = &self.metrics {
+          match &parsed {
+            Ok(_) => metrics.handled("ingestor"),
+            Err(_) => metrics.deserialize_error(),
+          }
+        }
+        match parsed {
           Ok(v) => match v.data {
-            BinanceMessageContent::BookTicker(tm) => {
-              log::debug!("Received ticker message: {tm:?}");
+            BinanceMessageContent::BookTicker(mut tm) => {
+              tm.received = Some(received);
+              tracing::debug!(
+                correlation_id = tm.update_id,
+                ticker = ?tm,
+                "Received ticker message"
+              );
 
               for r in &self.book_ticker_recipients {
                 r.do_send(tm.clone());
               }
             }
             BinanceMessageContent::UserDataAccountUpdate(aum) => {
-              log::debug!("Received account update message: {aum:?}");
+              tracing::debug!(update = ?aum, "Received account update message");
 
               for r in &self.user_data_account_update_recipients {
                 r.do_send(aum.clone());
@@ -123,10 +256,41 @@ impl BinanceIngestor {
             }
           },
           Err(e) => {
-            log::error!("Binance ingestor couldn't deserialize message: {txt:?}. Error: {e:?}");
+            tracing::error!(
+              message = ?txt,
+              error = ?e,
+              "Binance ingestor couldn't deserialize message"
+            );
           }
         }
       }
     }
   }
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+
+  #[test]
+  fn event_time() {
+    let payload = r#"{"stream":"btcusdt@bookTicker","data":{"u":400900217,
+      "s":"BTCUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66",
+      "E":1664625600000}}"#;
+    let msg = serde_json::from_str::<BinanceMessage>(payload).unwrap();
+    let BinanceMessageContent::BookTicker(ticker) = msg.data else {
+      panic!("not a ticker");
+    };
+    assert_eq!(
+      ticker.event_time,
+      Some(Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap())
+    );
+
+    let without: TickerMessage = serde_json::from_str(
+      r#"{"u":1,"s":"BTCUSDT","b":"1","B":"1","a":"2","A":"1"}"#,
+    )
+    .unwrap();
+    assert_eq!(without.event_time, None);
+  }
+}
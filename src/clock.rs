@@ -0,0 +1,78 @@
+//! Source of the current time for actors, so that backtests and tests can
+//! replace the wall clock.
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+
+pub trait Clock: Send + Sync {
+  fn now(&self) -> DateTime<Utc>;
+}
+
+/// Shared handle to a clock, cheap to clone into every actor.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The system time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WallClock;
+
+impl Clock for WallClock {
+  fn now(&self) -> DateTime<Utc> {
+    Utc::now()
+  }
+}
+
+/// Time set by whoever drives the simulation, typically to the time of the
+/// event being replayed. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct SimulatedClock {
+  micros: Arc<AtomicI64>,
+}
+
+impl SimulatedClock {
+  pub fn new(now: DateTime<Utc>) -> Self {
+    let clock = Self::default();
+    clock.set(now);
+    clock
+  }
+
+  pub fn set(&self, now: DateTime<Utc>) {
+    self.micros.store(now.timestamp_micros(), Ordering::SeqCst);
+  }
+
+  /// Moves the clock to `now` unless it is already later, so out of order
+  /// events can't turn time back.
+  pub fn advance(&self, now: DateTime<Utc>) {
+    self
+      .micros
+      .fetch_max(now.timestamp_micros(), Ordering::SeqCst);
+  }
+}
+
+impl Clock for SimulatedClock {
+  fn now(&self) -> DateTime<Utc> {
+    DateTime::from_timestamp_micros(self.micros.load(Ordering::SeqCst))
+      .expect("set from a valid timestamp")
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{Duration, TimeZone};
+
+  #[test]
+  fn simulated() {
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    let clock = SimulatedClock::new(start);
+    let shared: SharedClock = Arc::new(clock.clone());
+
+    clock.advance(start + Duration::seconds(2));
+    clock.advance(start + Duration::seconds(1));
+    assert_eq!(shared.now(), start + Duration::seconds(2));
+
+    clock.set(start);
+    assert_eq!(shared.now(), start);
+  }
+}
@@ -0,0 +1,1336 @@
+//! Declarative engine configuration, in TOML or YAML: where the market data
+//! comes from, the symbols, the indicators and what each subscribes to, the
+//! strategy, the risk limits and the execution venue. [`EngineConfig::build`]
+//! validates the actor graph and starts it.
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use actix::{Actor, Addr, Context, Handler, Message, Recipient};
+use actix_web::dev::Server;
+use binance::config::Config;
+use chrono::Duration;
+use serde::Deserialize;
+
+use crate::actors::indicator::{IndicatorActor, Subscribe};
+use crate::actors::mid_price::{MidPrice, MidPriceActor, MidPriceIndicator};
+use crate::actors::moving_average::MovingAverageMessage;
+use crate::actors::returns::{ReturnKind, Returns};
+use crate::actors::risk::calmar::{Calmar, CalmarRatio};
+use crate::actors::risk::drawdown::{Drawdown, DrawdownReport};
+use crate::actors::risk::omega::{Omega, OmegaRatio};
+use crate::actors::risk::sharpe::{Return, Sharpe, SharpeRatio};
+use crate::actors::risk::sortino::{Sortino, SortinoRatio};
+use crate::actors::risk::value_at_risk::{
+  EwmaVar, HistoricalVar, ParametricVar, TailRisk,
+};
+use crate::actors::risk::Equity;
+use crate::algos::indicators::Indicator;
+use crate::algos::moving_average::{Dema, Ema, Hma, Sma, Tema, TimeSma, Wma};
+use crate::backtest::exchange::{GetReport, PaperExchange};
+use crate::backtest::report::BacktestReport;
+use crate::backtest::Flush;
+use crate::binance_websocket::BinanceIngestor;
+use crate::clock::{SharedClock, SimulatedClock, WallClock};
+use crate::control::feed::EventFeed;
+use crate::control::monitor::Monitor;
+use crate::control::ControlApi;
+use crate::correlation::Correlated;
+use crate::metrics::{Export, Metrics};
+use crate::policy_maker::{PolicyMakerActor, PolicyParameters};
+use crate::recorder;
+use crate::replay::{Replay, ReplayFormat, Speed};
+use crate::trade::{Kill, RiskLimits, TradeActor, TradingStatus};
+
+/// Node publishing the mid prices of the source's tickers.
+pub const MID_PRICE: &str = "mid_price";
+
+/// Node publishing the equity of the venue: the paper account, or the PnL
+/// of the live fills marked to the mid prices.
+pub const EQUITY: &str = "equity";
+
+const TESTNET_STREAMS: &str = "wss://testnet.binance.vision/stream";
+const MAINNET_STREAMS: &str = "wss://stream.binance.com:9443/stream";
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct EngineConfig {
+  pub symbols: Vec<String>,
+  pub source: SourceConfig,
+  /// In order: an indicator may only subscribe to the ones before it.
+  #[serde(default)]
+  pub indicators: Vec<IndicatorConfig>,
+  pub strategy: StrategyConfig,
+  #[serde(default)]
+  pub risk_limits: RiskLimits,
+  pub execution: ExecutionConfig,
+  pub control: Option<ControlConfig>,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SourceConfig {
+  /// Book tickers of the symbols, streamed from Binance.
+  Binance {
+    /// Combined stream endpoint, by default the venue's one for the
+    /// symbols.
+    url: Option<String>,
+    #[serde(default)]
+    reconnects: u32,
+    #[serde(default = "default_reconnect_delay_ms")]
+    reconnect_delay_ms: u64,
+  },
+  /// Recorded tickers, replayed in order.
+  Replay {
+    files: Vec<PathBuf>,
+    format: ReplayFormatConfig,
+    /// Multiple of real time, as fast as possible when missing.
+    speed: Option<f64>,
+  },
+}
+
+fn default_reconnect_delay_ms() -> u64 {
+  1_000
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayFormatConfig {
+  BinanceJsonLines,
+  /// Rows of the only configured symbol.
+  TopOfBookCsv,
+  RecordingJsonLines,
+  RecordingBinary,
+}
+
+impl ReplayFormatConfig {
+  /// `symbol` names the rows of top of book CSV files.
+  pub fn to_format(self, symbol: &str) -> ReplayFormat {
+    match self {
+      Self::BinanceJsonLines => ReplayFormat::BinanceJsonLines,
+      Self::TopOfBookCsv => ReplayFormat::TopOfBookCsv {
+        symbol: symbol.to_owned(),
+      },
+      Self::RecordingJsonLines => {
+        ReplayFormat::Recording(recorder::Format::JsonLines)
+      }
+      Self::RecordingBinary => {
+        ReplayFormat::Recording(recorder::Format::Binary)
+      }
+    }
+  }
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct IndicatorConfig {
+  pub name: String,
+  /// Node the indicator subscribes to.
+  #[serde(default = "mid_price")]
+  pub input: String,
+  #[serde(flatten)]
+  pub kind: IndicatorKind,
+}
+
+fn mid_price() -> String {
+  MID_PRICE.to_owned()
+}
+
+/// Indicator and its parameters, see the type of the same name.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndicatorKind {
+  Sma {
+    period: usize,
+  },
+  Ema {
+    period: usize,
+  },
+  Wma {
+    period: usize,
+  },
+  Dema {
+    period: usize,
+  },
+  Tema {
+    period: usize,
+  },
+  Hma {
+    period: usize,
+  },
+  TimeSma {
+    window_secs: i64,
+  },
+  Returns {
+    #[serde(default)]
+    log: bool,
+    /// Between the values closing every interval rather than every value.
+    interval_secs: Option<i64>,
+  },
+  Sharpe {
+    window: u32,
+    #[serde(default)]
+    risk_free_rate: f64,
+  },
+  Sortino {
+    window: u32,
+    #[serde(default)]
+    target: f64,
+  },
+  Omega {
+    window: u32,
+    #[serde(default)]
+    threshold: f64,
+  },
+  Calmar {
+    interval_secs: i64,
+  },
+  Drawdown,
+  HistoricalVar {
+    window: usize,
+    confidence: f64,
+  },
+  ParametricVar {
+    window: usize,
+    confidence: f64,
+  },
+  EwmaVar {
+    lambda: f64,
+    confidence: f64,
+  },
+}
+
+/// Messages a node publishes or subscribes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+  MidPrices,
+  Equity,
+  MovingAverages,
+  Returns,
+  SharpeRatios,
+  SortinoRatios,
+  OmegaRatios,
+  CalmarRatios,
+  Drawdowns,
+  TailRisks,
+}
+
+impl fmt::Display for Stream {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Self::MidPrices => "mid prices",
+      Self::Equity => "equity",
+      Self::MovingAverages => "moving averages",
+      Self::Returns => "returns",
+      Self::SharpeRatios => "Sharpe ratios",
+      Self::SortinoRatios => "Sortino ratios",
+      Self::OmegaRatios => "Omega ratios",
+      Self::CalmarRatios => "Calmar ratios",
+      Self::Drawdowns => "drawdowns",
+      Self::TailRisks => "tail risks",
+    })
+  }
+}
+
+/// `None` when out of range.
+fn seconds(secs: i64) -> Option<Duration> {
+  Duration::try_seconds(secs)
+}
+
+impl IndicatorKind {
+  pub fn input(&self) -> Stream {
+    match self {
+      Self::Sma { .. }
+      | Self::Ema { .. }
+      | Self::Wma { .. }
+      | Self::Dema { .. }
+      | Self::Tema { .. }
+      | Self::Hma { .. }
+      | Self::TimeSma { .. }
+      | Self::Returns { .. } => Stream::MidPrices,
+      Self::Drawdown => Stream::Equity,
+      Self::Sharpe { .. }
+      | Self::Sortino { .. }
+      | Self::Omega { .. }
+      | Self::Calmar { .. }
+      | Self::HistoricalVar { .. }
+      | Self::ParametricVar { .. }
+      | Self::EwmaVar { .. } => Stream::Returns,
+    }
+  }
+
+  pub fn output(&self) -> Stream {
+    match self {
+      Self::Sma { .. }
+      | Self::Ema { .. }
+      | Self::Wma { .. }
+      | Self::Dema { .. }
+      | Self::Tema { .. }
+      | Self::Hma { .. }
+      | Self::TimeSma { .. } => Stream::MovingAverages,
+      Self::Returns { .. } => Stream::Returns,
+      Self::Sharpe { .. } => Stream::SharpeRatios,
+      Self::Sortino { .. } => Stream::SortinoRatios,
+      Self::Omega { .. } => Stream::OmegaRatios,
+      Self::Calmar { .. } => Stream::CalmarRatios,
+      Self::Drawdown => Stream::Drawdowns,
+      Self::HistoricalVar { .. }
+      | Self::ParametricVar { .. }
+      | Self::EwmaVar { .. } => Stream::TailRisks,
+    }
+  }
+
+  /// Whether the indicator's constructor accepts the parameters.
+  fn is_valid(&self) -> bool {
+    match *self {
+      Self::Sma { period } => Sma::new(period).is_some(),
+      Self::Ema { period } => Ema::new(period).is_some(),
+      Self::Wma { period } => Wma::new(period).is_some(),
+      Self::Dema { period } => Dema::new(period).is_some(),
+      Self::Tema { period } => Tema::new(period).is_some(),
+      Self::Hma { period } => Hma::new(period).is_some(),
+      Self::TimeSma { window_secs } => {
+        seconds(window_secs).and_then(TimeSma::new).is_some()
+      }
+      Self::Returns { interval_secs, .. } => interval_secs.is_none_or(|secs| {
+        seconds(secs)
+          .and_then(|interval| Returns::sampled(ReturnKind::Simple, interval))
+          .is_some()
+      }),
+      Self::Sharpe { window, .. } => Sharpe::new(window).is_some(),
+      Self::Sortino { window, .. } => Sortino::new(window).is_some(),
+      Self::Omega { window, .. } => Omega::new(window).is_some(),
+      Self::Calmar { interval_secs } => {
+        seconds(interval_secs).and_then(Calmar::new).is_some()
+      }
+      Self::Drawdown => true,
+      Self::HistoricalVar { window, confidence } => {
+        HistoricalVar::new(window, confidence).is_some()
+      }
+      Self::ParametricVar { window, confidence } => {
+        ParametricVar::new(window, confidence).is_some()
+      }
+      Self::EwmaVar { lambda, confidence } => {
+        EwmaVar::new(lambda, confidence).is_some()
+      }
+    }
+  }
+}
+
+/// The trend following policy of [`PolicyMakerActor`].
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StrategyConfig {
+  /// Indicator publishing the moving average the policy follows.
+  pub moving_average: String,
+  #[serde(default)]
+  pub parameters: PolicyParameters,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "venue", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ExecutionConfig {
+  /// Fills every decision on a [`PaperExchange`].
+  Paper {
+    #[serde(default = "default_initial_cash")]
+    initial_cash: f64,
+    #[serde(default)]
+    fee_rate: f64,
+  },
+  /// Orders signed with the credentials of the environment, see
+  /// [`TradeActor::new`].
+  Testnet {
+    rest_url: Option<String>,
+  },
+  Mainnet {
+    rest_url: Option<String>,
+  },
+}
+
+fn default_initial_cash() -> f64 {
+  10_000.
+}
+
+/// The [`ControlApi`], with the event feed.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ControlConfig {
+  /// Address to serve on, e.g. `127.0.0.1:8080`.
+  pub bind: String,
+  /// Serves Prometheus metrics on `/metrics`.
+  #[serde(default)]
+  pub metrics: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+  Io(io::Error),
+  Parse(String),
+  /// Every problem found in the graph.
+  Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(e) => write!(f, "{e}"),
+      Self::Parse(e) => write!(f, "invalid configuration: {e}"),
+      Self::Invalid(problems) => {
+        write!(f, "invalid configuration:")?;
+        for problem in problems {
+          write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+      }
+    }
+  }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+  fn from(e: io::Error) -> Self {
+    Self::Io(e)
+  }
+}
+
+impl EngineConfig {
+  pub fn from_toml(config: &str) -> Result<Self, ConfigError> {
+    toml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+  }
+
+  pub fn from_yaml(config: &str) -> Result<Self, ConfigError> {
+    serde_yaml::from_str(config).map_err(|e| ConfigError::Parse(e.to_string()))
+  }
+
+  /// Reads a `.toml`, `.yaml` or `.yml` file.
+  pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+    let path = path.as_ref();
+    let config = std::fs::read_to_string(path)?;
+    match path.extension().and_then(|e| e.to_str()) {
+      Some("toml") => Self::from_toml(&config),
+      Some("yaml" | "yml") => Self::from_yaml(&config),
+      _ => Err(ConfigError::Parse(format!(
+        "{} is neither .toml nor .yaml",
+        path.display()
+      ))),
+    }
+  }
+
+  /// Reports every problem of the graph at once: unknown or misordered
+  /// subscriptions, mismatched message types, indicators nothing subscribes
+  /// to and invalid parameters.
+  pub fn validate(&self) -> Result<(), ConfigError> {
+    let mut problems = vec![];
+    if self.symbols.is_empty() {
+      problems.push("no symbols".to_owned());
+    }
+    for symbol in &self.symbols {
+      if symbol.is_empty()
+        || !symbol
+          .chars()
+          .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+      {
+        problems.push(format!("symbol `{symbol}` isn't like `BTCUSDT`"));
+      }
+    }
+    self.validate_source(&mut problems);
+
+    // streams published by the nodes defined so far
+    let mut streams =
+      HashMap::from([(MID_PRICE, Stream::MidPrices), (EQUITY, Stream::Equity)]);
+    for indicator in &self.indicators {
+      let name = indicator.name.as_str();
+      if streams.contains_key(name) {
+        problems.push(format!("indicator `{name}` is defined twice"));
+      }
+      match streams.get(indicator.input.as_str()) {
+        Some(&stream) if stream != indicator.kind.input() => {
+          problems.push(format!(
+            "indicator `{name}` takes {} but `{}` publishes {stream}",
+            indicator.kind.input(),
+            indicator.input
+          ))
+        }
+        Some(_) => {}
+        None if self.indicators.iter().any(|i| i.name == indicator.input) => {
+          problems.push(format!(
+            "indicator `{name}` subscribes to `{}`, defined after it",
+            indicator.input
+          ))
+        }
+        None => problems.push(format!(
+          "indicator `{name}` subscribes to unknown `{}`",
+          indicator.input
+        )),
+      }
+      if !indicator.kind.is_valid() {
+        problems.push(format!("indicator `{name}` has invalid parameters"));
+      }
+      streams.entry(name).or_insert(indicator.kind.output());
+    }
+
+    let moving_average = &self.strategy.moving_average;
+    match streams.get(moving_average.as_str()) {
+      Some(Stream::MovingAverages) => {}
+      Some(stream) => problems.push(format!(
+        "the strategy takes moving averages but `{moving_average}` \
+         publishes {stream}"
+      )),
+      None => problems.push(format!(
+        "the strategy subscribes to unknown `{moving_average}`"
+      )),
+    }
+    let parameters = self.strategy.parameters;
+    if PolicyParameters::new(parameters.quantity, parameters.threshold)
+      .is_none()
+    {
+      problems.push("the strategy has invalid parameters".to_owned());
+    }
+
+    for indicator in &self.indicators {
+      let subscribed = self.indicators.iter().any(|i| i.input == indicator.name)
+        || *moving_average == indicator.name
+        // the monitor keeps all but the returns
+        || (self.control.is_some()
+          && indicator.kind.output() != Stream::Returns);
+      if !subscribed {
+        problems
+          .push(format!("indicator `{}` has no subscribers", indicator.name));
+      }
+    }
+    let paper = matches!(self.execution, ExecutionConfig::Paper { .. });
+    if !paper
+      && self.control.is_none()
+      && self.indicators.iter().any(|i| i.input == EQUITY)
+    {
+      // the live PnL is marked by the event feed of the control API
+      problems
+        .push("the equity needs a paper venue or the control API".to_owned());
+    }
+
+    self.validate_execution(&mut problems);
+    if problems.is_empty() {
+      Ok(())
+    } else {
+      Err(ConfigError::Invalid(problems))
+    }
+  }
+
+  fn validate_source(&self, problems: &mut Vec<String>) {
+    if let SourceConfig::Replay {
+      files,
+      format,
+      speed,
+    } = &self.source
+    {
+      if files.is_empty() {
+        problems.push("no files to replay".to_owned());
+      }
+      if speed.is_some_and(|speed| speed.is_nan() || speed <= 0.) {
+        problems.push("the replay speed isn't positive".to_owned());
+      }
+      if *format == ReplayFormatConfig::TopOfBookCsv && self.symbols.len() != 1
+      {
+        problems.push("top of book CSV files need a single symbol".to_owned());
+      }
+    }
+  }
+
+  fn validate_execution(&self, problems: &mut Vec<String>) {
+    let limits = self.risk_limits;
+    for (name, limit) in [
+      ("max_order_quantity", limits.max_order_quantity),
+      ("max_position", limits.max_position),
+    ] {
+      if limit.is_some_and(|limit| limit.is_nan() || limit <= 0.) {
+        problems.push(format!("the {name} risk limit isn't positive"));
+      }
+    }
+    if limits
+      .max_order_quantity
+      .is_some_and(|max| self.strategy.parameters.quantity > max)
+    {
+      problems.push(
+        "the strategy quantity exceeds the max_order_quantity risk limit"
+          .to_owned(),
+      );
+    }
+    if let ExecutionConfig::Paper {
+      initial_cash,
+      fee_rate,
+    } = self.execution
+    {
+      if initial_cash.is_nan() || initial_cash <= 0. {
+        problems.push("the paper initial cash isn't positive".to_owned());
+      }
+      if !(0. ..1.).contains(&fee_rate) {
+        problems.push("the paper fee rate isn't within [0, 1)".to_owned());
+      }
+      if self.control.is_some() {
+        problems
+          .push("the control API needs a testnet or mainnet venue".to_owned());
+      }
+    }
+  }
+
+  /// Validates the graph, then starts its actors, subscribers first, and
+  /// binds the control API. Must be called within an actix system.
+  pub fn build(&self) -> Result<Engine, ConfigError> {
+    self.validate()?;
+    Builder::new(self).build()
+  }
+}
+
+/// Combined book ticker streams of `symbols` on the mainnet or testnet
+/// endpoint.
+pub fn stream_url(symbols: &[String], mainnet: bool) -> String {
+  let base = if mainnet {
+    MAINNET_STREAMS
+  } else {
+    TESTNET_STREAMS
+  };
+  let streams = symbols
+    .iter()
+    .map(|symbol| format!("{}@bookTicker", symbol.to_lowercase()))
+    .collect::<Vec<_>>()
+    .join("/");
+  format!("{base}?streams={streams}")
+}
+
+/// Where an [`Engine`] sends its decisions.
+#[derive(Clone)]
+pub enum Execution {
+  Live(Addr<TradeActor>),
+  Paper(Addr<PaperExchange>),
+}
+
+enum Source {
+  Binance(BinanceIngestor),
+  Replay(Replay),
+}
+
+/// A started actor graph, waiting for market data.
+pub struct Engine {
+  source: Source,
+  execution: Execution,
+  policy_maker: Addr<PolicyMakerActor>,
+  control: Option<Server>,
+  /// Actors the market data goes through, in order.
+  nodes: Vec<Recipient<Flush>>,
+}
+
+/// What an [`Engine`] left its venue with.
+#[derive(Debug)]
+pub enum Settlement {
+  /// Status once the kill switch canceled the open orders.
+  Live(TradingStatus),
+  Paper(BacktestReport),
+}
+
+impl Engine {
+  pub fn execution(&self) -> &Execution {
+    &self.execution
+  }
+
+  pub fn policy_maker(&self) -> &Addr<PolicyMakerActor> {
+    &self.policy_maker
+  }
+
+  /// Serves the control API and feeds the market data until the source
+  /// ends, which a live stream only does once out of reconnects.
+  pub async fn run(self) -> io::Result<()> {
+    if let Some(server) = self.control {
+      actix::spawn(server);
+    }
+    match self.source {
+      Source::Binance(ingestor) => {
+        ingestor.run().await;
+        Ok(())
+      }
+      Source::Replay(replay) => replay.run().await.map(|_| ()),
+    }
+  }
+
+  /// Runs until the source ends or `shutdown` resolves, e.g. on a signal,
+  /// waits for the graph to handle the market data fed so far, then settles
+  /// the venue: the kill switch cancels the open live orders, and a paper
+  /// venue reports.
+  pub async fn run_until(
+    self,
+    shutdown: impl Future<Output = ()>,
+  ) -> io::Result<Settlement> {
+    let execution = self.execution.clone();
+    let nodes = self.nodes.clone();
+    tokio::select! {
+      res = self.run() => res?,
+      () = shutdown => tracing::info!("Engine shutting down"),
+    }
+    // mailboxes are FIFO, flushing in graph order settles the data
+    for node in nodes {
+      node.send(Flush).await.map_err(io::Error::other)?;
+    }
+    Ok(match execution {
+      Execution::Live(trader) => {
+        Settlement::Live(trader.send(Kill).await.map_err(io::Error::other)?)
+      }
+      Execution::Paper(exchange) => Settlement::Paper(
+        exchange.send(GetReport).await.map_err(io::Error::other)?,
+      ),
+    })
+  }
+}
+
+/// Recipients subscribed to each node, boxed `Recipient<M>`s of the
+/// message `M` the node publishes.
+#[derive(Default)]
+struct Subscriptions(HashMap<String, Vec<Box<dyn Any>>>);
+
+impl Subscriptions {
+  fn add<M>(&mut self, node: &str, recipient: Recipient<M>)
+  where
+    M: Message + Send + 'static,
+    M::Result: Send,
+  {
+    self
+      .0
+      .entry(node.to_owned())
+      .or_default()
+      .push(Box::new(recipient));
+  }
+
+  fn take<M>(&mut self, node: &str) -> Vec<Recipient<M>>
+  where
+    M: Message + Send + 'static,
+    M::Result: Send,
+  {
+    self
+      .0
+      .remove(node)
+      .unwrap_or_default()
+      .into_iter()
+      .map(|r| *r.downcast().expect("stream types are validated"))
+      .collect()
+  }
+}
+
+struct Builder<'a> {
+  config: &'a EngineConfig,
+  clock: SharedClock,
+  simulated: Option<SimulatedClock>,
+  metrics: Option<Metrics>,
+  subscriptions: Subscriptions,
+  observers: Option<(Addr<Monitor>, Addr<EventFeed>)>,
+  api: Option<ControlApi>,
+  /// Indicators started so far, last first.
+  indicators: Vec<Recipient<Flush>>,
+}
+
+impl<'a> Builder<'a> {
+  fn new(config: &'a EngineConfig) -> Self {
+    // replays run on the time of their tickers
+    let simulated = matches!(config.source, SourceConfig::Replay { .. })
+      .then(SimulatedClock::default);
+    let clock: SharedClock = match &simulated {
+      Some(clock) => Arc::new(clock.clone()),
+      None => Arc::new(WallClock),
+    };
+    let metrics = config
+      .control
+      .as_ref()
+      .is_some_and(|control| control.metrics)
+      .then(Metrics::new);
+    Self {
+      config,
+      clock,
+      simulated,
+      metrics,
+      subscriptions: Subscriptions::default(),
+      observers: None,
+      api: None,
+      indicators: vec![],
+    }
+  }
+
+  fn build(mut self) -> Result<Engine, ConfigError> {
+    let config = self.config;
+    if config.control.is_some() {
+      let monitor = Monitor::new().with_clock(self.clock.clone()).start();
+      let mut feed = EventFeed::new().with_clock(self.clock.clone());
+      if let Some(metrics) = &self.metrics {
+        feed = feed.with_metrics(metrics);
+      }
+      self.observers = Some((monitor, feed.start()));
+    }
+
+    let execution = self.execution();
+    let mut decisions = vec![match &execution {
+      Execution::Live(trader) => trader.clone().recipient(),
+      Execution::Paper(exchange) => exchange.clone().recipient(),
+    }];
+    if let Some((_, feed)) = &self.observers {
+      decisions.push(feed.clone().recipient());
+    }
+    let mut policy_maker = PolicyMakerActor::new(decisions)
+      .with_parameters(config.strategy.parameters);
+    if let Some(metrics) = &self.metrics {
+      policy_maker = policy_maker.with_metrics(metrics);
+    }
+    let policy_maker = policy_maker.start();
+    self.subscriptions.add::<MovingAverageMessage>(
+      &config.strategy.moving_average,
+      policy_maker.clone().recipient(),
+    );
+    self
+      .subscriptions
+      .add::<MidPrice>(MID_PRICE, policy_maker.clone().recipient());
+
+    match &execution {
+      Execution::Paper(exchange) => self
+        .subscriptions
+        .add::<MidPrice>(MID_PRICE, exchange.clone().recipient()),
+      Execution::Live(trader) => {
+        if let Some((monitor, feed)) = &self.observers {
+          self
+            .subscriptions
+            .add::<MidPrice>(MID_PRICE, monitor.clone().recipient());
+          self
+            .subscriptions
+            .add::<MidPrice>(MID_PRICE, feed.clone().recipient());
+          self.api = Some(ControlApi::new(
+            monitor.clone(),
+            trader.clone(),
+            policy_maker.clone(),
+          ));
+        }
+      }
+    }
+    if let Some(metrics) = &self.metrics {
+      let interval = StdDuration::from_secs(5);
+      metrics.watch_mailbox("policy_maker", policy_maker.clone(), interval);
+      if let Execution::Live(trader) = &execution {
+        metrics.watch_mailbox("trade", trader.clone(), interval);
+      }
+    }
+
+    for indicator in config.indicators.iter().rev() {
+      self.indicator(indicator);
+    }
+    // the venue started before the nodes subscribing to its equity
+    for recipient in self.subscriptions.take::<Equity>(EQUITY) {
+      match (&execution, &self.observers) {
+        (Execution::Paper(exchange), _) => {
+          exchange.do_send(Subscribe(recipient))
+        }
+        (Execution::Live(_), Some((_, feed))) => {
+          feed.do_send(Subscribe(recipient))
+        }
+        (Execution::Live(_), None) => unreachable!("equity is validated"),
+      }
+    }
+
+    let mut mid_price = MidPriceActor::new(
+      MidPriceIndicator::new(self.clock.clone()),
+      self.subscriptions.take(MID_PRICE),
+    );
+    if let Some(metrics) = &self.metrics {
+      mid_price = mid_price.with_metrics(metrics, MID_PRICE);
+    }
+    let mid_price = mid_price.start();
+    let mut nodes = vec![mid_price.clone().recipient()];
+    nodes.extend(self.indicators.drain(..).rev());
+    nodes.push(policy_maker.clone().recipient());
+    if let Execution::Paper(exchange) = &execution {
+      nodes.push(exchange.clone().recipient());
+    }
+
+    let control = match (self.api.take(), &config.control, &self.observers) {
+      (Some(mut api), Some(control), Some((_, feed))) => {
+        api = api
+          .with_actor(MID_PRICE, mid_price.clone())
+          .with_feed(feed.clone());
+        if let Some(metrics) = &self.metrics {
+          api = api.with_metrics(metrics.clone());
+        }
+        Some(api.bind(control.bind.as_str())?)
+      }
+      _ => None,
+    };
+
+    Ok(Engine {
+      source: self.source(mid_price),
+      execution,
+      policy_maker,
+      control,
+      nodes,
+    })
+  }
+
+  fn execution(&self) -> Execution {
+    let limits = self.config.risk_limits;
+    let (config, rest_url) = match &self.config.execution {
+      ExecutionConfig::Paper {
+        initial_cash,
+        fee_rate,
+      } => {
+        let exchange =
+          PaperExchange::new(self.clock.clone(), *initial_cash, *fee_rate)
+            .with_limits(limits);
+        return Execution::Paper(exchange.start());
+      }
+      ExecutionConfig::Testnet { rest_url } => (Config::testnet(), rest_url),
+      ExecutionConfig::Mainnet { rest_url } => (Config::default(), rest_url),
+    };
+    let config = match rest_url {
+      Some(url) => config.set_rest_api_endpoint(url.as_str()),
+      None => config,
+    };
+    let mut trader = TradeActor::new().with_config(config).with_limits(limits);
+    if let Some((_, feed)) = &self.observers {
+      trader = trader.with_subscribers(vec![feed.clone().recipient()]);
+    }
+    if let Some(metrics) = &self.metrics {
+      trader = trader.with_metrics(metrics);
+    }
+    Execution::Live(trader.start())
+  }
+
+  fn indicator(&mut self, config: &IndicatorConfig) {
+    match config.kind {
+      IndicatorKind::Sma { period } => {
+        self.moving_average(config, Sma::new(period))
+      }
+      IndicatorKind::Ema { period } => {
+        self.moving_average(config, Ema::new(period))
+      }
+      IndicatorKind::Wma { period } => {
+        self.moving_average(config, Wma::new(period))
+      }
+      IndicatorKind::Dema { period } => {
+        self.moving_average(config, Dema::new(period))
+      }
+      IndicatorKind::Tema { period } => {
+        self.moving_average(config, Tema::new(period))
+      }
+      IndicatorKind::Hma { period } => {
+        self.moving_average(config, Hma::new(period))
+      }
+      IndicatorKind::TimeSma { window_secs } => {
+        self.moving_average(config, seconds(window_secs).and_then(TimeSma::new))
+      }
+      IndicatorKind::Returns { log, interval_secs } => {
+        let kind = if log {
+          ReturnKind::Log
+        } else {
+          ReturnKind::Simple
+        };
+        let returns = match interval_secs {
+          Some(secs) => {
+            seconds(secs).and_then(|interval| Returns::sampled(kind, interval))
+          }
+          None => Some(Returns::new(kind)),
+        };
+        self.start::<_, MidPrice, Return>(config, returns)
+      }
+      IndicatorKind::Sharpe {
+        window,
+        risk_free_rate,
+      } => self.risk::<_, SharpeRatio>(
+        config,
+        Sharpe::new(window).map(|s| s.with_risk_free_rate(risk_free_rate)),
+      ),
+      IndicatorKind::Sortino { window, target } => self
+        .risk::<_, SortinoRatio>(
+          config,
+          Sortino::new(window).map(|s| s.with_target(target)),
+        ),
+      IndicatorKind::Omega { window, threshold } => self.risk::<_, OmegaRatio>(
+        config,
+        Omega::new(window).map(|o| o.with_threshold(threshold)),
+      ),
+      IndicatorKind::Calmar { interval_secs } => self.risk::<_, CalmarRatio>(
+        config,
+        seconds(interval_secs).and_then(Calmar::new),
+      ),
+      IndicatorKind::Drawdown => {
+        self.observe::<DrawdownReport>(config);
+        self.start::<_, Equity, DrawdownReport>(config, Some(Drawdown::new()))
+      }
+      IndicatorKind::HistoricalVar { window, confidence } => {
+        self.risk::<_, TailRisk>(config, HistoricalVar::new(window, confidence))
+      }
+      IndicatorKind::ParametricVar { window, confidence } => {
+        self.risk::<_, TailRisk>(config, ParametricVar::new(window, confidence))
+      }
+      IndicatorKind::EwmaVar { lambda, confidence } => {
+        self.risk::<_, TailRisk>(config, EwmaVar::new(lambda, confidence))
+      }
+    }
+  }
+
+  fn moving_average<I>(
+    &mut self,
+    config: &IndicatorConfig,
+    indicator: Option<I>,
+  ) where
+    I: Indicator + Unpin + 'static,
+    I::Input: From<MidPrice>,
+    MovingAverageMessage: From<I::Output>,
+  {
+    if let Some((monitor, _)) = &self.observers {
+      self
+        .subscriptions
+        .add::<MovingAverageMessage>(&config.name, monitor.clone().recipient());
+    }
+    self.start::<_, MidPrice, MovingAverageMessage>(config, indicator)
+  }
+
+  fn risk<I, O>(&mut self, config: &IndicatorConfig, indicator: Option<I>)
+  where
+    I: Indicator + Unpin + 'static,
+    I::Input: From<Return>,
+    O: Message<Result = ()>
+      + Send
+      + From<I::Output>
+      + Clone
+      + Correlated
+      + Export
+      + Unpin
+      + 'static,
+    Monitor: Handler<O>,
+    EventFeed: Handler<O>,
+  {
+    self.observe::<O>(config);
+    self.start::<_, Return, O>(config, indicator)
+  }
+
+  /// Subscribes the monitor and the event feed to a risk indicator.
+  fn observe<O>(&mut self, config: &IndicatorConfig)
+  where
+    O: Message<Result = ()> + Send + 'static,
+    Monitor: Handler<O>,
+    EventFeed: Handler<O>,
+  {
+    if let Some((monitor, feed)) = &self.observers {
+      self
+        .subscriptions
+        .add::<O>(&config.name, monitor.clone().recipient());
+      self
+        .subscriptions
+        .add::<O>(&config.name, feed.clone().recipient());
+    }
+  }
+
+  /// Starts an indicator publishing to the nodes subscribed to it, then
+  /// subscribes it to its input.
+  fn start<I, In, O>(&mut self, config: &IndicatorConfig, indicator: Option<I>)
+  where
+    I: Indicator + Unpin + 'static,
+    I::Input: From<In>,
+    In: Message<Result = ()> + Correlated + Send + 'static,
+    IndicatorActor<I, O>:
+      Actor<Context = Context<IndicatorActor<I, O>>> + Handler<In>,
+    O: Message
+      + Send
+      + From<I::Output>
+      + Clone
+      + Correlated
+      + Export
+      + Unpin
+      + 'static,
+    O::Result: Send,
+  {
+    let indicator = indicator.expect("parameters are validated");
+    let mut actor =
+      IndicatorActor::new(indicator, self.subscriptions.take(&config.name));
+    if let Some(metrics) = &self.metrics {
+      actor = actor.with_metrics(metrics, &config.name);
+    }
+    let addr = actor.start();
+    self
+      .subscriptions
+      .add::<In>(&config.input, addr.clone().recipient());
+    self.indicators.push(addr.clone().recipient::<Flush>());
+    self.api = self
+      .api
+      .take()
+      .map(|api| api.with_actor(&config.name, addr));
+  }
+
+  fn source(&self, mid_price: Addr<MidPriceActor>) -> Source {
+    let recipients = vec![mid_price.recipient()];
+    match &self.config.source {
+      SourceConfig::Binance {
+        url,
+        reconnects,
+        reconnect_delay_ms,
+      } => {
+        let url = url.clone().unwrap_or_else(|| self.stream_url());
+        let mut ingestor = BinanceIngestor::new(recipients, vec![])
+          .with_url(url)
+          .with_reconnects(
+            *reconnects,
+            StdDuration::from_millis(*reconnect_delay_ms),
+          );
+        if let Some(metrics) = &self.metrics {
+          ingestor = ingestor.with_metrics(metrics);
+        }
+        Source::Binance(ingestor)
+      }
+      SourceConfig::Replay {
+        files,
+        format,
+        speed,
+      } => {
+        let format = format.to_format(&self.config.symbols[0]);
+        let speed = speed.map_or(Speed::AsFastAsPossible, Speed::Scaled);
+        let replay = Replay::new(files.iter().cloned(), format, recipients)
+          .with_speed(speed)
+          .expect("speed is validated");
+        let replay = match &self.simulated {
+          Some(clock) => replay.with_clock(clock.clone()),
+          None => replay,
+        };
+        Source::Replay(replay)
+      }
+    }
+  }
+
+  fn stream_url(&self) -> String {
+    let mainnet =
+      matches!(self.config.execution, ExecutionConfig::Mainnet { .. });
+    stream_url(&self.config.symbols, mainnet)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backtest::exchange::GetReport;
+  use crate::mock::{self, PRICES};
+
+  const EXAMPLE: &str = include_str!("../examples/engine.toml");
+
+  fn paper(indicators: &str, moving_average: &str) -> String {
+    format!(
+      r#"
+symbols = ["BTCUSDT"]
+source = {{ kind = "replay", files = ["ticks.jsonl"], format = "binance_json_lines" }}
+strategy = {{ moving_average = "{moving_average}" }}
+execution = {{ venue = "paper", initial_cash = 1000.0 }}
+{indicators}"#
+    )
+  }
+
+  fn problems(config: &str) -> Vec<String> {
+    match EngineConfig::from_toml(config).unwrap().validate() {
+      Err(ConfigError::Invalid(problems)) => problems,
+      res => panic!("{res:?}"),
+    }
+  }
+
+  #[test]
+  fn example_is_valid() {
+    let config = EngineConfig::from_toml(EXAMPLE).unwrap();
+    config.validate().unwrap();
+    assert_eq!(config.indicators[2].input, "returns");
+    assert_eq!(config.indicators[0].kind, IndicatorKind::Sma { period: 3 });
+    assert_eq!(
+      Builder::new(&config).stream_url(),
+      "wss://testnet.binance.vision/stream?streams=btcusdt@bookTicker"
+    );
+  }
+
+  #[test]
+  fn reads_yaml() {
+    let yaml = r#"
+symbols: [BTCUSDT]
+source: { kind: binance, reconnects: 5 }
+indicators:
+  - { name: moving_average, kind: sma, period: 3 }
+  - { name: returns, kind: returns, log: true }
+  - { name: sharpe, kind: sharpe, input: returns, window: 100 }
+strategy:
+  moving_average: moving_average
+  parameters: { quantity: 0.001, threshold: 0.0 }
+risk_limits: { max_order_quantity: 0.01, max_position: 0.05 }
+execution: { venue: testnet }
+control: { bind: "127.0.0.1:8080", metrics: true }
+"#;
+    assert_eq!(
+      EngineConfig::from_yaml(yaml).unwrap(),
+      EngineConfig::from_toml(EXAMPLE).unwrap()
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_fields() {
+    let config = EXAMPLE.replace("reconnects = 5", "reconnect = 5");
+    assert!(matches!(
+      EngineConfig::from_toml(&config),
+      Err(ConfigError::Parse(e)) if e.contains("reconnect")
+    ));
+  }
+
+  #[test]
+  fn reports_every_problem() {
+    let indicators = r#"
+[[indicators]]
+name = "sharpe"
+kind = "sharpe"
+input = "returns"
+window = 10
+
+[[indicators]]
+name = "returns"
+kind = "returns"
+interval_secs = 9223372036854775807
+
+[[indicators]]
+name = "slow"
+kind = "time_sma"
+window_secs = 9223372036854775807
+
+[[indicators]]
+name = "average"
+kind = "ema"
+input = "returns"
+period = 0
+
+[[indicators]]
+name = "orphan"
+kind = "drawdown"
+input = "prices"
+
+[[indicators]]
+name = "price_drawdown"
+kind = "drawdown"
+"#;
+    assert_eq!(
+      problems(&paper(indicators, "returns")),
+      [
+        "indicator `sharpe` subscribes to `returns`, defined after it",
+        "indicator `returns` has invalid parameters",
+        "indicator `slow` has invalid parameters",
+        "indicator `average` takes mid prices but `returns` publishes returns",
+        "indicator `average` has invalid parameters",
+        "indicator `orphan` subscribes to unknown `prices`",
+        "indicator `price_drawdown` takes equity but `mid_price` publishes \
+         mid prices",
+        "the strategy takes moving averages but `returns` publishes returns",
+        "indicator `sharpe` has no subscribers",
+        "indicator `slow` has no subscribers",
+        "indicator `average` has no subscribers",
+        "indicator `orphan` has no subscribers",
+        "indicator `price_drawdown` has no subscribers",
+      ]
+    );
+  }
+
+  #[test]
+  fn checks_limits_and_venue() {
+    let config = EXAMPLE
+      .replace("max_order_quantity = 0.01", "max_order_quantity = 0.0001")
+      .replace("venue = \"testnet\"", "venue = \"paper\"")
+      .replace("symbols = [\"BTCUSDT\"]", "symbols = [\"btc\"]");
+    assert_eq!(
+      problems(&config),
+      [
+        "symbol `btc` isn't like `BTCUSDT`",
+        "the strategy quantity exceeds the max_order_quantity risk limit",
+        "the control API needs a testnet or mainnet venue",
+      ]
+    );
+  }
+
+  #[test]
+  fn live_equity_needs_the_control_api() {
+    let control = "[control]\nbind = \"127.0.0.1:8080\"\nmetrics = true\n";
+    assert!(EXAMPLE.ends_with(control));
+    let config = EXAMPLE.replace(
+      control,
+      "[[indicators]]\nname = \"drawdown\"\nkind = \"drawdown\"\n\
+       input = \"equity\"\n",
+    );
+    assert_eq!(
+      problems(&config),
+      [
+        "indicator `sharpe` has no subscribers",
+        "indicator `drawdown` has no subscribers",
+        "the equity needs a paper venue or the control API",
+      ]
+    );
+  }
+
+  fn tickers() -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    mock::write_payloads(&mut file, &mock::tickers(&PRICES)).unwrap();
+    file
+  }
+
+  fn replay(file: &tempfile::NamedTempFile) -> EngineConfig {
+    let indicators = r#"
+[[indicators]]
+name = "moving_average"
+kind = "sma"
+period = 3
+"#;
+    let config = paper(indicators, "moving_average")
+      .replace("ticks.jsonl", &file.path().display().to_string());
+    EngineConfig::from_toml(&config).unwrap()
+  }
+
+  #[actix_rt::test]
+  async fn replays_on_paper() {
+    let file = tickers();
+    let engine = replay(&file).build().unwrap();
+    let Execution::Paper(exchange) = engine.execution() else {
+      panic!("paper venue");
+    };
+    let exchange = exchange.clone();
+    engine.run().await.unwrap();
+
+    // the replay returns once the tickers are sent, not handled
+    let report = tokio::time::timeout(StdDuration::from_secs(5), async {
+      loop {
+        let report = exchange.send(GetReport).await.unwrap();
+        let decisions = report.decisions;
+        if decisions.buy + decisions.sell + decisions.hold == PRICES.len() - 2
+          && report.equity.len() == PRICES.len()
+        {
+          break report;
+        }
+        actix_rt::time::sleep(StdDuration::from_millis(10)).await;
+      }
+    })
+    .await
+    .expect("every ticker handled");
+    assert_eq!(report.initial_equity, 1000.);
+    assert_eq!(
+      report.equity.last().unwrap().timestamp,
+      chrono::DateTime::from_timestamp_millis(1664625611000).unwrap()
+    );
+  }
+
+  #[actix_rt::test]
+  async fn settles_once_every_ticker_is_handled() {
+    let file = tickers();
+    let engine = replay(&file).build().unwrap();
+    let settlement = engine.run_until(std::future::pending()).await.unwrap();
+
+    let Settlement::Paper(report) = settlement else {
+      panic!("paper venue");
+    };
+    let decisions = report.decisions;
+    assert_eq!(
+      decisions.buy + decisions.sell + decisions.hold,
+      PRICES.len() - 2
+    );
+    assert_eq!(report.equity.len(), PRICES.len());
+  }
+}
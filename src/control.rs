@@ -0,0 +1,508 @@
+//! HTTP API to monitor and control a running engine: health of the actors,
+//! positions, open orders, latest prices, indicator values and risk
+//! metrics, pausing and resuming trading, the kill switch and the strategy
+//! parameters.
+
+pub mod feed;
+pub mod monitor;
+
+use std::io;
+use std::net::ToSocketAddrs;
+use std::sync::Arc;
+
+use actix::{Actor, Addr};
+use actix_web::dev::Server;
+use actix_web::error::{
+  ErrorBadRequest, ErrorNotFound, ErrorServiceUnavailable,
+};
+use actix_web::{web, App, HttpResponse, HttpServer};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::metrics::Metrics;
+use crate::policy_maker::{
+  GetParameters, PolicyMakerActor, PolicyParameters, SetParameters,
+};
+use crate::trade::{
+  GetTradingStatus, Kill, Pause, Resume, TradeActor, TradingState,
+};
+use feed::{Connect, EventFeed};
+use monitor::{GetSnapshot, Monitor, Snapshot};
+
+type Probe = Arc<dyn Fn() -> bool + Send + Sync>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ActorHealth {
+  pub name: String,
+  /// Whether the actor is still running.
+  pub alive: bool,
+}
+
+/// The actors the API reads from and controls, cheap to clone into every
+/// server worker.
+#[derive(Clone)]
+pub struct ControlApi {
+  monitor: Addr<Monitor>,
+  trader: Addr<TradeActor>,
+  policy_maker: Addr<PolicyMakerActor>,
+  feed: Option<Addr<EventFeed>>,
+  metrics: Option<Metrics>,
+  actors: Vec<(String, Probe)>,
+  started: DateTime<Utc>,
+}
+
+impl ControlApi {
+  pub fn new(
+    monitor: Addr<Monitor>,
+    trader: Addr<TradeActor>,
+    policy_maker: Addr<PolicyMakerActor>,
+  ) -> Self {
+    Self {
+      monitor: monitor.clone(),
+      trader: trader.clone(),
+      policy_maker: policy_maker.clone(),
+      feed: None,
+      metrics: None,
+      actors: vec![],
+      started: Utc::now(),
+    }
+    .with_actor("monitor", monitor)
+    .with_actor("trade", trader)
+    .with_actor("policy_maker", policy_maker)
+  }
+
+  /// Reports the health of another actor, e.g. an indicator.
+  pub fn with_actor<A: Actor>(mut self, name: &str, addr: Addr<A>) -> Self {
+    self
+      .actors
+      .push((name.to_owned(), Arc::new(move || addr.connected())));
+    self
+  }
+
+  /// Streams the events of `feed` as Server-Sent Events on `/events`.
+  pub fn with_feed(mut self, feed: Addr<EventFeed>) -> Self {
+    self.feed = Some(feed.clone());
+    self.with_actor("feed", feed)
+  }
+
+  /// Renders `metrics` for Prometheus on `/metrics`.
+  pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+    self.metrics = Some(metrics);
+    self
+  }
+
+  /// Adds the API routes to an app.
+  pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+    cfg
+      .app_data(web::Data::new(self.clone()))
+      .route("/health", web::get().to(health))
+      .route("/prices", web::get().to(prices))
+      .route("/indicators", web::get().to(indicators))
+      .route("/risk", web::get().to(risk))
+      .route("/positions", web::get().to(positions))
+      .route("/orders", web::get().to(orders))
+      .route("/trading", web::get().to(trading))
+      .route("/trading/pause", web::post().to(pause))
+      .route("/trading/resume", web::post().to(resume))
+      .route("/trading/kill", web::post().to(kill))
+      .route("/strategy", web::get().to(strategy))
+      .route("/strategy", web::put().to(set_strategy))
+      .route("/events", web::get().to(events))
+      .route("/metrics", web::get().to(metrics));
+  }
+
+  /// Binds the API to `addr`. The server runs once awaited or spawned.
+  pub fn bind(self, addr: impl ToSocketAddrs) -> io::Result<Server> {
+    Ok(
+      HttpServer::new(move || {
+        let api = self.clone();
+        App::new().configure(move |cfg| api.configure(cfg))
+      })
+      .bind(addr)?
+      .run(),
+    )
+  }
+
+  async fn snapshot(&self) -> actix_web::Result<Snapshot> {
+    self
+      .monitor
+      .send(GetSnapshot)
+      .await
+      .map_err(ErrorServiceUnavailable)
+  }
+}
+
+type Api = web::Data<ControlApi>;
+
+async fn health(api: Api) -> actix_web::Result<HttpResponse> {
+  let actors: Vec<_> = api
+    .actors
+    .iter()
+    .map(|(name, alive)| ActorHealth {
+      name: name.clone(),
+      alive: alive(),
+    })
+    .collect();
+  // a dead monitor shows in the actors, not as an error
+  let updated = api.snapshot().await.map(|s| s.updated).unwrap_or_default();
+  Ok(HttpResponse::Ok().json(json!({
+    "uptime_seconds": (Utc::now() - api.started).num_seconds(),
+    "actors": actors,
+    "updated": updated,
+  })))
+}
+
+async fn prices(api: Api) -> actix_web::Result<HttpResponse> {
+  Ok(HttpResponse::Ok().json(api.snapshot().await?.prices))
+}
+
+async fn indicators(api: Api) -> actix_web::Result<HttpResponse> {
+  Ok(HttpResponse::Ok().json(api.snapshot().await?.indicators))
+}
+
+async fn risk(api: Api) -> actix_web::Result<HttpResponse> {
+  Ok(HttpResponse::Ok().json(api.snapshot().await?.risk))
+}
+
+async fn positions(api: Api) -> actix_web::Result<HttpResponse> {
+  let status = api
+    .trader
+    .send(GetTradingStatus)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(status.positions))
+}
+
+async fn orders(api: Api) -> actix_web::Result<HttpResponse> {
+  let status = api
+    .trader
+    .send(GetTradingStatus)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(status.open_orders))
+}
+
+async fn trading(api: Api) -> actix_web::Result<HttpResponse> {
+  let status = api
+    .trader
+    .send(GetTradingStatus)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(status))
+}
+
+async fn pause(api: Api) -> actix_web::Result<HttpResponse> {
+  let state = api
+    .trader
+    .send(Pause)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(json!({ "state": state })))
+}
+
+async fn resume(api: Api) -> actix_web::Result<HttpResponse> {
+  let state = api
+    .trader
+    .send(Resume)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  let body = json!({ "state": state });
+  Ok(match state {
+    TradingState::Killed => HttpResponse::Conflict().json(body),
+    _ => HttpResponse::Ok().json(body),
+  })
+}
+
+async fn kill(api: Api) -> actix_web::Result<HttpResponse> {
+  let status = api
+    .trader
+    .send(Kill)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(status))
+}
+
+async fn strategy(api: Api) -> actix_web::Result<HttpResponse> {
+  let parameters = api
+    .policy_maker
+    .send(GetParameters)
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(parameters))
+}
+
+async fn set_strategy(
+  api: Api,
+  parameters: web::Json<PolicyParameters>,
+) -> actix_web::Result<HttpResponse> {
+  let parameters =
+    PolicyParameters::new(parameters.quantity, parameters.threshold)
+      .ok_or_else(|| {
+        ErrorBadRequest("quantity must be positive, threshold non-negative")
+      })?;
+  api
+    .policy_maker
+    .send(SetParameters(parameters))
+    .await
+    .map_err(ErrorServiceUnavailable)?;
+  Ok(HttpResponse::Ok().json(parameters))
+}
+
+async fn events(api: Api) -> actix_web::Result<HttpResponse> {
+  let feed = api
+    .feed
+    .as_ref()
+    .ok_or_else(|| ErrorNotFound("no event feed"))?;
+  let receiver = feed.send(Connect).await.map_err(ErrorServiceUnavailable)?;
+  let frames = futures_util::stream::unfold(receiver, |mut receiver| async {
+    loop {
+      match receiver.recv().await {
+        Ok(frame) => return Some((Ok::<_, actix_web::Error>(frame), receiver)),
+        Err(RecvError::Lagged(missed)) => {
+          tracing::warn!(missed, "Event feed client missed events")
+        }
+        Err(RecvError::Closed) => return None,
+      }
+    }
+  });
+  Ok(
+    HttpResponse::Ok()
+      .content_type("text/event-stream")
+      .insert_header(("Cache-Control", "no-cache"))
+      .streaming(frames),
+  )
+}
+
+async fn metrics(api: Api) -> actix_web::Result<HttpResponse> {
+  let metrics = api
+    .metrics
+    .as_ref()
+    .ok_or_else(|| ErrorNotFound("no metrics"))?;
+  Ok(
+    HttpResponse::Ok()
+      .content_type(prometheus::TEXT_FORMAT)
+      .body(metrics.render()),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::mid_price::MidPrice;
+  use crate::actors::moving_average::MovingAverageMessage;
+  use crate::actors::risk::sharpe::SharpeRatio;
+  use crate::mock::rest::{MockRest, MockRestServer};
+  use crate::policy_maker::PolicyDecision;
+  use crate::trade::Buy;
+  use actix_web::http::StatusCode;
+  use actix_web::test::{self, TestRequest};
+  use serde_json::Value;
+
+  struct Engine {
+    api: ControlApi,
+    monitor: Addr<Monitor>,
+    trader: Addr<TradeActor>,
+    exchange: MockRest,
+  }
+
+  fn engine() -> Engine {
+    let exchange = MockRestServer::new("key", "secret").start().unwrap();
+    let trader = TradeActor::new()
+      .with_config(exchange.config())
+      .with_credentials("key", "secret")
+      .start();
+    let policy_maker =
+      PolicyMakerActor::new(vec![trader.clone().recipient()]).start();
+    let monitor = Monitor::new().start();
+    Engine {
+      api: ControlApi::new(monitor.clone(), trader.clone(), policy_maker),
+      monitor,
+      trader,
+      exchange,
+    }
+  }
+
+  fn buy() -> PolicyDecision {
+    PolicyDecision::BuyAction(Buy {
+      symbol: "BTCUSDT".to_owned(),
+      quantity: 0.001,
+      price: 10_000.,
+      timestamp: Utc::now(),
+      correlation: None,
+    })
+  }
+
+  #[actix_rt::test]
+  async fn reports_latest_values() {
+    let engine = engine();
+    let app =
+      test::init_service(App::new().configure(|cfg| engine.api.configure(cfg)))
+        .await;
+    let timestamp = Utc::now();
+    engine
+      .monitor
+      .send(MidPrice {
+        price: 19_000.5,
+        symbol: "BTCUSDT".to_owned(),
+        timestamp,
+        correlation: None,
+      })
+      .await
+      .unwrap();
+    engine
+      .monitor
+      .send(MovingAverageMessage::from(19_000.))
+      .await
+      .unwrap();
+    engine.monitor.send(SharpeRatio(1.5)).await.unwrap();
+
+    let get = |uri| TestRequest::get().uri(uri).to_request();
+    let prices: Value =
+      test::call_and_read_body_json(&app, get("/prices")).await;
+    assert_eq!(prices["BTCUSDT"]["price"], 19_000.5);
+    let indicators: Value =
+      test::call_and_read_body_json(&app, get("/indicators")).await;
+    assert_eq!(indicators, json!({ "moving_average": 19_000. }));
+    let risk: Value = test::call_and_read_body_json(&app, get("/risk")).await;
+    assert_eq!(risk["sharpe"], 1.5);
+    assert_eq!(risk["value_at_risk"], Value::Null);
+
+    let health: Value =
+      test::call_and_read_body_json(&app, get("/health")).await;
+    assert_eq!(
+      health["actors"],
+      json!([
+        { "name": "monitor", "alive": true },
+        { "name": "trade", "alive": true },
+        { "name": "policy_maker", "alive": true },
+      ])
+    );
+    assert!(health["updated"]["sharpe"].is_string());
+  }
+
+  #[actix_rt::test]
+  async fn pauses_and_kills_trading() {
+    let engine = engine();
+    let app =
+      test::init_service(App::new().configure(|cfg| engine.api.configure(cfg)))
+        .await;
+    let post = |uri| TestRequest::post().uri(uri).to_request();
+
+    let paused: Value =
+      test::call_and_read_body_json(&app, post("/trading/pause")).await;
+    assert_eq!(paused, json!({ "state": "paused" }));
+    engine.trader.send(buy()).await.unwrap();
+    assert!(engine.exchange.orders().is_empty());
+
+    test::call_service(&app, post("/trading/resume")).await;
+    engine.trader.send(buy()).await.unwrap();
+    let positions: Value = test::call_and_read_body_json(
+      &app,
+      TestRequest::get().uri("/positions").to_request(),
+    )
+    .await;
+    assert_eq!(positions, json!({ "BTCUSDT": 0.001 }));
+
+    let killed: Value =
+      test::call_and_read_body_json(&app, post("/trading/kill")).await;
+    assert_eq!(killed["state"], "killed");
+    let res = test::call_service(&app, post("/trading/resume")).await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+  }
+
+  #[actix_rt::test]
+  async fn changes_strategy_parameters() {
+    let engine = engine();
+    let app =
+      test::init_service(App::new().configure(|cfg| engine.api.configure(cfg)))
+        .await;
+    let put = |body| {
+      TestRequest::put()
+        .uri("/strategy")
+        .set_json(body)
+        .to_request()
+    };
+
+    let res = test::call_service(
+      &app,
+      put(json!({ "quantity": -1., "threshold": 0. })),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = test::call_service(
+      &app,
+      put(json!({ "quantity": 0.5, "threshold": 0.01 })),
+    )
+    .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let parameters: PolicyParameters = test::call_and_read_body_json(
+      &app,
+      TestRequest::get().uri("/strategy").to_request(),
+    )
+    .await;
+    assert_eq!(parameters, PolicyParameters::new(0.5, 0.01).unwrap());
+  }
+
+  #[actix_rt::test]
+  async fn streams_events() {
+    use actix_web::body::MessageBody;
+    use futures_util::future::poll_fn;
+
+    let engine = engine();
+    let feed = EventFeed::new().start();
+    let api = engine.api.clone().with_feed(feed.clone());
+    let app =
+      test::init_service(App::new().configure(|cfg| api.configure(cfg))).await;
+
+    let res =
+      test::call_service(&app, TestRequest::get().uri("/events").to_request())
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+      res.headers().get("content-type").unwrap(),
+      "text/event-stream"
+    );
+
+    feed.send(SharpeRatio(1.5)).await.unwrap();
+    let mut body = Box::pin(res.into_body());
+    let frame = poll_fn(|cx| body.as_mut().poll_next(cx))
+      .await
+      .unwrap()
+      .unwrap();
+    let frame = std::str::from_utf8(&frame).unwrap();
+    assert!(frame.starts_with("data: {"), "{frame}");
+    assert!(frame.contains(r#""metric":"sharpe""#), "{frame}");
+  }
+
+  #[actix_rt::test]
+  async fn serves_metrics() {
+    let engine = engine();
+    let app =
+      test::init_service(App::new().configure(|cfg| engine.api.configure(cfg)))
+        .await;
+    let res =
+      test::call_service(&app, TestRequest::get().uri("/metrics").to_request())
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let metrics = Metrics::new();
+    let api = engine.api.clone().with_metrics(metrics.clone());
+    let app =
+      test::init_service(App::new().configure(|cfg| api.configure(cfg))).await;
+    let res =
+      test::call_service(&app, TestRequest::get().uri("/metrics").to_request())
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+      res.headers().get("content-type").unwrap(),
+      prometheus::TEXT_FORMAT
+    );
+    let body = test::read_body(res).await;
+    assert!(std::str::from_utf8(&body)
+      .unwrap()
+      .contains("# TYPE tactix_pnl gauge"));
+  }
+}
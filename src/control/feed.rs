@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix::{Actor, Context, Handler, Message, MessageResult, Recipient};
+use actix_web::web::Bytes;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::actors::indicator::Subscribe;
+use crate::actors::mid_price::MidPrice;
+use crate::actors::risk::Equity;
+use crate::backtest::exchange::Fill;
+use crate::clock::{SharedClock, WallClock};
+use crate::control::monitor::{risk_handlers, RiskMetrics, RiskUpdate};
+use crate::metrics::Metrics;
+use crate::policy_maker::PolicyDecision;
+
+/// Events a slow client may fall behind by before missing some.
+const CAPACITY: usize = 1024;
+
+/// What the engine did, as streamed to dashboards.
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+  Decision(PolicyDecision),
+  Fill(Fill),
+  /// Cash flows of the fills plus the positions at their last mid price.
+  Pnl {
+    pnl: f64,
+    positions: BTreeMap<String, f64>,
+  },
+  /// Every risk metric after one of them changed.
+  Risk {
+    metric: &'static str,
+    metrics: RiskMetrics,
+  },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+  emitted_at: DateTime<Utc>,
+  #[serde(flatten)]
+  event: &'a Event,
+}
+
+/// Opens a stream of Server-Sent Events frames, one per event.
+#[derive(Message)]
+#[rtype(result = "broadcast::Receiver<Bytes>")]
+pub struct Connect;
+
+/// Subscribes to the policy maker, the trade actor, the mid prices and the
+/// risk actors, and broadcasts each event as JSON to every connected
+/// client. Publishes the PnL as [`Equity`] to the nodes subscribed to it.
+pub struct EventFeed {
+  clock: SharedClock,
+  sender: broadcast::Sender<Bytes>,
+  cash: f64,
+  positions: BTreeMap<String, f64>,
+  prices: BTreeMap<String, f64>,
+  risk: RiskMetrics,
+  metrics: Option<Metrics>,
+  subscribers: Vec<Recipient<Equity>>,
+}
+
+impl Default for EventFeed {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl EventFeed {
+  pub fn new() -> Self {
+    Self {
+      clock: Arc::new(WallClock),
+      sender: broadcast::channel(CAPACITY).0,
+      cash: 0.,
+      positions: BTreeMap::new(),
+      prices: BTreeMap::new(),
+      risk: RiskMetrics::default(),
+      metrics: None,
+      subscribers: vec![],
+    }
+  }
+
+  pub fn with_clock(mut self, clock: SharedClock) -> Self {
+    self.clock = clock;
+    self
+  }
+
+  /// Sets the PnL gauge, with or without connected clients.
+  pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+    self.metrics = Some(metrics.clone());
+    self
+  }
+
+  fn publish(&self, event: Event) {
+    if self.sender.receiver_count() == 0 {
+      return;
+    }
+    let envelope = Envelope {
+      emitted_at: self.clock.now(),
+      event: &event,
+    };
+    match serde_json::to_string(&envelope) {
+      Ok(json) => {
+        // fails only without clients
+        let _ = self.sender.send(Bytes::from(format!("data: {json}\n\n")));
+      }
+      Err(e) => tracing::warn!(?event, error = %e, "Error serializing event"),
+    }
+  }
+
+  fn publish_pnl(&self) {
+    let price = |symbol| self.prices.get(symbol).copied().unwrap_or_default();
+    let pnl = self
+      .positions
+      .iter()
+      .fold(self.cash, |pnl, (symbol, quantity)| {
+        pnl + quantity * price(symbol)
+      });
+    if let Some(metrics) = &self.metrics {
+      metrics.set_pnl(pnl);
+    }
+    let equity = Equity {
+      value: pnl,
+      timestamp: self.clock.now(),
+    };
+    for s in &self.subscribers {
+      s.do_send(equity);
+    }
+    self.publish(Event::Pnl {
+      pnl,
+      positions: self.positions.clone(),
+    });
+  }
+
+  fn update_risk(&mut self, msg: impl RiskUpdate) {
+    let metric = msg.apply(&mut self.risk);
+    self.publish(Event::Risk {
+      metric,
+      metrics: self.risk.clone(),
+    });
+  }
+}
+
+impl Actor for EventFeed {
+  type Context = Context<Self>;
+}
+
+impl Handler<Connect> for EventFeed {
+  type Result = MessageResult<Connect>;
+
+  fn handle(&mut self, _: Connect, _ctx: &mut Context<Self>) -> Self::Result {
+    MessageResult(self.sender.subscribe())
+  }
+}
+
+impl Handler<Subscribe<Equity>> for EventFeed {
+  type Result = ();
+
+  fn handle(&mut self, msg: Subscribe<Equity>, _ctx: &mut Context<Self>) {
+    self.subscribers.push(msg.0);
+  }
+}
+
+impl Handler<PolicyDecision> for EventFeed {
+  type Result = ();
+
+  fn handle(&mut self, msg: PolicyDecision, _ctx: &mut Context<Self>) {
+    self.publish(Event::Decision(msg));
+  }
+}
+
+impl Handler<Fill> for EventFeed {
+  type Result = ();
+
+  fn handle(&mut self, msg: Fill, _ctx: &mut Context<Self>) {
+    self.cash += msg.cash_flow();
+    // marks the position at the fill until a mid price arrives
+    self.prices.entry(msg.symbol.clone()).or_insert(msg.price);
+    *self.positions.entry(msg.symbol.clone()).or_default() +=
+      msg.signed_quantity();
+    self.publish(Event::Fill(msg));
+    self.publish_pnl();
+  }
+}
+
+impl Handler<MidPrice> for EventFeed {
+  type Result = ();
+
+  fn handle(&mut self, msg: MidPrice, _ctx: &mut Context<Self>) {
+    let held = self.positions.get(&msg.symbol).is_some_and(|q| *q != 0.);
+    let changed = self.prices.insert(msg.symbol, msg.price) != Some(msg.price);
+    if held && changed {
+      self.publish_pnl();
+    }
+  }
+}
+
+risk_handlers!(EventFeed, update_risk);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::actors::risk::sharpe::SharpeRatio;
+  use crate::backtest::exchange::Side;
+  use crate::trade::Hold;
+  use chrono::TimeZone;
+  use serde_json::{json, Value};
+
+  fn parse(frame: Bytes) -> Value {
+    let frame = std::str::from_utf8(&frame).unwrap();
+    let json = frame.strip_prefix("data: ").unwrap().trim_end();
+    serde_json::from_str(json).unwrap()
+  }
+
+  #[actix_rt::test]
+  async fn streams_events_as_json() {
+    let feed = EventFeed::new().start();
+    let mut events = feed.send(Connect).await.unwrap();
+    let timestamp = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+    feed
+      .send(PolicyDecision::HoldAction(Hold {
+        symbol: "BTCUSDT".to_owned(),
+        timestamp,
+        correlation: None,
+      }))
+      .await
+      .unwrap();
+    feed
+      .send(Fill {
+        symbol: "BTCUSDT".to_owned(),
+        side: Side::Buy,
+        quantity: 2.,
+        price: 100.,
+        fee: 1.,
+        timestamp,
+      })
+      .await
+      .unwrap();
+    for price in [100., 110., 110.] {
+      feed
+        .send(MidPrice {
+          price,
+          symbol: "BTCUSDT".to_owned(),
+          timestamp,
+          correlation: None,
+        })
+        .await
+        .unwrap();
+    }
+    feed.send(SharpeRatio(1.5)).await.unwrap();
+
+    let mut received = vec![];
+    while let Ok(frame) = events.try_recv() {
+      let mut event = parse(frame);
+      assert!(event
+        .as_object_mut()
+        .unwrap()
+        .remove("emitted_at")
+        .is_some());
+      received.push(event);
+    }
+    assert_eq!(received.len(), 5, "{received:?}");
+    assert_eq!(
+      received[0],
+      json!({
+        "type": "decision",
+        "action": "hold",
+        "symbol": "BTCUSDT",
+        "timestamp": "2022-10-01T12:00:00Z",
+      })
+    );
+    assert_eq!(received[1]["type"], "fill");
+    assert_eq!(received[1]["side"], "Buy");
+    // buying costs the fee, then the position gains 2 * 10, unchanged mid
+    // prices publish nothing
+    assert_eq!(
+      received[2],
+      json!({
+        "type": "pnl",
+        "pnl": -1.,
+        "positions": { "BTCUSDT": 2. },
+      })
+    );
+    assert_eq!(received[3]["pnl"], 19.);
+    assert_eq!(received[4]["type"], "risk");
+    assert_eq!(received[4]["metric"], "sharpe");
+    assert_eq!(received[4]["metrics"]["sharpe"], 1.5);
+  }
+
+  #[actix_rt::test]
+  async fn publishes_pnl_as_equity() {
+    let (collector, recipient) = collect::<Equity>();
+    let feed = EventFeed::new().start();
+    feed.send(Subscribe(recipient)).await.unwrap();
+    let timestamp = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+
+    feed
+      .send(Fill {
+        symbol: "BTCUSDT".to_owned(),
+        side: Side::Buy,
+        quantity: 2.,
+        price: 100.,
+        fee: 1.,
+        timestamp,
+      })
+      .await
+      .unwrap();
+    feed
+      .send(MidPrice {
+        price: 90.,
+        symbol: "BTCUSDT".to_owned(),
+        timestamp,
+        correlation: None,
+      })
+      .await
+      .unwrap();
+
+    let received = collector.send(Take::new()).await.unwrap();
+    let values: Vec<_> = received.iter().map(|e| e.value).collect();
+    assert_eq!(values, [-1., -21.]);
+  }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use actix::{Actor, Context, Handler, Message, MessageResult};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::actors::mid_price::MidPrice;
+use crate::actors::moving_average::MovingAverageMessage;
+use crate::actors::risk::calmar::CalmarRatio;
+use crate::actors::risk::drawdown::DrawdownReport;
+use crate::actors::risk::information::InformationRatio;
+use crate::actors::risk::omega::OmegaRatio;
+use crate::actors::risk::sharpe::SharpeRatio;
+use crate::actors::risk::sortino::SortinoRatio;
+use crate::actors::risk::value_at_risk::TailRisk;
+use crate::clock::{SharedClock, WallClock};
+
+/// Latest mid price of a symbol.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Quote {
+  pub price: f64,
+  pub timestamp: DateTime<Utc>,
+}
+
+/// Latest value of each risk metric, `None` until one is received.
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct RiskMetrics {
+  pub sharpe: Option<f64>,
+  pub sortino: Option<f64>,
+  pub calmar: Option<f64>,
+  pub omega: Option<f64>,
+  pub information: Option<f64>,
+  pub value_at_risk: Option<f64>,
+  pub expected_shortfall: Option<f64>,
+  pub drawdown: Option<f64>,
+  pub drawdown_percent: Option<f64>,
+  pub max_drawdown: Option<f64>,
+  pub max_drawdown_percent: Option<f64>,
+}
+
+impl RiskMetrics {
+  /// Metrics with a value, by name.
+  pub fn values(&self) -> Vec<(&'static str, f64)> {
+    [
+      ("sharpe", self.sharpe),
+      ("sortino", self.sortino),
+      ("calmar", self.calmar),
+      ("omega", self.omega),
+      ("information", self.information),
+      ("value_at_risk", self.value_at_risk),
+      ("expected_shortfall", self.expected_shortfall),
+      ("drawdown", self.drawdown),
+      ("drawdown_percent", self.drawdown_percent),
+      ("max_drawdown", self.max_drawdown),
+      ("max_drawdown_percent", self.max_drawdown_percent),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .collect()
+  }
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+  pub prices: BTreeMap<String, Quote>,
+  pub indicators: BTreeMap<String, f64>,
+  pub risk: RiskMetrics,
+  /// When each kind of message was last received, to spot stalled actors.
+  pub updated: BTreeMap<&'static str, DateTime<Utc>>,
+}
+
+#[derive(Message)]
+#[rtype(result = "Snapshot")]
+pub struct GetSnapshot;
+
+/// Subscribes to the mid price, indicator and risk actors and keeps their
+/// latest values for the control API.
+pub struct Monitor {
+  clock: SharedClock,
+  snapshot: Snapshot,
+}
+
+impl Default for Monitor {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Monitor {
+  pub fn new() -> Self {
+    Self {
+      clock: Arc::new(WallClock),
+      snapshot: Snapshot::default(),
+    }
+  }
+
+  pub fn with_clock(mut self, clock: SharedClock) -> Self {
+    self.clock = clock;
+    self
+  }
+
+  fn touch(&mut self, kind: &'static str) {
+    self.snapshot.updated.insert(kind, self.clock.now());
+  }
+
+  fn update_risk(&mut self, msg: impl RiskUpdate) {
+    let metric = msg.apply(&mut self.snapshot.risk);
+    self.touch(metric);
+  }
+}
+
+impl Actor for Monitor {
+  type Context = Context<Self>;
+}
+
+impl Handler<GetSnapshot> for Monitor {
+  type Result = MessageResult<GetSnapshot>;
+
+  fn handle(
+    &mut self,
+    _: GetSnapshot,
+    _ctx: &mut Context<Self>,
+  ) -> Self::Result {
+    MessageResult(self.snapshot.clone())
+  }
+}
+
+impl Handler<MidPrice> for Monitor {
+  type Result = ();
+
+  fn handle(&mut self, msg: MidPrice, _ctx: &mut Context<Self>) {
+    self.snapshot.prices.insert(
+      msg.symbol,
+      Quote {
+        price: msg.price,
+        timestamp: msg.timestamp,
+      },
+    );
+    self.touch("mid_price");
+  }
+}
+
+impl Handler<MovingAverageMessage> for Monitor {
+  type Result = f64;
+
+  fn handle(
+    &mut self,
+    msg: MovingAverageMessage,
+    _ctx: &mut Context<Self>,
+  ) -> f64 {
+    self
+      .snapshot
+      .indicators
+      .insert("moving_average".to_owned(), msg.0);
+    self.touch("moving_average");
+    msg.0
+  }
+}
+
+/// A risk message whose latest value the [`RiskMetrics`] keep.
+pub trait RiskUpdate {
+  /// Stores the value and names the metric.
+  fn apply(self, risk: &mut RiskMetrics) -> &'static str;
+}
+
+impl RiskUpdate for TailRisk {
+  fn apply(self, risk: &mut RiskMetrics) -> &'static str {
+    risk.value_at_risk = Some(self.value_at_risk);
+    risk.expected_shortfall = Some(self.expected_shortfall);
+    "value_at_risk"
+  }
+}
+
+impl RiskUpdate for DrawdownReport {
+  fn apply(self, risk: &mut RiskMetrics) -> &'static str {
+    risk.drawdown = Some(self.current);
+    risk.drawdown_percent = Some(self.current_percent);
+    risk.max_drawdown = Some(self.max);
+    risk.max_drawdown_percent = Some(self.max_percent);
+    "drawdown"
+  }
+}
+
+macro_rules! ratio_updates {
+  ($($ratio:ty => $field:ident),*) => {$(
+    impl RiskUpdate for $ratio {
+      fn apply(self, risk: &mut RiskMetrics) -> &'static str {
+        risk.$field = Some(self.0);
+        stringify!($field)
+      }
+    }
+  )*};
+}
+
+ratio_updates!(
+  SharpeRatio => sharpe,
+  SortinoRatio => sortino,
+  CalmarRatio => calmar,
+  OmegaRatio => omega,
+  InformationRatio => information
+);
+
+/// Handles every [`RiskUpdate`] message of `$actor` with `$update`.
+macro_rules! risk_handlers {
+  ($actor:ty, $update:ident) => {
+    $crate::control::monitor::risk_handlers!(
+      @impl $actor, $update,
+      $crate::actors::risk::sharpe::SharpeRatio,
+      $crate::actors::risk::sortino::SortinoRatio,
+      $crate::actors::risk::calmar::CalmarRatio,
+      $crate::actors::risk::omega::OmegaRatio,
+      $crate::actors::risk::information::InformationRatio,
+      $crate::actors::risk::value_at_risk::TailRisk,
+      $crate::actors::risk::drawdown::DrawdownReport
+    );
+  };
+  (@impl $actor:ty, $update:ident, $($msg:ty),*) => {$(
+    impl actix::Handler<$msg> for $actor {
+      type Result = ();
+
+      fn handle(&mut self, msg: $msg, _ctx: &mut actix::Context<Self>) {
+        self.$update(msg)
+      }
+    }
+  )*};
+}
+pub(crate) use risk_handlers;
+
+risk_handlers!(Monitor, update_risk);
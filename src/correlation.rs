@@ -0,0 +1,141 @@
+//! Correlation ids following a ticker through the actor graph: the mid
+//! price, the indicators, the policy decision and the order all carry the
+//! `update_id` of the ticker they derive from, and are handled within a
+//! `tracing` span recording it.
+
+use std::time::Instant;
+
+use serde::Serialize;
+use tracing::Span;
+
+use crate::actors::bar::Bar;
+use crate::actors::indicator::IndicatorMessage;
+use crate::actors::risk::calmar::CalmarRatio;
+use crate::actors::risk::drawdown::DrawdownReport;
+use crate::actors::risk::information::{BenchmarkReturn, InformationRatio};
+use crate::actors::risk::omega::OmegaRatio;
+use crate::actors::risk::sharpe::{Return, SharpeRatio};
+use crate::actors::risk::sortino::SortinoRatio;
+use crate::actors::risk::value_at_risk::TailRisk;
+use crate::actors::risk::Equity;
+use crate::util::Double;
+
+/// The ticker a message derives from.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(into = "u64")]
+pub struct Correlation {
+  /// `update_id` of the ticker.
+  pub id: u64,
+  /// When the ticker was received, to measure the tick-to-trade latency.
+  pub received: Instant,
+}
+
+impl From<Correlation> for u64 {
+  fn from(correlation: Correlation) -> Self {
+    correlation.id
+  }
+}
+
+/// A message that may derive from a ticker. Messages without a correlation
+/// neither report nor keep one.
+pub trait Correlated {
+  fn correlation(&self) -> Option<Correlation> {
+    None
+  }
+
+  fn correlate(&mut self, _correlation: Correlation) {}
+}
+
+/// Span of `actor` handling a message derived from the ticker of
+/// `correlation`, if any.
+pub fn span(actor: &str, correlation: Option<Correlation>) -> Span {
+  tracing::info_span!(
+    "handle",
+    actor,
+    correlation_id = correlation.map(|c| c.id)
+  )
+}
+
+impl<T> Correlated for IndicatorMessage<T> {}
+
+macro_rules! uncorrelated {
+  ($($msg:ty),*) => {$(
+    impl Correlated for $msg {}
+  )*};
+}
+
+uncorrelated!(
+  Bar,
+  Double,
+  Return,
+  BenchmarkReturn,
+  Equity,
+  SharpeRatio,
+  SortinoRatio,
+  CalmarRatio,
+  OmegaRatio,
+  InformationRatio,
+  TailRisk,
+  DrawdownReport
+);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::actors::mid_price::{MidPriceActor, MidPriceIndicator};
+  use crate::actors::moving_average::MovingAverageActor;
+  use crate::algos::moving_average::Sma;
+  use crate::binance_websocket::TickerMessage;
+  use crate::policy_maker::{PolicyDecision, PolicyMakerActor};
+  use actix::Actor;
+  use std::time::Duration;
+
+  #[actix_rt::test]
+  async fn follows_tickers_to_decisions() {
+    let (collector, recipient) = collect::<PolicyDecision>();
+    let policy_maker = PolicyMakerActor::new(vec![recipient]).start();
+    let moving_average = MovingAverageActor::new(
+      Sma::new(1).unwrap(),
+      vec![policy_maker.recipient()],
+    )
+    .start();
+    let mid_price = MidPriceActor::new(
+      MidPriceIndicator::default(),
+      vec![moving_average.recipient()],
+    )
+    .start();
+    let received = Instant::now();
+    for update_id in [1, 2] {
+      mid_price.do_send(TickerMessage {
+        update_id,
+        symbol: "BTCUSDT".to_owned(),
+        best_bid_price: 100.,
+        best_ask_price: 101.,
+        received: Some(received),
+        ..Default::default()
+      });
+    }
+
+    let decisions = tokio::time::timeout(Duration::from_secs(5), async {
+      let mut decisions = vec![];
+      while decisions.len() < 2 {
+        actix_rt::time::sleep(Duration::from_millis(10)).await;
+        decisions.extend(collector.send(Take::new()).await.unwrap());
+      }
+      decisions
+    })
+    .await
+    .expect("decisions");
+    let correlations = decisions
+      .iter()
+      .map(|decision| decision.correlation())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      correlations,
+      [1, 2].map(|id| Some(Correlation { id, received }))
+    );
+    let event = serde_json::to_value(&decisions[0]).unwrap();
+    assert_eq!(event["correlation_id"], 1);
+  }
+}
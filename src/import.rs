@@ -0,0 +1,355 @@
+//! Converts historical data into the crate's message types: the Binance
+//! public data dumps (unzipped klines and trades CSV) and, with the
+//! `parquet` feature, Parquet bar files.
+
+#[cfg(feature = "parquet")]
+mod parquet;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use actix::Message;
+use chrono::{DateTime, Duration, Utc};
+
+use crate::actors::bar::Bar;
+use crate::binance_websocket::TickerMessage;
+
+/// An executed trade of the public market.
+#[derive(Message, Debug, Clone, PartialEq)]
+#[rtype(result = "()")]
+pub struct Trade {
+  pub symbol: String,
+  pub id: u64,
+  pub price: f64,
+  pub quantity: f64,
+  pub timestamp: DateTime<Utc>,
+  pub is_buyer_maker: bool,
+}
+
+/// Quotes both sides at the trade price, for replays and backtests.
+impl From<Trade> for (DateTime<Utc>, TickerMessage) {
+  fn from(trade: Trade) -> Self {
+    let ticker = TickerMessage {
+      update_id: trade.id,
+      symbol: trade.symbol,
+      best_bid_price: trade.price,
+      best_bid_qty: trade.quantity,
+      best_ask_price: trade.price,
+      best_ask_qty: trade.quantity,
+      event_time: Some(trade.timestamp),
+      received: None,
+    };
+    (trade.timestamp, ticker)
+  }
+}
+
+/// What to do about intervals without a bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GapPolicy {
+  /// Leave them out.
+  #[default]
+  Skip,
+  /// Insert flat bars at the previous close without volume.
+  FillForward,
+}
+
+/// Epoch timestamps in either milliseconds or, as in the dumps since 2025,
+/// microseconds.
+pub(crate) fn epoch_timestamp(value: i64) -> io::Result<DateTime<Utc>> {
+  let timestamp = if value.abs() >= 100_000_000_000_000 {
+    DateTime::from_timestamp_micros(value)
+  } else {
+    DateTime::from_timestamp_millis(value)
+  };
+  timestamp
+    .ok_or_else(|| invalid_data(format!("timestamp {value} out of range")))
+}
+
+fn invalid_data(
+  e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+fn field<T: std::str::FromStr>(
+  record: &csv::StringRecord,
+  i: usize,
+) -> io::Result<T> {
+  record
+    .get(i)
+    .and_then(|value| value.trim().parse().ok())
+    .ok_or_else(|| {
+      invalid_data(format!("bad column {i} in row {:?}", record.position()))
+    })
+}
+
+/// Rows of a dump, skipping the header the newer files have.
+fn rows(
+  path: &Path,
+) -> io::Result<impl Iterator<Item = io::Result<csv::StringRecord>>> {
+  let reader = csv::ReaderBuilder::new()
+    .has_headers(false)
+    .from_reader(File::open(path)?);
+  Ok(
+    reader
+      .into_records()
+      .enumerate()
+      .filter_map(|(i, row)| match row {
+        Ok(row) if i == 0 && field::<i64>(&row, 0).is_err() => None,
+        row => Some(row.map_err(invalid_data)),
+      }),
+  )
+}
+
+/// Reads historical data files into messages, mapping exchange symbols to
+/// the ones used by the actor graph.
+#[derive(Debug, Clone, Default)]
+pub struct Importer {
+  symbols: HashMap<String, String>,
+  gaps: GapPolicy,
+}
+
+impl Importer {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Renames `from`, as named by the files, to `to`.
+  pub fn with_symbol(
+    mut self,
+    from: impl Into<String>,
+    to: impl Into<String>,
+  ) -> Self {
+    self.symbols.insert(from.into(), to.into());
+    self
+  }
+
+  pub fn with_gaps(mut self, gaps: GapPolicy) -> Self {
+    self.gaps = gaps;
+    self
+  }
+
+  /// Symbol of a dump, the prefix of its name, as in
+  /// `BTCUSDT-1m-2024-01-01.csv`.
+  pub fn symbol(&self, path: &Path) -> io::Result<String> {
+    let symbol = path
+      .file_stem()
+      .and_then(|stem| stem.to_str())
+      .and_then(|stem| stem.split('-').next())
+      .filter(|symbol| !symbol.is_empty())
+      .ok_or_else(|| {
+        io::Error::new(
+          io::ErrorKind::InvalidInput,
+          format!("no symbol in file name {path:?}"),
+        )
+      })?;
+    Ok(self.map_symbol(symbol))
+  }
+
+  fn map_symbol(&self, symbol: &str) -> String {
+    self
+      .symbols
+      .get(symbol)
+      .cloned()
+      .unwrap_or_else(|| symbol.to_owned())
+  }
+
+  /// Reads a klines dump: open time, open, high, low, close, volume and
+  /// close time, the last millisecond of the bar, followed by columns that
+  /// are ignored.
+  pub fn klines(&self, path: impl AsRef<Path>) -> io::Result<Vec<Bar>> {
+    let path = path.as_ref();
+    let symbol = self.symbol(path)?;
+    let bars = rows(path)?.map(|row| {
+      let row = row?;
+      Ok(Bar {
+        symbol: symbol.clone(),
+        open_time: epoch_timestamp(field(&row, 0)?)?,
+        open: field(&row, 1)?,
+        high: field(&row, 2)?,
+        low: field(&row, 3)?,
+        close: field(&row, 4)?,
+        volume: field(&row, 5)?,
+        close_time: epoch_timestamp(field::<i64>(&row, 6)? + 1)?,
+      })
+    });
+    self.fill_gaps(bars)
+  }
+
+  /// Reads a trades dump: id, price, quantity, quote quantity, time and
+  /// whether the buyer was the maker.
+  pub fn trades(&self, path: impl AsRef<Path>) -> io::Result<Vec<Trade>> {
+    let path = path.as_ref();
+    let symbol = self.symbol(path)?;
+    rows(path)?
+      .map(|row| {
+        let row = row?;
+        Ok(Trade {
+          symbol: symbol.clone(),
+          id: field(&row, 0)?,
+          price: field(&row, 1)?,
+          quantity: field(&row, 2)?,
+          timestamp: epoch_timestamp(field(&row, 4)?)?,
+          is_buyer_maker: field::<String>(&row, 5)?
+            .eq_ignore_ascii_case("true"),
+        })
+      })
+      .collect()
+  }
+
+  /// Applies the gap policy to bars ordered by open time, the interval of a
+  /// bar is from its open to its close.
+  pub(crate) fn fill_gaps(
+    &self,
+    bars: impl IntoIterator<Item = io::Result<Bar>>,
+  ) -> io::Result<Vec<Bar>> {
+    let mut filled: Vec<Bar> = vec![];
+    for bar in bars {
+      let bar = bar?;
+      if let Some(last) = filled.last() {
+        if bar.open_time < last.close_time {
+          return Err(invalid_data(format!(
+            "bar at {} overlaps the previous one",
+            bar.open_time
+          )));
+        }
+        if bar.open_time > last.close_time {
+          tracing::warn!(
+            symbol = bar.symbol,
+            from = %last.close_time,
+            to = %bar.open_time,
+            "Gap in bars"
+          );
+          if self.gaps == GapPolicy::FillForward {
+            let interval = bar.close_time - bar.open_time;
+            filled.extend(flat_bars(last, bar.open_time, interval));
+          }
+        }
+      }
+      filled.push(bar);
+    }
+    Ok(filled)
+  }
+}
+
+fn flat_bars(last: &Bar, until: DateTime<Utc>, interval: Duration) -> Vec<Bar> {
+  let mut bars = vec![];
+  let mut open_time = last.close_time;
+  while open_time < until && interval > Duration::zero() {
+    let close_time = (open_time + interval).min(until);
+    bars.push(Bar {
+      symbol: last.symbol.clone(),
+      open_time,
+      close_time,
+      open: last.close,
+      high: last.close,
+      low: last.close,
+      close: last.close,
+      volume: 0.,
+    });
+    open_time = close_time;
+  }
+  bars
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::TimeZone;
+  use std::io::Write;
+
+  fn dump(
+    name: &str,
+    contents: &str,
+  ) -> (tempfile::TempDir, std::path::PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join(name);
+    File::create(&path)
+      .unwrap()
+      .write_all(contents.as_bytes())
+      .unwrap();
+    (dir, path)
+  }
+
+  const KLINES: &str = "\
+1664625600000,19000.0,19010.0,18990.0,19005.0,12.5,1664625659999,0,0,0,0,0
+1664625660000,19005.0,19020.0,19000.0,19015.0,3.0,1664625719999,0,0,0,0,0
+1664625780000,19015.0,19030.0,19010.0,19025.0,4.0,1664625839999,0,0,0,0,0
+";
+
+  #[test]
+  fn klines() {
+    let (_dir, path) = dump("BTCUSDT-1m-2022-10-01.csv", KLINES);
+    let bars = Importer::new()
+      .with_symbol("BTCUSDT", "btcusdt")
+      .klines(&path)
+      .unwrap();
+
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    assert_eq!(bars.len(), 3);
+    assert_eq!(
+      bars[0],
+      Bar {
+        symbol: "btcusdt".to_owned(),
+        open_time: start,
+        close_time: start + Duration::minutes(1),
+        open: 19000.,
+        high: 19010.,
+        low: 18990.,
+        close: 19005.,
+        volume: 12.5,
+      }
+    );
+    assert_eq!(bars[2].open_time, start + Duration::minutes(3));
+  }
+
+  #[test]
+  fn klines_fill_forward() {
+    let (_dir, path) = dump("BTCUSDT-1m-2022-10-01.csv", KLINES);
+    let bars = Importer::new()
+      .with_gaps(GapPolicy::FillForward)
+      .klines(&path)
+      .unwrap();
+
+    assert_eq!(bars.len(), 4);
+    let gap = &bars[2];
+    assert_eq!(gap.open_time, bars[1].close_time);
+    assert_eq!(gap.close_time, bars[3].open_time);
+    assert_eq!((gap.open, gap.close, gap.volume), (19015., 19015., 0.));
+  }
+
+  #[test]
+  fn trades_with_header_and_micros() {
+    let (_dir, path) = dump(
+      "ETHUSDT-trades-2025-01-01.csv",
+      "id,price,qty,quote_qty,time,is_buyer_maker,is_best_match\n\
+       1,3300.5,0.25,825.125,1735689600000000,True,True\n\
+       2,3301.0,0.5,1650.5,1735689600250000,false,True\n",
+    );
+    let trades = Importer::new().trades(&path).unwrap();
+
+    assert_eq!(trades.len(), 2);
+    assert_eq!(trades[0].symbol, "ETHUSDT");
+    assert!(trades[0].is_buyer_maker && !trades[1].is_buyer_maker);
+    assert_eq!(
+      trades[1].timestamp,
+      Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap()
+        + Duration::milliseconds(250)
+    );
+    let (time, ticker) = trades[1].clone().into();
+    assert_eq!(time, trades[1].timestamp);
+    assert_eq!(
+      (ticker.best_bid_price, ticker.best_ask_price),
+      (3301., 3301.)
+    );
+  }
+
+  #[test]
+  fn malformed() {
+    let (_dir, path) = dump("BTCUSDT-1m.csv", "1664625600000,oops\n");
+    assert!(Importer::new().klines(&path).is_err());
+    assert!(Importer::new().klines("-1m.csv").is_err());
+  }
+}
@@ -0,0 +1,176 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::{Field, Row};
+
+use super::{epoch_timestamp, invalid_data, Importer};
+use crate::actors::bar::Bar;
+
+fn column<'a>(row: &'a Row, name: &str) -> Option<&'a Field> {
+  row
+    .get_column_iter()
+    .find(|(column, _)| column.as_str() == name)
+    .map(|(_, field)| field)
+}
+
+fn number(row: &Row, name: &str) -> io::Result<f64> {
+  match column(row, name) {
+    Some(Field::Double(value)) => Ok(*value),
+    Some(Field::Float(value)) => Ok(*value as f64),
+    Some(Field::Long(value)) => Ok(*value as f64),
+    Some(Field::Int(value)) => Ok(*value as f64),
+    Some(Field::Str(value)) => value.parse().map_err(invalid_data),
+    other => Err(invalid_data(format!("bad {name} column: {other:?}"))),
+  }
+}
+
+/// Timestamp of the column plus `ticks` of its unit.
+fn timestamp(row: &Row, name: &str, ticks: i64) -> io::Result<DateTime<Utc>> {
+  match column(row, name) {
+    Some(Field::TimestampMillis(millis)) => {
+      epoch_timestamp(millis.saturating_add(ticks))
+    }
+    Some(Field::TimestampMicros(micros)) => {
+      DateTime::from_timestamp_micros(micros.saturating_add(ticks))
+        .ok_or_else(|| invalid_data("timestamp out of range"))
+    }
+    Some(Field::Long(value)) => epoch_timestamp(value.saturating_add(ticks)),
+    other => Err(invalid_data(format!("bad {name} column: {other:?}"))),
+  }
+}
+
+impl Importer {
+  /// Reads bars from a Parquet file with `open_time`, `open`, `high`, `low`,
+  /// `close` and `close_time`, the last millisecond or microsecond of the
+  /// bar as in the klines dumps, and optionally `volume` and `symbol`
+  /// columns. Timestamps are Parquet timestamps or epoch integers, the
+  /// symbol defaults to the prefix of the file name.
+  pub fn parquet_bars(&self, path: impl AsRef<Path>) -> io::Result<Vec<Bar>> {
+    let path = path.as_ref();
+    let reader =
+      SerializedFileReader::new(File::open(path)?).map_err(invalid_data)?;
+    let rows = reader.get_row_iter(None).map_err(invalid_data)?;
+    let bars = rows.map(|row| {
+      let row = row.map_err(invalid_data)?;
+      let symbol = match column(&row, "symbol") {
+        Some(Field::Str(symbol)) => self.map_symbol(symbol),
+        _ => self.symbol(path)?,
+      };
+      let volume = match column(&row, "volume") {
+        Some(_) => number(&row, "volume")?,
+        None => 0.,
+      };
+      Ok(Bar {
+        symbol,
+        open_time: timestamp(&row, "open_time", 0)?,
+        close_time: timestamp(&row, "close_time", 1)?,
+        open: number(&row, "open")?,
+        high: number(&row, "high")?,
+        low: number(&row, "low")?,
+        close: number(&row, "close")?,
+        volume,
+      })
+    });
+    self.fill_gaps(bars)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use chrono::{Duration, TimeZone};
+  use parquet::column::writer::ColumnWriter;
+  use parquet::data_type::ByteArray;
+  use parquet::file::properties::WriterProperties;
+  use parquet::file::writer::SerializedFileWriter;
+  use parquet::schema::parser::parse_message_type;
+  use std::sync::Arc;
+
+  /// Two one minute bars from 2022-10-01 12:00 UTC, with `close_time` of
+  /// the `close_type` annotation.
+  fn write_bars(path: &Path, close_type: &str, close_times: [i64; 2]) {
+    let schema = parse_message_type(&format!(
+      "message bars {{
+        REQUIRED BYTE_ARRAY symbol (UTF8);
+        REQUIRED INT64 open_time (TIMESTAMP(MILLIS, true));
+        REQUIRED DOUBLE open;
+        REQUIRED DOUBLE high;
+        REQUIRED DOUBLE low;
+        REQUIRED DOUBLE close;
+        REQUIRED INT64 close_time {close_type};
+      }}"
+    ))
+    .unwrap();
+    let mut writer = SerializedFileWriter::new(
+      File::create(path).unwrap(),
+      Arc::new(schema),
+      Arc::new(WriterProperties::builder().build()),
+    )
+    .unwrap();
+    let mut row_group = writer.next_row_group().unwrap();
+    let mut index = 0;
+    while let Some(mut column) = row_group.next_column().unwrap() {
+      match column.untyped() {
+        ColumnWriter::ByteArrayColumnWriter(w) => {
+          let symbols =
+            [ByteArray::from("BTCUSDT"), ByteArray::from("BTCUSDT")];
+          w.write_batch(&symbols, None, None).unwrap();
+        }
+        ColumnWriter::Int64ColumnWriter(w) if index == 1 => {
+          w.write_batch(&[1664625600000, 1664625660000], None, None)
+            .unwrap();
+        }
+        ColumnWriter::Int64ColumnWriter(w) => {
+          w.write_batch(&close_times, None, None).unwrap();
+        }
+        ColumnWriter::DoubleColumnWriter(w) => {
+          let value = 10. + index as f64;
+          w.write_batch(&[value, value + 1.], None, None).unwrap();
+        }
+        _ => unreachable!(),
+      }
+      column.close().unwrap();
+      index += 1;
+    }
+    row_group.close().unwrap();
+    writer.close().unwrap();
+  }
+
+  #[test]
+  fn bars() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("bars.parquet");
+    write_bars(&path, "", [1664625659999, 1664625719999]);
+
+    let bars = Importer::new()
+      .with_symbol("BTCUSDT", "btcusdt")
+      .parquet_bars(&path)
+      .unwrap();
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    assert_eq!(
+      bars[1],
+      Bar {
+        symbol: "btcusdt".to_owned(),
+        open_time: start + Duration::minutes(1),
+        close_time: start + Duration::minutes(2),
+        open: 13.,
+        high: 14.,
+        low: 15.,
+        close: 16.,
+        volume: 0.,
+      }
+    );
+
+    write_bars(
+      &path,
+      "(TIMESTAMP(MICROS, true))",
+      [1664625659999999, 1664625719999999],
+    );
+    let bars = Importer::new().parquet_bars(&path).unwrap();
+    assert_eq!(bars[0].close_time, bars[1].open_time);
+    assert_eq!(bars[1].close_time, start + Duration::minutes(2));
+  }
+}
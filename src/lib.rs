@@ -1,13 +1,22 @@
 pub use assert_matches::assert_matches;
 
+pub mod clock;
+pub mod config;
+pub mod correlation;
+pub mod import;
 pub mod util;
 
 pub mod binance_websocket;
-pub mod test_server;
+pub mod control;
+pub mod metrics;
+pub mod mock;
 
 pub mod trade;
 pub use actix::prelude::*;
 pub mod algos;
 pub mod policy_maker;
+pub mod recorder;
+pub mod replay;
 
 pub mod actors;
+pub mod backtest;
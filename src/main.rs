@@ -0,0 +1,364 @@
+//! The `tactix` command: runs an engine configuration, backtests, records
+//! and replays market data. SIGINT and SIGTERM shut down gracefully, the
+//! open orders are canceled and the recordings flushed.
+
+use std::error::Error;
+use std::fs::File;
+use std::future::Future;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration as StdDuration;
+
+use actix::Actor;
+use chrono::Duration;
+use clap::{Args, Parser, Subcommand};
+use dotenv::dotenv;
+use serde::de::{value, IntoDeserializer};
+use serde::Deserialize;
+use tactix::backtest::report::BacktestReport;
+use tactix::backtest::{Backtest, Flush};
+use tactix::binance_websocket::BinanceIngestor;
+use tactix::config::{
+  self, EngineConfig, ReplayFormatConfig, Settlement, SourceConfig,
+};
+use tactix::policy_maker::PolicyParameters;
+use tactix::recorder::{self, Recorder, Rotation};
+use tactix::replay::read_tickers;
+use tracing_subscriber::EnvFilter;
+
+type Result<T = ()> = std::result::Result<T, Box<dyn Error>>;
+
+#[derive(Parser, Debug)]
+#[command(version, about = "Trading engine built on Actix actors")]
+struct Cli {
+  #[command(subcommand)]
+  command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Runs the engine of a configuration file until its source ends.
+  Run {
+    /// TOML or YAML engine configuration.
+    config: PathBuf,
+  },
+  /// Runs the moving average strategy over recorded tickers and prints its
+  /// report as JSON.
+  Backtest(BacktestArgs),
+  /// Records the book tickers of symbols to disk.
+  Record(RecordArgs),
+  /// Replays recorded tickers through the engine of a configuration file,
+  /// in place of its source.
+  Replay {
+    /// TOML or YAML engine configuration.
+    config: PathBuf,
+    #[command(flatten)]
+    data: Data,
+    /// Multiple of real time, as fast as possible when missing.
+    #[arg(long)]
+    speed: Option<f64>,
+  },
+  /// Validates a configuration file, listing every problem.
+  CheckConfig {
+    /// TOML or YAML engine configuration.
+    config: PathBuf,
+  },
+}
+
+#[derive(Args, Debug)]
+struct Data {
+  /// Files to read, in order.
+  #[arg(required = true)]
+  files: Vec<PathBuf>,
+  /// binance_json_lines, top_of_book_csv, recording_json_lines or
+  /// recording_binary.
+  #[arg(long, value_parser = replay_format)]
+  format: ReplayFormatConfig,
+}
+
+#[derive(Args, Debug)]
+struct BacktestArgs {
+  #[command(flatten)]
+  data: Data,
+  /// Symbol of the rows of top of book CSV files.
+  #[arg(long, default_value = "BTCUSDT")]
+  symbol: String,
+  /// Period of the moving average.
+  #[arg(long, default_value_t = 3)]
+  window: usize,
+  /// Quantity of every order.
+  #[arg(long, default_value_t = PolicyParameters::default().quantity)]
+  quantity: f64,
+  /// Fraction the price has to cross the moving average by to trade.
+  #[arg(long, default_value_t = PolicyParameters::default().threshold)]
+  threshold: f64,
+  #[arg(long, default_value_t = 10_000.)]
+  initial_cash: f64,
+  /// Fee per fill, as a fraction of its notional.
+  #[arg(long, default_value_t = 0.)]
+  fee_rate: f64,
+  /// Directory to write the equity, fills and round trips CSV files to.
+  #[arg(long)]
+  out: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct RecordArgs {
+  /// Symbol to record, e.g. BTCUSDT.
+  #[arg(long = "symbol", required = true)]
+  symbols: Vec<String>,
+  /// Directory of the recordings.
+  #[arg(long, default_value = "recordings")]
+  dir: PathBuf,
+  /// Writes bincode rather than JSON lines.
+  #[arg(long)]
+  binary: bool,
+  /// Seconds before starting a new file.
+  #[arg(
+    long,
+    default_value_t = 3_600,
+    value_parser = clap::value_parser!(i64).range(1..)
+  )]
+  rotate_secs: i64,
+  /// Bytes before starting a new file.
+  #[arg(long)]
+  max_bytes: Option<u64>,
+  /// Streams from the mainnet rather than the testnet.
+  #[arg(long)]
+  mainnet: bool,
+  /// Combined stream endpoint, by default the network's one for the
+  /// symbols.
+  #[arg(long)]
+  url: Option<String>,
+  #[arg(long, default_value_t = 0)]
+  reconnects: u32,
+  #[arg(long, default_value_t = 1_000)]
+  reconnect_delay_ms: u64,
+}
+
+fn replay_format(
+  format: &str,
+) -> std::result::Result<ReplayFormatConfig, String> {
+  let format: value::StrDeserializer<value::Error> = format.into_deserializer();
+  ReplayFormatConfig::deserialize(format).map_err(|e| e.to_string())
+}
+
+/// Listens to SIGINT and SIGTERM, resolving on the first received.
+#[cfg(unix)]
+fn shutdown_signal() -> io::Result<impl Future<Output = ()>> {
+  use tokio::signal::unix::{signal, SignalKind};
+  let mut interrupt = signal(SignalKind::interrupt())?;
+  let mut terminate = signal(SignalKind::terminate())?;
+  Ok(async move {
+    tokio::select! {
+      _ = interrupt.recv() => tracing::info!("Received SIGINT"),
+      _ = terminate.recv() => tracing::info!("Received SIGTERM"),
+    }
+  })
+}
+
+/// Listens to Ctrl-C.
+#[cfg(not(unix))]
+fn shutdown_signal() -> io::Result<impl Future<Output = ()>> {
+  Ok(async {
+    match tokio::signal::ctrl_c().await {
+      Ok(()) => tracing::info!("Received Ctrl-C"),
+      Err(e) => {
+        tracing::error!(error = ?e, "Couldn't listen to Ctrl-C");
+        std::future::pending::<()>().await
+      }
+    }
+  })
+}
+
+fn print_report(report: &BacktestReport) -> Result {
+  println!("{}", report.to_json()?);
+  Ok(())
+}
+
+async fn run(config: EngineConfig) -> Result {
+  let shutdown = shutdown_signal()?;
+  let engine = config.build()?;
+  tracing::info!("Engine started");
+  match engine.run_until(shutdown).await? {
+    Settlement::Live(status) => {
+      tracing::info!(positions = ?status.positions, "Trading stopped");
+      if !status.open_orders.is_empty() {
+        tracing::warn!(
+          open_orders = ?status.open_orders,
+          "Orders left open, they couldn't be canceled"
+        );
+      }
+      Ok(())
+    }
+    Settlement::Paper(report) => print_report(&report),
+  }
+}
+
+async fn backtest(args: BacktestArgs) -> Result {
+  let format = args.data.format.to_format(&args.symbol);
+  let mut tickers = vec![];
+  for path in &args.data.files {
+    for ticker in read_tickers(path, &format)? {
+      match ticker? {
+        (Some(time), ticker) => tickers.push((time, ticker)),
+        (None, ticker) => {
+          return Err(
+            format!(
+              "ticker {} of {} has no time",
+              ticker.update_id,
+              path.display()
+            )
+            .into(),
+          )
+        }
+      }
+    }
+  }
+  let parameters = PolicyParameters::new(args.quantity, args.threshold)
+    .ok_or("the quantity isn't positive or the threshold is negative")?;
+  let report = Backtest::new(args.window)
+    .ok_or("the window is zero")?
+    .with_parameters(parameters)
+    .with_initial_cash(args.initial_cash)
+    .with_fee_rate(args.fee_rate)
+    .run(tickers)
+    .await;
+  if let Some(dir) = &args.out {
+    write_csvs(&report, dir)?;
+  }
+  print_report(&report)
+}
+
+fn write_csvs(report: &BacktestReport, dir: &Path) -> Result {
+  std::fs::create_dir_all(dir)?;
+  report.write_equity_csv(File::create(dir.join("equity.csv"))?)?;
+  report.write_fills_csv(File::create(dir.join("fills.csv"))?)?;
+  report.write_round_trips_csv(File::create(dir.join("round_trips.csv"))?)?;
+  Ok(())
+}
+
+async fn record(args: RecordArgs) -> Result {
+  let format = if args.binary {
+    recorder::Format::Binary
+  } else {
+    recorder::Format::JsonLines
+  };
+  let prefix = args.symbols.join("-").to_lowercase();
+  let max_age = Duration::try_seconds(args.rotate_secs)
+    .ok_or("the rotation interval is out of range")?;
+  let recorder = Recorder::new(&args.dir, prefix, format)?
+    .with_rotation(Rotation {
+      max_bytes: args.max_bytes,
+      max_age: Some(max_age),
+    })
+    .start();
+  let url = args
+    .url
+    .unwrap_or_else(|| config::stream_url(&args.symbols, args.mainnet));
+  let ingestor = BinanceIngestor::new(
+    vec![recorder.clone().recipient()],
+    vec![recorder.clone().recipient()],
+  )
+  .with_url(url)
+  .with_reconnects(
+    args.reconnects,
+    StdDuration::from_millis(args.reconnect_delay_ms),
+  );
+  let shutdown = shutdown_signal()?;
+  tracing::info!(dir = ?args.dir, "Recording started");
+  tokio::select! {
+    () = ingestor.run() => {}
+    () = shutdown => {}
+  }
+  recorder.send(Flush).await?;
+  tracing::info!(dir = ?args.dir, "Recordings flushed");
+  Ok(())
+}
+
+fn check_config(path: &Path) -> Result {
+  EngineConfig::load(path)?.validate()?;
+  println!("{} is valid", path.display());
+  Ok(())
+}
+
+impl Command {
+  async fn run(self) -> Result {
+    match self {
+      Self::Run { config } => run(EngineConfig::load(config)?).await,
+      Self::Backtest(args) => backtest(args).await,
+      Self::Record(args) => record(args).await,
+      Self::Replay {
+        config,
+        data,
+        speed,
+      } => {
+        let mut config = EngineConfig::load(config)?;
+        config.source = SourceConfig::Replay {
+          files: data.files,
+          format: data.format,
+          speed,
+        };
+        run(config).await
+      }
+      Self::CheckConfig { config } => check_config(&config),
+    }
+  }
+}
+
+#[actix::main]
+async fn main() -> ExitCode {
+  dotenv().ok();
+  tracing_subscriber::fmt()
+    .with_env_filter(
+      EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()),
+    )
+    .with_writer(io::stderr)
+    .init();
+
+  match Cli::parse().command.run().await {
+    Ok(()) => ExitCode::SUCCESS,
+    Err(e) => {
+      eprintln!("error: {e}");
+      ExitCode::FAILURE
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use clap::CommandFactory;
+
+  #[test]
+  fn cli() {
+    Cli::command().debug_assert();
+  }
+
+  #[test]
+  fn parses_replay_formats() {
+    let cli = Cli::try_parse_from([
+      "tactix",
+      "backtest",
+      "--format",
+      "top_of_book_csv",
+      "--symbol",
+      "ETHUSDT",
+      "a.csv",
+      "b.csv",
+    ])
+    .unwrap();
+    let Command::Backtest(args) = cli.command else {
+      panic!("{:?}", cli.command);
+    };
+    assert_eq!(args.data.format, ReplayFormatConfig::TopOfBookCsv);
+    assert_eq!(args.data.files.len(), 2);
+    assert_eq!(args.window, 3);
+
+    assert!(Cli::try_parse_from([
+      "tactix", "backtest", "--format", "parquet", "a.csv"
+    ])
+    .is_err());
+  }
+}
@@ -0,0 +1,413 @@
+//! Prometheus metrics of the actor graph. Actors built `with_metrics`
+//! record into a shared [`Metrics`], which the control API renders in the
+//! text exposition format on `/metrics`.
+
+use std::time::{Duration, Instant};
+
+use actix::dev::ToEnvelope;
+use actix::{Actor, Addr, Handler, Message};
+use prometheus::{
+  Encoder, Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounter,
+  IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+use crate::actors::mid_price::MidPrice;
+use crate::actors::moving_average::MovingAverageMessage;
+use crate::actors::risk::sharpe::Return;
+use crate::control::monitor::{RiskMetrics, RiskUpdate};
+
+/// Answered as soon as it's handled, to time an actor's mailbox.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct Ping;
+
+/// Collectors of one registry. Clones record into the same collectors.
+#[derive(Clone)]
+pub struct Metrics {
+  registry: Registry,
+  messages: IntCounterVec,
+  reconnects: IntCounter,
+  deserialize_errors: IntCounter,
+  decisions: IntCounterVec,
+  orders: IntCounterVec,
+  order_latency: HistogramVec,
+  tick_to_trade: HistogramVec,
+  mailbox_wait: GaugeVec,
+  risk: GaugeVec,
+  pnl: Gauge,
+}
+
+impl Default for Metrics {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+fn opts(name: &str, help: &str) -> Opts {
+  Opts::new(name, help).namespace("tactix")
+}
+
+impl Metrics {
+  pub fn new() -> Self {
+    let metrics = Self {
+      registry: Registry::new(),
+      messages: IntCounterVec::new(
+        opts("messages_total", "Messages handled per actor."),
+        &["actor"],
+      )
+      .unwrap(),
+      reconnects: IntCounter::with_opts(opts(
+        "ingestor_reconnects_total",
+        "Reconnects of the Binance ingestor.",
+      ))
+      .unwrap(),
+      deserialize_errors: IntCounter::with_opts(opts(
+        "ingestor_deserialize_errors_total",
+        "Stream messages the Binance ingestor couldn't deserialize.",
+      ))
+      .unwrap(),
+      decisions: IntCounterVec::new(
+        opts("decisions_total", "Policy decisions per action."),
+        &["action"],
+      )
+      .unwrap(),
+      orders: IntCounterVec::new(
+        opts(
+          "orders_total",
+          "Orders placed per side and status, `rejected` when refused.",
+        ),
+        &["side", "status"],
+      )
+      .unwrap(),
+      order_latency: HistogramVec::new(
+        HistogramOpts::from(opts(
+          "order_latency_seconds",
+          "Time from sending an order to its acknowledgement.",
+        ))
+        .buckets(vec![
+          0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10.,
+        ]),
+        &["side"],
+      )
+      .unwrap(),
+      tick_to_trade: HistogramVec::new(
+        HistogramOpts::from(opts(
+          "tick_to_trade_seconds",
+          "Time from receiving a ticker to acknowledging the order it caused.",
+        ))
+        .buckets(vec![
+          0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1., 2.5, 5., 10.,
+        ]),
+        &["side"],
+      )
+      .unwrap(),
+      mailbox_wait: GaugeVec::new(
+        opts(
+          "mailbox_wait_seconds",
+          "Round trip of the latest ping, the time spent behind the backlog.",
+        ),
+        &["actor"],
+      )
+      .unwrap(),
+      risk: GaugeVec::new(
+        opts("risk", "Latest value of each risk metric."),
+        &["metric"],
+      )
+      .unwrap(),
+      pnl: Gauge::with_opts(opts(
+        "pnl",
+        "Cash flows of the fills plus the positions at their mid price.",
+      ))
+      .unwrap(),
+    };
+    let collectors: [Box<dyn prometheus::core::Collector>; 10] = [
+      Box::new(metrics.messages.clone()),
+      Box::new(metrics.reconnects.clone()),
+      Box::new(metrics.deserialize_errors.clone()),
+      Box::new(metrics.decisions.clone()),
+      Box::new(metrics.orders.clone()),
+      Box::new(metrics.order_latency.clone()),
+      Box::new(metrics.tick_to_trade.clone()),
+      Box::new(metrics.mailbox_wait.clone()),
+      Box::new(metrics.risk.clone()),
+      Box::new(metrics.pnl.clone()),
+    ];
+    for collector in collectors {
+      metrics.registry.register(collector).expect("unique metric");
+    }
+    metrics
+  }
+
+  /// Every metric in the Prometheus text format.
+  pub fn render(&self) -> String {
+    let mut buffer = vec![];
+    TextEncoder::new()
+      .encode(&self.registry.gather(), &mut buffer)
+      .expect("text encoding");
+    String::from_utf8(buffer).expect("utf-8 metrics")
+  }
+
+  /// Pings `addr` every `interval` for as long as it runs, recording how
+  /// long each ping waits in its mailbox. Must be called within an actix
+  /// system.
+  pub fn watch_mailbox<A>(&self, actor: &str, addr: Addr<A>, interval: Duration)
+  where
+    A: Actor + Handler<Ping>,
+    A::Context: ToEnvelope<A, Ping>,
+  {
+    let gauge = self.mailbox_wait.with_label_values(&[actor]);
+    actix_rt::spawn(async move {
+      loop {
+        let sent = Instant::now();
+        if addr.send(Ping).await.is_err() {
+          break;
+        }
+        gauge.set(sent.elapsed().as_secs_f64());
+        actix_rt::time::sleep(interval).await;
+      }
+    });
+  }
+
+  pub(crate) fn handled(&self, actor: &str) {
+    self.messages.with_label_values(&[actor]).inc();
+  }
+
+  pub(crate) fn reconnected(&self) {
+    self.reconnects.inc();
+  }
+
+  pub(crate) fn deserialize_error(&self) {
+    self.deserialize_errors.inc();
+  }
+
+  pub(crate) fn decided(&self, action: &str) {
+    self.decisions.with_label_values(&[action]).inc();
+  }
+
+  pub(crate) fn ordered(&self, side: &str, status: &str, latency: Duration) {
+    self.orders.with_label_values(&[side, status]).inc();
+    self
+      .order_latency
+      .with_label_values(&[side])
+      .observe(latency.as_secs_f64());
+  }
+
+  pub(crate) fn traded(&self, side: &str, tick_to_trade: Duration) {
+    self
+      .tick_to_trade
+      .with_label_values(&[side])
+      .observe(tick_to_trade.as_secs_f64());
+  }
+
+  pub(crate) fn set_risk(&self, risk: &RiskMetrics) {
+    for (metric, value) in risk.values() {
+      self.risk.with_label_values(&[metric]).set(value);
+    }
+  }
+
+  pub(crate) fn set_pnl(&self, pnl: f64) {
+    self.pnl.set(pnl);
+  }
+}
+
+/// Indicator output an instrumented
+/// [`IndicatorActor`](crate::actors::indicator::IndicatorActor) records,
+/// nothing by default.
+pub trait Export {
+  fn export(&self, _metrics: &Metrics) {}
+}
+
+impl Export for MidPrice {}
+impl Export for MovingAverageMessage {}
+impl Export for Return {}
+
+macro_rules! risk_exports {
+  ($($msg:ty),*) => {$(
+    impl Export for $msg {
+      fn export(&self, metrics: &Metrics) {
+        let mut risk = RiskMetrics::default();
+        self.clone().apply(&mut risk);
+        metrics.set_risk(&risk);
+      }
+    }
+  )*};
+}
+
+risk_exports!(
+  crate::actors::risk::sharpe::SharpeRatio,
+  crate::actors::risk::sortino::SortinoRatio,
+  crate::actors::risk::calmar::CalmarRatio,
+  crate::actors::risk::omega::OmegaRatio,
+  crate::actors::risk::information::InformationRatio,
+  crate::actors::risk::value_at_risk::TailRisk,
+  crate::actors::risk::drawdown::DrawdownReport
+);
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::risk::sharpe::{Sharpe, SharpeActor};
+  use crate::binance_websocket::{BinanceIngestor, TickerMessage};
+  use crate::correlation::Correlation;
+  use crate::mock::rest::{MockRestServer, Reply};
+  use crate::mock::websocket::{MockStreamServer, Step};
+  use crate::trade::{Buy, Sell, TradeActor};
+  use chrono::Utc;
+
+  fn sample(rendered: &str, series: &str) -> Option<f64> {
+    rendered
+      .lines()
+      .find_map(|line| line.strip_prefix(series)?.trim().parse().ok())
+  }
+
+  #[actix_rt::test]
+  async fn exports_indicator_outputs() {
+    let metrics = Metrics::new();
+    let sharpe = SharpeActor::new(Sharpe::new(2).unwrap(), vec![])
+      .with_metrics(&metrics, "sharpe")
+      .start();
+    for r in [0.01, 0.03] {
+      sharpe.send(Return(r)).await.unwrap();
+    }
+
+    let rendered = metrics.render();
+    assert_eq!(
+      sample(&rendered, r#"tactix_messages_total{actor="sharpe"}"#),
+      Some(2.)
+    );
+    assert!(sample(&rendered, r#"tactix_risk{metric="sharpe"}"#).is_some());
+  }
+
+  #[actix_rt::test]
+  async fn counts_ingestor_errors_and_reconnects() {
+    let ticker = TickerMessage {
+      update_id: 1,
+      symbol: "BTCUSDT".to_owned(),
+      ..Default::default()
+    };
+    let server = MockStreamServer::new(vec![
+      vec![Step::Text("not json".to_owned()), Step::Drop],
+      vec![Step::book_ticker(&ticker), Step::Close],
+    ])
+    .start()
+    .unwrap();
+    let metrics = Metrics::new();
+    let ingestor = BinanceIngestor::new(vec![], vec![])
+      .with_url(server.url())
+      .with_reconnects(1, Duration::from_millis(10))
+      .with_metrics(&metrics);
+    tokio::time::timeout(Duration::from_secs(10), ingestor.run())
+      .await
+      .expect("ingestor stopped");
+
+    let rendered = metrics.render();
+    assert_eq!(
+      sample(&rendered, "tactix_ingestor_deserialize_errors_total"),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(&rendered, "tactix_ingestor_reconnects_total"),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(&rendered, r#"tactix_messages_total{actor="ingestor"}"#),
+      Some(1.)
+    );
+  }
+
+  #[actix_rt::test]
+  async fn counts_orders_per_status() {
+    let server = MockRestServer::new("key", "secret")
+      .with_replies(vec![
+        Reply::Fill,
+        Reply::Reject {
+          code: -2010,
+          msg: "Account has insufficient balance.".to_owned(),
+        },
+      ])
+      .start()
+      .unwrap();
+    let metrics = Metrics::new();
+    let trader = TradeActor::new()
+      .with_config(server.config())
+      .with_credentials("key", "secret")
+      .with_metrics(&metrics)
+      .start();
+    let buy = trader.send(Buy {
+      symbol: "BTCUSDT".to_owned(),
+      quantity: 0.001,
+      price: 10_000.,
+      timestamp: Utc::now(),
+      correlation: Some(Correlation {
+        id: 1,
+        received: Instant::now(),
+      }),
+    });
+    assert!(buy.await.unwrap().is_ok());
+    let sell = trader.send(Sell {
+      symbol: "BTCUSDT".to_owned(),
+      quantity: 0.001,
+      price: 10_000.,
+      timestamp: Utc::now(),
+      correlation: None,
+    });
+    assert!(sell.await.unwrap().is_err());
+
+    let rendered = metrics.render();
+    assert_eq!(
+      sample(
+        &rendered,
+        r#"tactix_orders_total{side="buy",status="filled"}"#
+      ),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(
+        &rendered,
+        r#"tactix_orders_total{side="sell",status="rejected"}"#
+      ),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(
+        &rendered,
+        r#"tactix_order_latency_seconds_count{side="buy"}"#
+      ),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(&rendered, r#"tactix_messages_total{actor="trade"}"#),
+      Some(2.)
+    );
+    // only the buy derives from a ticker
+    assert_eq!(
+      sample(
+        &rendered,
+        r#"tactix_tick_to_trade_seconds_count{side="buy"}"#
+      ),
+      Some(1.)
+    );
+    assert_eq!(
+      sample(
+        &rendered,
+        r#"tactix_tick_to_trade_seconds_count{side="sell"}"#
+      ),
+      None
+    );
+  }
+
+  #[actix_rt::test]
+  async fn watches_mailboxes() {
+    let metrics = Metrics::new();
+    let sharpe = SharpeActor::new(Sharpe::new(2).unwrap(), vec![]).start();
+    metrics.watch_mailbox("sharpe", sharpe, Duration::from_millis(10));
+    actix_rt::time::sleep(Duration::from_millis(50)).await;
+
+    let rendered = metrics.render();
+    assert!(
+      sample(&rendered, r#"tactix_mailbox_wait_seconds{actor="sharpe"}"#)
+        .is_some(),
+      "{rendered}"
+    );
+  }
+}
@@ -0,0 +1,87 @@
+//! Local stand-ins for the Binance endpoints, to test against without
+//! network access or credentials.
+
+pub mod rest;
+pub mod websocket;
+
+use std::io::{self, Write};
+use std::net::SocketAddr;
+
+use actix_web::dev::ServerHandle;
+use actix_web::{web, App, HttpServer};
+use chrono::{Duration, TimeZone, Utc};
+
+use crate::binance_websocket::TickerMessage;
+
+/// A mock server listening on a local port, stopped when dropped.
+pub struct Server {
+  addr: SocketAddr,
+  handle: ServerHandle,
+}
+
+impl Server {
+  pub fn addr(&self) -> SocketAddr {
+    self.addr
+  }
+}
+
+impl Drop for Server {
+  fn drop(&mut self) {
+    // stopping resolves without being awaited
+    drop(self.handle.stop(false));
+  }
+}
+
+/// Serves the routes `app` configures from one worker on a free local port.
+/// Must be called within an actix system.
+pub fn start(
+  app: impl Fn(&mut web::ServiceConfig) + Clone + Send + 'static,
+) -> io::Result<Server> {
+  let server = HttpServer::new(move || App::new().configure(app.clone()))
+    .workers(1)
+    .disable_signals()
+    .bind(("127.0.0.1", 0))?;
+  let addr = server.addrs()[0];
+  let server = server.run();
+  let handle = server.handle();
+  actix_rt::spawn(server);
+  Ok(Server { addr, handle })
+}
+
+/// Mid prices rising, falling and rising again, enough for a short moving
+/// average strategy to buy and sell.
+pub const PRICES: [f64; 12] = [
+  100., 101., 103., 106., 104., 101., 97., 99., 102., 106., 111., 108.,
+];
+
+/// `BTCUSDT` tickers quoting `prices` with a spread of one, a second apart
+/// from 2022-10-01 12:00 UTC.
+pub fn tickers(prices: &[f64]) -> Vec<TickerMessage> {
+  let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+  prices
+    .iter()
+    .enumerate()
+    .map(|(i, price)| TickerMessage {
+      update_id: i as u64,
+      symbol: "BTCUSDT".to_owned(),
+      best_bid_price: price - 0.5,
+      best_bid_qty: 1.,
+      best_ask_price: price + 0.5,
+      best_ask_qty: 1.,
+      event_time: Some(start + Duration::seconds(i as i64)),
+      received: None,
+    })
+    .collect()
+}
+
+/// Writes the combined stream payloads of `tickers` one per line, as
+/// replayed from `ReplayFormat::BinanceJsonLines` files.
+pub fn write_payloads(
+  mut writer: impl Write,
+  tickers: &[TickerMessage],
+) -> io::Result<()> {
+  for ticker in tickers {
+    writeln!(writer, "{}", websocket::book_ticker_payload(ticker))?;
+  }
+  Ok(())
+}
@@ -0,0 +1,651 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Mutex};
+
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use binance::account::{OrderCancellation, OrderRequest, OrderStatusRequest};
+use binance::config::Config;
+use binance::rest_model::{
+  AccountInformation, AccountType, Balance, ExchangeInformation, Fill, Order,
+  OrderCanceled, OrderSide, OrderStatus, OrderType, Symbol, TimeInForce,
+  Transaction,
+};
+use chrono::Utc;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+use serde_json::json;
+
+/// Binance's default `recvWindow`, in milliseconds.
+const RECV_WINDOW: i64 = 5000;
+
+/// How the server answers the next order placement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reply {
+  /// Fills the whole quantity at the order price.
+  Fill,
+  /// Accepts the order without filling it, so it can be canceled.
+  Accept,
+  /// Rejects the order with a Binance error code, e.g. `-2010`.
+  Reject { code: i32, msg: String },
+  /// Answers 429 Too Many Requests.
+  RateLimited,
+  /// Answers 418, as Binance does for clients ignoring 429s.
+  Banned,
+}
+
+/// A symbol the server trades, with its base and quote asset.
+#[derive(Debug, Clone)]
+struct Listing {
+  symbol: String,
+  base: String,
+  quote: String,
+}
+
+struct Book {
+  replies: VecDeque<Reply>,
+  orders: Vec<Order>,
+  balances: BTreeMap<String, f64>,
+}
+
+struct State {
+  api_key: String,
+  secret: String,
+  listings: Vec<Listing>,
+  book: Mutex<Book>,
+}
+
+/// REST server standing in for the Binance spot API: places, cancels and
+/// queries orders, reports the account and the exchange info. Signed
+/// requests must carry the API key and a valid HMAC-SHA256 signature.
+/// Order placements consume the scripted replies in order and are filled
+/// once the script is done.
+pub struct MockRestServer {
+  api_key: String,
+  secret: String,
+  replies: Vec<Reply>,
+  listings: Vec<Listing>,
+  balances: BTreeMap<String, f64>,
+}
+
+impl MockRestServer {
+  pub fn new(api_key: impl Into<String>, secret: impl Into<String>) -> Self {
+    Self {
+      api_key: api_key.into(),
+      secret: secret.into(),
+      replies: vec![],
+      listings: vec![Listing {
+        symbol: "BTCUSDT".to_owned(),
+        base: "BTC".to_owned(),
+        quote: "USDT".to_owned(),
+      }],
+      balances: BTreeMap::new(),
+    }
+  }
+
+  pub fn with_replies(mut self, replies: Vec<Reply>) -> Self {
+    self.replies = replies;
+    self
+  }
+
+  /// Lists another symbol, besides BTCUSDT.
+  pub fn with_symbol(mut self, symbol: &str, base: &str, quote: &str) -> Self {
+    self.listings.push(Listing {
+      symbol: symbol.to_owned(),
+      base: base.to_owned(),
+      quote: quote.to_owned(),
+    });
+    self
+  }
+
+  /// Free balance of `asset`, moved by every fill.
+  pub fn with_balance(mut self, asset: &str, free: f64) -> Self {
+    self.balances.insert(asset.to_owned(), free);
+    self
+  }
+
+  /// Listens on a free local port. Must be called within an actix system.
+  pub fn start(self) -> io::Result<MockRest> {
+    let state = web::Data::new(State {
+      api_key: self.api_key,
+      secret: self.secret,
+      listings: self.listings,
+      book: Mutex::new(Book {
+        replies: self.replies.into(),
+        orders: vec![],
+        balances: self.balances,
+      }),
+    });
+    let app_state = state.clone();
+    let server = super::start(move |app| {
+      app
+        .app_data(app_state.clone())
+        .route("/api/v3/order", web::post().to(place_order))
+        .route("/api/v3/order", web::delete().to(cancel_order))
+        .route("/api/v3/order", web::get().to(query_order))
+        .route("/api/v3/account", web::get().to(account))
+        .route("/api/v3/exchangeInfo", web::get().to(exchange_info));
+    })?;
+    Ok(MockRest {
+      server,
+      state: state.into_inner(),
+    })
+  }
+}
+
+/// Error answered with a Binance error code.
+#[derive(Debug)]
+struct ApiError {
+  status: StatusCode,
+  code: i32,
+  msg: String,
+}
+
+impl fmt::Display for ApiError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} ({})", self.msg, self.code)
+  }
+}
+
+impl ResponseError for ApiError {
+  fn status_code(&self) -> StatusCode {
+    self.status
+  }
+
+  fn error_response(&self) -> HttpResponse {
+    HttpResponse::build(self.status)
+      .json(json!({ "code": self.code, "msg": self.msg }))
+  }
+}
+
+fn error(status: StatusCode, code: i32, msg: &str) -> ApiError {
+  ApiError {
+    status,
+    code,
+    msg: msg.to_owned(),
+  }
+}
+
+fn bad_request(code: i32, msg: &str) -> ApiError {
+  error(StatusCode::BAD_REQUEST, code, msg)
+}
+
+fn now_millis() -> u64 {
+  Utc::now().timestamp_millis() as u64
+}
+
+/// Hex encoded HMAC-SHA256 of `payload`, as Binance signs requests.
+pub fn sign(secret: &str, payload: &str) -> String {
+  let key = PKey::hmac(secret.as_bytes()).expect("hmac key");
+  let mut signer =
+    Signer::new(MessageDigest::sha256(), &key).expect("hmac signer");
+  signer.update(payload.as_bytes()).expect("hmac update");
+  let mac = signer.sign_to_vec().expect("hmac signature");
+  mac.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Checks the API key header, the signature and the `timestamp` of a
+/// signed request.
+fn verify(req: &HttpRequest, state: &State) -> Result<(), ApiError> {
+  let api_key = req
+    .headers()
+    .get("X-MBX-APIKEY")
+    .and_then(|key| key.to_str().ok());
+  if api_key != Some(state.api_key.as_str()) {
+    return Err(error(
+      StatusCode::UNAUTHORIZED,
+      -2015,
+      "Invalid API-key, IP, or permissions for action.",
+    ));
+  }
+
+  let invalid_signature =
+    || bad_request(-1022, "Signature for this request is not valid.");
+  let (payload, signature) = req
+    .query_string()
+    .rsplit_once("&signature=")
+    .ok_or_else(invalid_signature)?;
+  if sign(&state.secret, payload) != signature {
+    return Err(invalid_signature());
+  }
+
+  let params = web::Query::<HashMap<String, String>>::from_query(payload)
+    .map_err(|_| {
+      bad_request(-1102, "Mandatory parameter was not sent or malformed.")
+    })?;
+  let number = |key: &str| params.get(key).and_then(|v| v.parse::<i64>().ok());
+  let timestamp = number("timestamp").ok_or_else(|| {
+    bad_request(-1102, "Mandatory parameter 'timestamp' was not sent.")
+  })?;
+  let recv_window = number("recvWindow").unwrap_or(RECV_WINDOW);
+  if (Utc::now().timestamp_millis() - timestamp).abs() > recv_window {
+    return Err(bad_request(
+      -1021,
+      "Timestamp for this request is outside of the recvWindow.",
+    ));
+  }
+  Ok(())
+}
+
+fn parse<T: serde::de::DeserializeOwned>(
+  req: &HttpRequest,
+) -> Result<T, ApiError> {
+  web::Query::<T>::from_query(req.query_string())
+    .map(web::Query::into_inner)
+    .map_err(|e| bad_request(-1102, &e.to_string()))
+}
+
+async fn place_order(
+  req: HttpRequest,
+  state: web::Data<State>,
+) -> Result<HttpResponse, ApiError> {
+  verify(&req, &state)?;
+  let request: OrderRequest = parse(&req)?;
+  let listing = state
+    .listings
+    .iter()
+    .find(|l| l.symbol == request.symbol)
+    .ok_or_else(|| bad_request(-1121, "Invalid symbol."))?;
+  let quantity = request.quantity.ok_or_else(|| {
+    bad_request(-1102, "Mandatory parameter 'quantity' was not sent.")
+  })?;
+  let price = request.price.unwrap_or_default();
+
+  let mut book = state.book.lock().unwrap();
+  let status = match book.replies.pop_front().unwrap_or(Reply::Fill) {
+    Reply::Fill => OrderStatus::Filled,
+    Reply::Accept => OrderStatus::New,
+    Reply::Reject { code, msg } => return Err(bad_request(code, &msg)),
+    Reply::RateLimited => {
+      return Err(error(
+        StatusCode::TOO_MANY_REQUESTS,
+        -1003,
+        "Too many requests.",
+      ))
+    }
+    Reply::Banned => {
+      return Err(error(
+        StatusCode::IM_A_TEAPOT,
+        -1003,
+        "Way too many requests; IP banned.",
+      ))
+    }
+  };
+
+  let order_id = book.orders.len() as u64 + 1;
+  let filled = status == OrderStatus::Filled;
+  let executed_qty = if filled { quantity } else { 0. };
+  if filled {
+    let sign = if request.side == OrderSide::Buy {
+      1.
+    } else {
+      -1.
+    };
+    *book.balances.entry(listing.base.clone()).or_default() += sign * quantity;
+    *book.balances.entry(listing.quote.clone()).or_default() -=
+      sign * quantity * price;
+  }
+  let now = now_millis();
+  let order = Order {
+    symbol: request.symbol,
+    order_id,
+    order_list_id: -1,
+    client_order_id: request
+      .new_client_order_id
+      .unwrap_or_else(|| format!("mock-{order_id}")),
+    price,
+    orig_qty: quantity,
+    executed_qty,
+    cummulative_quote_qty: executed_qty * price,
+    status,
+    time_in_force: request.time_in_force.unwrap_or(TimeInForce::GTC),
+    order_type: request.order_type,
+    side: request.side,
+    stop_price: 0.,
+    iceberg_qty: 0.,
+    time: now,
+    update_time: now,
+    is_working: !filled,
+    orig_quote_order_qty: 0.,
+  };
+  book.orders.push(order.clone());
+
+  Ok(HttpResponse::Ok().json(Transaction {
+    symbol: order.symbol,
+    order_id,
+    client_order_id: order.client_order_id,
+    transact_time: now,
+    price,
+    orig_qty: quantity,
+    executed_qty,
+    cummulative_quote_qty: order.cummulative_quote_qty,
+    status: order.status,
+    time_in_force: order.time_in_force,
+    order_type: order.order_type,
+    side: order.side,
+    fills: if filled {
+      vec![Fill {
+        price,
+        qty: quantity,
+        commission: 0.,
+        commission_asset: listing.quote.clone(),
+      }]
+    } else {
+      vec![]
+    },
+  }))
+}
+
+/// Index of the order a request refers to, by id or client order id.
+fn find(
+  orders: &[Order],
+  symbol: &str,
+  order_id: Option<u64>,
+  client_order_id: Option<&str>,
+) -> Option<usize> {
+  orders.iter().position(|o| {
+    o.symbol == symbol
+      && (order_id == Some(o.order_id)
+        || client_order_id == Some(o.client_order_id.as_str()))
+  })
+}
+
+async fn cancel_order(
+  req: HttpRequest,
+  state: web::Data<State>,
+) -> Result<HttpResponse, ApiError> {
+  verify(&req, &state)?;
+  let request: OrderCancellation = parse(&req)?;
+  let mut book = state.book.lock().unwrap();
+  let unknown = || bad_request(-2011, "Unknown order sent.");
+  let index = find(
+    &book.orders,
+    &request.symbol,
+    request.order_id,
+    request.orig_client_order_id.as_deref(),
+  )
+  .ok_or_else(unknown)?;
+  let order = &mut book.orders[index];
+  if order.status != OrderStatus::New {
+    return Err(unknown());
+  }
+  order.status = OrderStatus::Canceled;
+  order.is_working = false;
+  order.update_time = now_millis();
+  Ok(
+    HttpResponse::Ok().json(OrderCanceled {
+      symbol: order.symbol.clone(),
+      orig_client_order_id: order.client_order_id.clone(),
+      order_id: order.order_id,
+      client_order_id: request
+        .new_client_order_id
+        .unwrap_or_else(|| format!("cancel-{}", order.order_id)),
+    }),
+  )
+}
+
+async fn query_order(
+  req: HttpRequest,
+  state: web::Data<State>,
+) -> Result<HttpResponse, ApiError> {
+  verify(&req, &state)?;
+  let request: OrderStatusRequest = parse(&req)?;
+  let book = state.book.lock().unwrap();
+  find(
+    &book.orders,
+    &request.symbol,
+    request.order_id,
+    request.orig_client_order_id.as_deref(),
+  )
+  .map(|index| HttpResponse::Ok().json(&book.orders[index]))
+  .ok_or_else(|| bad_request(-2013, "Order does not exist."))
+}
+
+async fn account(
+  req: HttpRequest,
+  state: web::Data<State>,
+) -> Result<HttpResponse, ApiError> {
+  verify(&req, &state)?;
+  let book = state.book.lock().unwrap();
+  Ok(
+    HttpResponse::Ok().json(AccountInformation {
+      maker_commission: 0.,
+      taker_commission: 0.,
+      buyer_commission: 0.,
+      seller_commission: 0.,
+      can_trade: true,
+      can_withdraw: false,
+      can_deposit: false,
+      account_type: AccountType::Spot,
+      balances: book
+        .balances
+        .iter()
+        .map(|(asset, free)| Balance {
+          asset: asset.clone(),
+          free: *free,
+          locked: 0.,
+        })
+        .collect(),
+      permissions: vec![AccountType::Spot],
+      update_time: Utc::now().timestamp_millis(),
+    }),
+  )
+}
+
+async fn exchange_info(state: web::Data<State>) -> HttpResponse {
+  HttpResponse::Ok().json(ExchangeInformation {
+    timezone: "UTC".to_owned(),
+    server_time: now_millis(),
+    rate_limits: vec![],
+    symbols: state
+      .listings
+      .iter()
+      .map(|listing| Symbol {
+        symbol: listing.symbol.clone(),
+        status: "TRADING".to_owned(),
+        base_asset: listing.base.clone(),
+        base_asset_precision: 8,
+        quote_asset: listing.quote.clone(),
+        quote_precision: 8,
+        quote_asset_precision: 8,
+        base_commission_precision: 8,
+        quote_commission_precision: 8,
+        order_types: vec![OrderType::Limit, OrderType::Market],
+        iceberg_allowed: false,
+        oco_allowed: false,
+        quote_order_qty_market_allowed: false,
+        is_spot_trading_allowed: true,
+        is_margin_trading_allowed: false,
+        filters: vec![],
+        permissions: vec![],
+      })
+      .collect(),
+    exchange_filters: vec![],
+  })
+}
+
+/// A running [`MockRestServer`], stopped when dropped.
+pub struct MockRest {
+  server: super::Server,
+  state: Arc<State>,
+}
+
+impl MockRest {
+  pub fn url(&self) -> String {
+    format!("http://{}", self.server.addr())
+  }
+
+  /// Client configuration pointing the REST API at this server.
+  pub fn config(&self) -> Config {
+    Config::default().set_rest_api_endpoint(self.url())
+  }
+
+  /// Every order placed so far, in placement order.
+  pub fn orders(&self) -> Vec<Order> {
+    self.state.book.lock().unwrap().orders.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use binance::account::Account;
+  use binance::api::Binance;
+  use binance::errors::Error;
+  use binance::general::General;
+  use binance::rest_model::OrderSide;
+
+  const API_KEY: &str = "key";
+  const SECRET: &str = "secret";
+
+  fn account(server: &MockRest, secret: &str) -> Account {
+    Binance::new_with_config(
+      Some(API_KEY.to_owned()),
+      Some(secret.to_owned()),
+      &server.config(),
+    )
+  }
+
+  fn limit(side: OrderSide) -> OrderRequest {
+    OrderRequest {
+      symbol: "BTCUSDT".to_owned(),
+      side,
+      order_type: OrderType::Limit,
+      time_in_force: Some(TimeInForce::GTC),
+      quantity: Some(0.5),
+      price: Some(20_000.),
+      ..OrderRequest::default()
+    }
+  }
+
+  fn error_code(res: Result<impl std::fmt::Debug, Error>) -> i32 {
+    match res {
+      Err(Error::BinanceError { response }) => response.code,
+      other => panic!("expected a Binance error, got {other:?}"),
+    }
+  }
+
+  #[test]
+  fn signs_like_binance() {
+    // example from the Binance API documentation
+    let secret =
+      "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+    let payload = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC\
+      &quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+    assert_eq!(
+      sign(secret, payload),
+      "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+    );
+  }
+
+  #[actix_rt::test]
+  async fn fills_and_updates_balances() {
+    let server = MockRestServer::new(API_KEY, SECRET)
+      .with_balance("USDT", 20_000.)
+      .start()
+      .unwrap();
+    let account = account(&server, SECRET);
+
+    let transaction = account.place_order(limit(OrderSide::Buy)).await.unwrap();
+    assert_eq!(transaction.status, OrderStatus::Filled);
+    assert_eq!(transaction.executed_qty, 0.5);
+
+    let info = account.get_account().await.unwrap();
+    let balances: Vec<_> = info
+      .balances
+      .iter()
+      .map(|b| (b.asset.as_str(), b.free))
+      .collect();
+    assert_eq!(balances, vec![("BTC", 0.5), ("USDT", 10_000.)]);
+
+    let symbols = General::new_with_config(None, None, &server.config())
+      .exchange_info()
+      .await
+      .unwrap()
+      .symbols;
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].base_asset, "BTC");
+  }
+
+  #[actix_rt::test]
+  async fn scripted_replies() {
+    let server = MockRestServer::new(API_KEY, SECRET)
+      .with_replies(vec![
+        Reply::Reject {
+          code: -2010,
+          msg: "Account has insufficient balance.".to_owned(),
+        },
+        Reply::RateLimited,
+        Reply::Banned,
+      ])
+      .start()
+      .unwrap();
+    let account = account(&server, SECRET);
+
+    let rejected = account.place_order(limit(OrderSide::Sell)).await;
+    assert_eq!(error_code(rejected), -2010);
+    for status in ["429", "418"] {
+      match account.place_order(limit(OrderSide::Sell)).await {
+        Err(Error::Msg(msg)) => assert!(msg.contains(status), "{msg}"),
+        other => panic!("expected status {status}, got {other:?}"),
+      }
+    }
+    // the script is done
+    assert!(account.place_order(limit(OrderSide::Sell)).await.is_ok());
+    assert_eq!(server.orders().len(), 1);
+  }
+
+  #[actix_rt::test]
+  async fn cancels_open_orders() {
+    let server = MockRestServer::new(API_KEY, SECRET)
+      .with_replies(vec![Reply::Accept])
+      .start()
+      .unwrap();
+    let account = account(&server, SECRET);
+
+    let order_id = account
+      .place_order(limit(OrderSide::Buy))
+      .await
+      .unwrap()
+      .order_id;
+    let cancellation = OrderCancellation {
+      symbol: "BTCUSDT".to_owned(),
+      order_id: Some(order_id),
+      ..OrderCancellation::default()
+    };
+    account.cancel_order(cancellation.clone()).await.unwrap();
+
+    let status = account
+      .order_status(OrderStatusRequest {
+        symbol: "BTCUSDT".to_owned(),
+        order_id: Some(order_id),
+        ..OrderStatusRequest::default()
+      })
+      .await
+      .unwrap()
+      .status;
+    assert_eq!(status, OrderStatus::Canceled);
+    assert_eq!(error_code(account.cancel_order(cancellation).await), -2011);
+  }
+
+  #[actix_rt::test]
+  async fn verifies_credentials() {
+    let server = MockRestServer::new(API_KEY, SECRET).start().unwrap();
+
+    let forged = account(&server, "other secret");
+    assert_eq!(error_code(forged.get_account().await), -1022);
+
+    let anonymous: Account = Binance::new_with_config(
+      Some("other key".to_owned()),
+      Some(SECRET.to_owned()),
+      &server.config(),
+    );
+    assert!(matches!(
+      anonymous.get_account().await,
+      Err(Error::Unauthorized)
+    ));
+    assert!(server.orders().is_empty());
+  }
+}
@@ -0,0 +1,210 @@
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{web, HttpRequest, HttpResponse};
+use futures_util::StreamExt;
+use serde_json::json;
+
+use crate::binance_websocket::TickerMessage;
+
+/// What the server does next on a connection.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+  /// Sends a text frame, valid or not.
+  Text(String),
+  /// Sends a binary frame.
+  Binary(Vec<u8>),
+  Wait(Duration),
+  /// Closes the connection with a close frame.
+  Close,
+  /// Ends the connection without a close frame.
+  Drop,
+}
+
+impl Step {
+  /// A `bookTicker` payload of the combined stream, as Binance sends it.
+  pub fn book_ticker(ticker: &TickerMessage) -> Self {
+    Self::Text(book_ticker_payload(ticker))
+  }
+}
+
+/// The text of [`Step::book_ticker`].
+pub fn book_ticker_payload(ticker: &TickerMessage) -> String {
+  let mut data = json!({
+    "u": ticker.update_id,
+    "s": ticker.symbol,
+    "b": ticker.best_bid_price.to_string(),
+    "B": ticker.best_bid_qty.to_string(),
+    "a": ticker.best_ask_price.to_string(),
+    "A": ticker.best_ask_qty.to_string(),
+  });
+  if let Some(event_time) = ticker.event_time {
+    data["E"] = event_time.timestamp_millis().into();
+  }
+  let stream = format!("{}@bookTicker", ticker.symbol.to_lowercase());
+  json!({ "stream": stream, "data": data }).to_string()
+}
+
+struct State {
+  scripts: Vec<Vec<Step>>,
+  connections: AtomicUsize,
+}
+
+/// Websocket server playing one script per connection: the n-th connection
+/// gets the n-th script, later ones none. Once its script is done a
+/// connection stays open until the client leaves.
+pub struct MockStreamServer {
+  scripts: Vec<Vec<Step>>,
+}
+
+impl MockStreamServer {
+  pub fn new(scripts: Vec<Vec<Step>>) -> Self {
+    Self { scripts }
+  }
+
+  /// Listens on a free local port. Must be called within an actix system.
+  pub fn start(self) -> io::Result<MockStream> {
+    let state = web::Data::new(State {
+      scripts: self.scripts,
+      connections: AtomicUsize::new(0),
+    });
+    let app_state = state.clone();
+    let server = super::start(move |app| {
+      app
+        .app_data(app_state.clone())
+        .route("/stream", web::get().to(stream))
+        .route("/ws", web::get().to(stream));
+    })?;
+    Ok(MockStream {
+      server,
+      state: state.into_inner(),
+    })
+  }
+}
+
+async fn stream(
+  req: HttpRequest,
+  body: web::Payload,
+  state: web::Data<State>,
+) -> actix_web::Result<HttpResponse> {
+  let (response, mut session, mut incoming) = actix_ws::handle(&req, body)?;
+  let n = state.connections.fetch_add(1, Ordering::SeqCst);
+  let script = state.scripts.get(n).cloned().unwrap_or_default();
+
+  actix_rt::spawn(async move {
+    for step in script {
+      let sent = match step {
+        Step::Text(text) => session.text(text).await,
+        Step::Binary(bytes) => session.binary(bytes).await,
+        Step::Wait(duration) => {
+          actix_rt::time::sleep(duration).await;
+          Ok(())
+        }
+        Step::Close => {
+          let _ = session.close(None).await;
+          return;
+        }
+        Step::Drop => return,
+      };
+      if sent.is_err() {
+        return;
+      }
+    }
+    while incoming.next().await.is_some() {}
+  });
+
+  Ok(response)
+}
+
+/// A running [`MockStreamServer`], stopped when dropped.
+pub struct MockStream {
+  server: super::Server,
+  state: Arc<State>,
+}
+
+impl MockStream {
+  /// Combined stream endpoint to give the ingestor.
+  pub fn url(&self) -> String {
+    format!(
+      "ws://{}/stream?streams=btcusdt@bookTicker",
+      self.server.addr()
+    )
+  }
+
+  /// Number of connections accepted so far.
+  pub fn connections(&self) -> usize {
+    self.state.connections.load(Ordering::SeqCst)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::binance_websocket::BinanceIngestor;
+
+  fn ticker(update_id: u64) -> TickerMessage {
+    TickerMessage {
+      update_id,
+      symbol: "BTCUSDT".to_owned(),
+      best_bid_price: 19_000.5,
+      best_bid_qty: 1.,
+      best_ask_price: 19_001.,
+      best_ask_qty: 2.,
+      ..Default::default()
+    }
+  }
+
+  /// Checks the ingestor stamped every ticker, then drops the stamps.
+  fn stamped(mut tickers: Vec<TickerMessage>) -> Vec<TickerMessage> {
+    for ticker in &mut tickers {
+      assert!(ticker.received.take().is_some(), "{ticker:?}");
+    }
+    tickers
+  }
+
+  #[actix_rt::test]
+  async fn skips_malformed_frames() {
+    let server = MockStreamServer::new(vec![vec![
+      Step::book_ticker(&ticker(1)),
+      Step::Text("{not json".to_owned()),
+      Step::Binary(vec![0, 159, 146, 150]),
+      Step::book_ticker(&ticker(2)),
+      Step::Close,
+    ]])
+    .start()
+    .unwrap();
+    let (collector, recipient) = collect::<TickerMessage>();
+
+    BinanceIngestor::new(vec![recipient], vec![])
+      .with_url(server.url())
+      .run()
+      .await;
+
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(stamped(received), vec![ticker(1), ticker(2)]);
+  }
+
+  #[actix_rt::test]
+  async fn reconnects_after_drop() {
+    let server = MockStreamServer::new(vec![
+      vec![Step::book_ticker(&ticker(1)), Step::Drop],
+      vec![Step::book_ticker(&ticker(2)), Step::Close],
+    ])
+    .start()
+    .unwrap();
+    let (collector, recipient) = collect::<TickerMessage>();
+
+    BinanceIngestor::new(vec![recipient], vec![])
+      .with_url(server.url())
+      .with_reconnects(1, Duration::from_millis(10))
+      .run()
+      .await;
+
+    assert_eq!(server.connections(), 2);
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_eq!(stamped(received), vec![ticker(1), ticker(2)]);
+  }
+}
@@ -1,16 +1,20 @@
-use crate::actors::mid_price::{MidPrice, MidPriceResponse};
+use crate::actors::mid_price::MidPrice;
 use crate::actors::moving_average::MovingAverageMessage;
+use crate::correlation::{self, Correlated, Correlation};
+use crate::metrics::{Metrics, Ping};
 use crate::trade::{Buy, Hold, Sell};
 use crate::util::deserialize_from_str;
 
 use actix::{Actor, Context, Handler, Message, MessageResult, Recipient};
-use chrono::Utc;
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 
 pub struct PolicyMakerActor {
   current_true_price: f64,
   frame: PolicyFrame,
+  parameters: PolicyParameters,
   recipients: Vec<Recipient<PolicyDecision>>, // TODO
+  metrics: Option<Metrics>,
 }
 
 impl Actor for PolicyMakerActor {
@@ -19,10 +23,48 @@ impl Actor for PolicyMakerActor {
   fn started(&mut self, _ctx: &mut Context<Self>) {}
 
   fn stopped(&mut self, _ctx: &mut Context<Self>) {
-    println!("Actor is stopped");
+    tracing::info!("Policy maker stopped");
   }
 }
 
+/// Tunable parameters of the trend following policy.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct PolicyParameters {
+  /// Quantity of every buy and sell order.
+  pub quantity: f64,
+  /// Fraction the true price has to be above the moving average to buy,
+  /// or below it to sell.
+  pub threshold: f64,
+}
+
+impl PolicyParameters {
+  /// Parameters with a positive quantity and a non-negative threshold.
+  pub fn new(quantity: f64, threshold: f64) -> Option<Self> {
+    (quantity > 0. && quantity.is_finite() && threshold >= 0.).then_some(Self {
+      quantity,
+      threshold,
+    })
+  }
+}
+
+impl Default for PolicyParameters {
+  fn default() -> Self {
+    Self {
+      quantity: 0.1,
+      threshold: 0.,
+    }
+  }
+}
+
+/// Replaces the parameters of a running [`PolicyMakerActor`].
+#[derive(Message, Debug, Clone, Copy)]
+#[rtype(result = "()")]
+pub struct SetParameters(pub PolicyParameters);
+
+#[derive(Message)]
+#[rtype(result = "PolicyParameters")]
+pub struct GetParameters;
+
 // PolicyFrame is a snapshot in time, containing all parameters
 // necessary to make a policy decision
 pub struct PolicyFrame {
@@ -31,17 +73,35 @@ pub struct PolicyFrame {
   true_price_gradient: f64,
   moving_average_price: f64,
   true_price: f64,
+  /// Event time of the latest mid price, decisions are stamped with it.
+  timestamp: DateTime<Utc>,
+  /// Ticker of the latest mid price or moving average, decisions carry it.
+  correlation: Option<Correlation>,
   prev_decision: Option<PolicyDecision>,
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Serialize, Debug, Clone)]
 #[rtype(result = "()")]
+#[serde(tag = "action")]
 pub enum PolicyDecision {
+  #[serde(rename = "buy")]
   BuyAction(Buy),
+  #[serde(rename = "sell")]
   SellAction(Sell),
+  #[serde(rename = "hold")]
   HoldAction(Hold),
 }
 
+impl Correlated for PolicyDecision {
+  fn correlation(&self) -> Option<Correlation> {
+    match self {
+      Self::BuyAction(buy) => buy.correlation,
+      Self::SellAction(sell) => sell.correlation,
+      Self::HoldAction(hold) => hold.correlation,
+    }
+  }
+}
+
 impl PolicyMakerActor {
   pub fn new(recipients: Vec<Recipient<PolicyDecision>>) -> Self {
     Self {
@@ -52,13 +112,41 @@ impl PolicyMakerActor {
         true_price_gradient: 0.0,
         moving_average_price: 0.0,
         true_price: 0.0,
+        timestamp: DateTime::UNIX_EPOCH,
+        correlation: None,
         prev_decision: None,
       },
+      parameters: PolicyParameters::default(),
       recipients,
+      metrics: None,
     }
   }
 
+  /// Counts the messages handled and the decisions per action.
+  pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+    self.metrics = Some(metrics.clone());
+    self
+  }
+
+  fn handled(&self) {
+    if let Some(metrics) = &self.metrics {
+      metrics.handled("policy_maker");
+    }
+  }
+
+  pub fn with_parameters(mut self, parameters: PolicyParameters) -> Self {
+    self.parameters = parameters;
+    self
+  }
+
   fn propagate_decision(&self, decision: PolicyDecision) {
+    if let Some(metrics) = &self.metrics {
+      metrics.decided(match decision {
+        PolicyDecision::BuyAction(_) => "buy",
+        PolicyDecision::SellAction(_) => "sell",
+        PolicyDecision::HoldAction(_) => "hold",
+      });
+    }
     for recipient in self.recipients.iter() {
       recipient.do_send(decision.clone());
     }
@@ -74,24 +162,27 @@ impl PolicyMakerActor {
   //
   // else, hold and do nothing
   fn make_policy_decision(&self, frame: &PolicyFrame) -> PolicyDecision {
-    if should_buy(frame) {
+    if should_buy(frame, &self.parameters) {
       PolicyDecision::BuyAction(Buy {
         symbol: frame.symbol.clone(),
-        quantity: 0.1,
+        quantity: self.parameters.quantity,
         price: frame.true_price,
-        timestamp: Utc::now(),
+        timestamp: frame.timestamp,
+        correlation: frame.correlation,
       })
-    } else if should_sell(frame) {
+    } else if should_sell(frame, &self.parameters) {
       PolicyDecision::SellAction(Sell {
         symbol: frame.symbol.clone(),
-        quantity: 0.1,
+        quantity: self.parameters.quantity,
         price: frame.true_price,
-        timestamp: Utc::now(),
+        timestamp: frame.timestamp,
+        correlation: frame.correlation,
       })
     } else {
       PolicyDecision::HoldAction(Hold {
         symbol: frame.symbol.clone(),
-        timestamp: Utc::now(),
+        timestamp: frame.timestamp,
+        correlation: frame.correlation,
       })
     }
   }
@@ -115,21 +206,21 @@ pub struct MovingMessage {
 }
 
 impl Handler<MidPrice> for PolicyMakerActor {
-  type Result = MessageResult<MidPrice>;
+  type Result = ();
 
   // Handle true price (TickerMessage), always keep the latest true price
   // The actual decision making is done when handling moving average message
-  fn handle(
-    &mut self,
-    msg: MidPrice,
-    _ctx: &mut Context<Self>,
-  ) -> Self::Result {
+  fn handle(&mut self, msg: MidPrice, _ctx: &mut Context<Self>) {
+    let _span = correlation::span("policy_maker", msg.correlation).entered();
+    self.handled();
     let prev_true_price = self.current_true_price;
     self.current_true_price = msg.price;
 
     let frame = PolicyFrame {
       true_price_gradient: self.current_true_price - prev_true_price,
       true_price: self.current_true_price,
+      timestamp: msg.timestamp,
+      correlation: msg.correlation,
       symbol: msg.symbol,
       prev_decision: self.frame.prev_decision.take(),
       // TODO: update
@@ -137,8 +228,6 @@ impl Handler<MidPrice> for PolicyMakerActor {
       moving_average_price: 0.,
     };
     self.frame = frame;
-
-    MessageResult(MidPriceResponse::MovingAverage(0.))
   }
 }
 
@@ -150,6 +239,11 @@ impl Handler<MovingAverageMessage> for PolicyMakerActor {
     msg: MovingAverageMessage,
     _ctx: &mut Context<Self>,
   ) -> f64 {
+    let _span = correlation::span("policy_maker", msg.1).entered();
+    self.handled();
+    if msg.1.is_some() {
+      self.frame.correlation = msg.1;
+    }
     let _prev_moving_price = self.frame.moving_average_price;
 
     self.frame.moving_average_gradient =
@@ -157,21 +251,50 @@ impl Handler<MovingAverageMessage> for PolicyMakerActor {
     self.frame.moving_average_price = msg.0;
 
     let decision = self.make_policy_decision(&self.frame);
-    log::info!("Policy decided: {:?}", decision);
+    tracing::info!(?decision, "Policy decided");
     self.propagate_decision(decision);
     msg.0
   }
 }
 
-fn should_buy(frame: &PolicyFrame) -> bool {
+impl Handler<SetParameters> for PolicyMakerActor {
+  type Result = ();
+
+  fn handle(&mut self, msg: SetParameters, _ctx: &mut Context<Self>) {
+    tracing::info!(parameters = ?msg.0, "Policy parameters set");
+    self.parameters = msg.0;
+  }
+}
+
+impl Handler<Ping> for PolicyMakerActor {
+  type Result = ();
+
+  fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+}
+
+impl Handler<GetParameters> for PolicyMakerActor {
+  type Result = MessageResult<GetParameters>;
+
+  fn handle(
+    &mut self,
+    _: GetParameters,
+    _ctx: &mut Context<Self>,
+  ) -> Self::Result {
+    MessageResult(self.parameters)
+  }
+}
+
+fn should_buy(frame: &PolicyFrame, parameters: &PolicyParameters) -> bool {
   is_rising_trend(frame)
-    && frame.moving_average_price < frame.true_price
+    && frame.moving_average_price * (1. + parameters.threshold)
+      < frame.true_price
     && !(matches!(frame.prev_decision, Some(PolicyDecision::BuyAction(_))))
 }
 
-fn should_sell(frame: &PolicyFrame) -> bool {
+fn should_sell(frame: &PolicyFrame, parameters: &PolicyParameters) -> bool {
   is_downward_trend(frame)
-    && frame.moving_average_price > frame.true_price
+    && frame.moving_average_price * (1. - parameters.threshold)
+      > frame.true_price
     && !(matches!(frame.prev_decision, Some(PolicyDecision::SellAction(_))))
 }
 
@@ -191,12 +314,15 @@ mod test {
   use super::PolicyDecision;
   use super::PolicyFrame;
   use super::PolicyMakerActor;
+  use super::PolicyParameters;
 
   use super::{Buy, Sell};
+  use crate::actors::collector::{collect, Take};
   use crate::trade::TradeActor;
   use actix::Actor;
+  use assert_matches::assert_matches;
 
-  use chrono::Utc;
+  use chrono::{TimeZone, Utc};
 
   #[actix_rt::test]
   #[ignore]
@@ -209,17 +335,45 @@ mod test {
     addr.do_send(MidPrice {
       symbol: "BTCUSDT".to_string(),
       price: 100.,
+      timestamp: Utc::now(),
+      correlation: None,
     });
-    addr.send(MovingAverageMessage(10.)).await.unwrap();
+    addr.send(MovingAverageMessage::from(10.)).await.unwrap();
 
     addr
       .send(MidPrice {
         symbol: "BTCUSDT".to_string(),
         price: 1000.,
+        timestamp: Utc::now(),
+        correlation: None,
+      })
+      .await
+      .unwrap();
+    addr.do_send(MovingAverageMessage::from(100.));
+  }
+
+  #[actix_rt::test]
+  async fn test_decision_event_time() {
+    let (collector, recipient) = collect::<PolicyDecision>();
+    let addr = PolicyMakerActor::new(vec![recipient]).start();
+    let event_time = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    addr
+      .send(MidPrice {
+        symbol: "BTCUSDT".to_string(),
+        price: 100.,
+        timestamp: event_time,
+        correlation: None,
       })
       .await
       .unwrap();
-    addr.do_send(MovingAverageMessage(100.));
+    addr.send(MovingAverageMessage::from(10.)).await.unwrap();
+
+    let received = collector.send(Take::new()).await.unwrap();
+    assert_matches!(
+      &received[..],
+      [PolicyDecision::BuyAction(Buy { timestamp, .. })]
+        if *timestamp == event_time
+    );
   }
 
   #[test]
@@ -229,6 +383,7 @@ mod test {
       quantity: 0.1,
       price: 10.0,
       timestamp: Utc::now(),
+      correlation: None,
     };
 
     let frame = PolicyFrame {
@@ -237,16 +392,39 @@ mod test {
       true_price_gradient: 1.0,
       moving_average_price: 10.0,
       true_price: 20.0,
+      timestamp: Utc::now(),
+      correlation: None,
       prev_decision: Some(PolicyDecision::SellAction(sell)),
     };
 
-    let result = super::should_buy(&frame);
+    let result = super::should_buy(&frame, &PolicyParameters::default());
     assert!(
       result,
       "True price trending upwards and higher than avg price, should buy"
     );
   }
 
+  #[test]
+  fn test_threshold() {
+    let frame = PolicyFrame {
+      symbol: "btcusdt".to_string(),
+      moving_average_gradient: 1.0,
+      true_price_gradient: 1.0,
+      moving_average_price: 10.0,
+      true_price: 12.0,
+      timestamp: Utc::now(),
+      correlation: None,
+      prev_decision: None,
+    };
+    let parameters = |threshold| PolicyParameters {
+      quantity: 1.,
+      threshold,
+    };
+
+    assert!(super::should_buy(&frame, &parameters(0.1)));
+    assert!(!super::should_buy(&frame, &parameters(0.25)));
+  }
+
   #[test]
   fn test_not_should_buy_again() {
     let buy = Buy {
@@ -254,6 +432,7 @@ mod test {
       quantity: 0.1,
       price: 10.0,
       timestamp: Utc::now(),
+      correlation: None,
     };
 
     let frame = PolicyFrame {
@@ -262,10 +441,12 @@ mod test {
       true_price_gradient: 1.0,
       moving_average_price: 10.0,
       true_price: 20.0,
+      timestamp: Utc::now(),
+      correlation: None,
       prev_decision: Some(PolicyDecision::BuyAction(buy)),
     };
 
-    let result = super::should_buy(&frame);
+    let result = super::should_buy(&frame, &PolicyParameters::default());
     assert!(
       !result,
       "Prev decision was already buy, should not buy again"
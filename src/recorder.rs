@@ -0,0 +1,390 @@
+//! Captures market data streams to disk for replays and post mortems.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use actix::{Actor, Context, Handler, Message};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::backtest::Flush;
+use crate::binance_websocket::{AccountUpdateMessage, TickerMessage};
+use crate::clock::{SharedClock, WallClock};
+
+/// Any message of the streams a [`Recorder`] captures.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+  BookTicker(TickerMessage),
+  AccountUpdate(AccountUpdateMessage),
+}
+
+impl From<TickerMessage> for MarketEvent {
+  fn from(msg: TickerMessage) -> Self {
+    Self::BookTicker(msg)
+  }
+}
+
+impl From<AccountUpdateMessage> for MarketEvent {
+  fn from(msg: AccountUpdateMessage) -> Self {
+    Self::AccountUpdate(msg)
+  }
+}
+
+/// A captured event with the time it was received.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Record {
+  pub received_at: DateTime<Utc>,
+  pub event: MarketEvent,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+  /// One JSON object per line.
+  JsonLines,
+  /// Consecutive bincode encoded records.
+  Binary,
+}
+
+impl Format {
+  pub fn extension(self) -> &'static str {
+    match self {
+      Self::JsonLines => "jsonl",
+      Self::Binary => "bin",
+    }
+  }
+}
+
+/// When a [`Recorder`] starts a new file, whichever limit is hit first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+  pub max_bytes: Option<u64>,
+  pub max_age: Option<Duration>,
+}
+
+impl Default for Rotation {
+  /// A new file every hour.
+  fn default() -> Self {
+    Self {
+      max_bytes: None,
+      max_age: Some(Duration::hours(1)),
+    }
+  }
+}
+
+struct Output {
+  writer: BufWriter<File>,
+  opened_at: DateTime<Utc>,
+  bytes: u64,
+}
+
+/// Writes every [`MarketEvent`] it receives, stamped with the receive time,
+/// to `{prefix}-{opened at}.{extension}` files in `dir`, suffixed with a
+/// counter when a file was opened at the same instant before. Writes are buffered
+/// and flushed on rotation, on [`Flush`] and when the actor stops.
+pub struct Recorder {
+  dir: PathBuf,
+  prefix: String,
+  format: Format,
+  rotation: Rotation,
+  clock: SharedClock,
+  output: Option<Output>,
+}
+
+impl Recorder {
+  /// Creates `dir` if needed.
+  pub fn new(
+    dir: impl AsRef<Path>,
+    prefix: impl Into<String>,
+    format: Format,
+  ) -> io::Result<Self> {
+    fs::create_dir_all(&dir)?;
+    Ok(Self {
+      dir: dir.as_ref().to_owned(),
+      prefix: prefix.into(),
+      format,
+      rotation: Rotation::default(),
+      clock: Arc::new(WallClock),
+      output: None,
+    })
+  }
+
+  pub fn with_rotation(mut self, rotation: Rotation) -> Self {
+    self.rotation = rotation;
+    self
+  }
+
+  /// Clock the receive times are read from.
+  pub fn with_clock(mut self, clock: SharedClock) -> Self {
+    self.clock = clock;
+    self
+  }
+
+  pub fn flush(&mut self) -> io::Result<()> {
+    match &mut self.output {
+      Some(output) => output.writer.flush(),
+      None => Ok(()),
+    }
+  }
+
+  fn is_due(&self, output: &Output, now: DateTime<Utc>) -> bool {
+    let Rotation { max_bytes, max_age } = self.rotation;
+    max_bytes.is_some_and(|max| output.bytes >= max)
+      || max_age.is_some_and(|max| now - output.opened_at >= max)
+  }
+
+  fn output(&mut self, now: DateTime<Utc>) -> io::Result<&mut Output> {
+    if let Some(output) = &self.output {
+      if self.is_due(output, now) {
+        self.flush()?;
+        self.output = None;
+      }
+    }
+    if self.output.is_none() {
+      let file = self.create(now)?;
+      self.output = Some(Output {
+        writer: BufWriter::new(file),
+        opened_at: now,
+        bytes: 0,
+      });
+    }
+    Ok(self.output.as_mut().expect("opened above"))
+  }
+
+  /// Never overwrites a previous file.
+  fn create(&self, now: DateTime<Utc>) -> io::Result<File> {
+    let stem = format!("{}-{}", self.prefix, now.format("%Y%m%dT%H%M%S%.6fZ"));
+    let extension = self.format.extension();
+    let mut name = format!("{stem}.{extension}");
+    let mut n = 0;
+    loop {
+      let opened = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(self.dir.join(&name));
+      match opened {
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+          n += 1;
+          name = format!("{stem}-{n}.{extension}");
+        }
+        opened => return opened,
+      }
+    }
+  }
+
+  pub(crate) fn write(&mut self, event: MarketEvent) -> io::Result<()> {
+    let now = self.clock.now();
+    let record = Record {
+      received_at: now,
+      event,
+    };
+    let bytes = match self.format {
+      Format::JsonLines => {
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        line
+      }
+      Format::Binary => bincode::serialize(&record)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+    };
+    let output = self.output(now)?;
+    output.writer.write_all(&bytes)?;
+    output.bytes += bytes.len() as u64;
+    Ok(())
+  }
+}
+
+impl Actor for Recorder {
+  type Context = Context<Self>;
+
+  fn stopped(&mut self, _ctx: &mut Context<Self>) {
+    if let Err(e) = self.flush() {
+      tracing::error!(error = ?e, "Recorder couldn't flush on stop");
+    }
+  }
+}
+
+impl<M> Handler<M> for Recorder
+where
+  M: Message<Result = ()> + 'static,
+  MarketEvent: From<M>,
+{
+  type Result = ();
+
+  fn handle(&mut self, msg: M, _ctx: &mut Context<Self>) {
+    if let Err(e) = self.write(msg.into()) {
+      tracing::error!(dir = ?self.dir, error = ?e, "Recorder couldn't write");
+    }
+  }
+}
+
+impl Handler<Flush> for Recorder {
+  type Result = ();
+
+  fn handle(&mut self, _: Flush, _ctx: &mut Context<Self>) {
+    if let Err(e) = self.flush() {
+      tracing::error!(dir = ?self.dir, error = ?e, "Recorder couldn't flush");
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::clock::SimulatedClock;
+  use chrono::TimeZone;
+  use std::io::{BufRead, BufReader};
+
+  fn ticker(update_id: u64) -> TickerMessage {
+    TickerMessage {
+      update_id,
+      symbol: "BTCUSDT".to_owned(),
+      best_bid_price: 19_000.5,
+      best_ask_price: 19_001.25,
+      ..Default::default()
+    }
+  }
+
+  fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<_> = fs::read_dir(dir)
+      .unwrap()
+      .map(|entry| entry.unwrap().path())
+      .collect();
+    files.sort();
+    files
+  }
+
+  #[actix_rt::test]
+  async fn json_lines_rotated_by_age() {
+    let dir = tempfile::tempdir().unwrap();
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    let clock = SimulatedClock::new(start);
+    let recorder = Recorder::new(dir.path(), "btcusdt", Format::JsonLines)
+      .unwrap()
+      .with_rotation(Rotation {
+        max_bytes: None,
+        max_age: Some(Duration::minutes(1)),
+      })
+      .with_clock(Arc::new(clock.clone()))
+      .start();
+
+    recorder.send(ticker(1)).await.unwrap();
+    clock.set(start + Duration::seconds(30));
+    let account = AccountUpdateMessage {
+      e: "outboundAccountPosition".to_owned(),
+      ..Default::default()
+    };
+    recorder.send(account.clone()).await.unwrap();
+    clock.set(start + Duration::seconds(90));
+    recorder.send(ticker(2)).await.unwrap();
+    // the last file is flushed once the recorder stops
+    drop(recorder);
+    let read = || -> Vec<Record> {
+      files(dir.path())
+        .iter()
+        .flat_map(|path| BufReader::new(File::open(path).unwrap()).lines())
+        .map(|line| serde_json::from_str(&line.unwrap()).unwrap())
+        .collect()
+    };
+    let mut records = read();
+    for _ in 0..100 {
+      if records.len() == 3 {
+        break;
+      }
+      actix_rt::time::sleep(std::time::Duration::from_millis(10)).await;
+      records = read();
+    }
+
+    assert_eq!(files(dir.path()).len(), 2);
+    assert_eq!(
+      records,
+      vec![
+        Record {
+          received_at: start,
+          event: MarketEvent::BookTicker(ticker(1)),
+        },
+        Record {
+          received_at: start + Duration::seconds(30),
+          event: MarketEvent::AccountUpdate(account),
+        },
+        Record {
+          received_at: start + Duration::seconds(90),
+          event: MarketEvent::BookTicker(ticker(2)),
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn rotates_at_the_same_instant() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut recorder = Recorder::new(dir.path(), "btcusdt", Format::JsonLines)
+      .unwrap()
+      .with_rotation(Rotation {
+        max_bytes: Some(1),
+        max_age: None,
+      })
+      .with_clock(Arc::new(SimulatedClock::default()));
+
+    for update_id in 0..3 {
+      recorder.write(ticker(update_id).into()).unwrap();
+    }
+    recorder.flush().unwrap();
+
+    let records: Vec<Record> = files(dir.path())
+      .iter()
+      .map(|path| serde_json::from_reader(File::open(path).unwrap()).unwrap())
+      .collect();
+    let mut update_ids: Vec<_> = records
+      .into_iter()
+      .map(|record| match record.event {
+        MarketEvent::BookTicker(ticker) => ticker.update_id,
+        event => panic!("{event:?}"),
+      })
+      .collect();
+    update_ids.sort();
+    assert_eq!(update_ids, [0, 1, 2]);
+  }
+
+  #[actix_rt::test]
+  async fn flushes_on_request() {
+    let dir = tempfile::tempdir().unwrap();
+    let recorder = Recorder::new(dir.path(), "btcusdt", Format::JsonLines)
+      .unwrap()
+      .start();
+    recorder.send(ticker(1)).await.unwrap();
+    recorder.send(Flush).await.unwrap();
+
+    let files = files(dir.path());
+    let record: Record =
+      serde_json::from_reader(File::open(&files[0]).unwrap()).unwrap();
+    assert_eq!(record.event, MarketEvent::BookTicker(ticker(1)));
+  }
+
+  #[actix_rt::test]
+  async fn binary_rotated_by_size() {
+    let dir = tempfile::tempdir().unwrap();
+    let clock = SimulatedClock::default();
+    let mut recorder = Recorder::new(dir.path(), "btcusdt", Format::Binary)
+      .unwrap()
+      .with_rotation(Rotation {
+        max_bytes: Some(1),
+        max_age: None,
+      })
+      .with_clock(Arc::new(clock.clone()));
+
+    let start = Utc::now();
+    for update_id in 0..3 {
+      clock.set(start + Duration::seconds(update_id as i64));
+      recorder.write(ticker(update_id).into()).unwrap();
+    }
+    recorder.flush().unwrap();
+
+    let files = files(dir.path());
+    assert_eq!(files.len(), 3);
+    let last: Record =
+      bincode::deserialize_from(File::open(&files[2]).unwrap()).unwrap();
+    assert_eq!(last.event, MarketEvent::BookTicker(ticker(2)));
+  }
+}
@@ -0,0 +1,403 @@
+//! Plays recorded market data back to the recipients a
+//! [`BinanceIngestor`](crate::binance_websocket::BinanceIngestor) would feed.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+use actix::Recipient;
+use actix_rt::time::{sleep_until, Instant};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::binance_websocket::{
+  BinanceMessage, BinanceMessageContent, TickerMessage,
+};
+use crate::clock::SimulatedClock;
+use crate::recorder::{self, MarketEvent, Record};
+
+/// Layout of a file to replay.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayFormat {
+  /// Raw combined stream payloads, one per line, timed by their event time.
+  BinanceJsonLines,
+  /// Top of book rows with a header, as in the Binance `bookTicker` dumps,
+  /// timed by their event or transaction time in milliseconds. The rows
+  /// don't name the symbol.
+  TopOfBookCsv { symbol: String },
+  /// Output of a [`recorder::Recorder`], timed by the receive time.
+  Recording(recorder::Format),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Speed {
+  AsFastAsPossible,
+  RealTime,
+  /// Multiple of real time, `2.` plays twice as fast.
+  Scaled(f64),
+}
+
+/// A ticker along with the time it happened, if known.
+pub type TimedTicker = (Option<DateTime<Utc>>, TickerMessage);
+
+#[derive(Deserialize)]
+struct TopOfBookRow {
+  update_id: u64,
+  best_bid_price: f64,
+  best_bid_qty: f64,
+  best_ask_price: f64,
+  best_ask_qty: f64,
+  #[serde(default)]
+  transaction_time: Option<i64>,
+  #[serde(default)]
+  event_time: Option<i64>,
+}
+
+fn invalid_data(
+  e: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Reads the tickers of a file in order, skipping other streams. Lines of
+/// raw payloads that don't parse are logged and skipped like the ingestor
+/// does, other malformed input ends the iteration with an error.
+pub fn read_tickers(
+  path: impl AsRef<Path>,
+  format: &ReplayFormat,
+) -> io::Result<Box<dyn Iterator<Item = io::Result<TimedTicker>>>> {
+  let reader = BufReader::new(File::open(path)?);
+  Ok(match format {
+    ReplayFormat::BinanceJsonLines => {
+      Box::new(reader.lines().filter_map(|line| {
+        let line = match line {
+          Ok(line) if line.trim().is_empty() => return None,
+          Ok(line) => line,
+          Err(e) => return Some(Err(e)),
+        };
+        match serde_json::from_str::<BinanceMessage>(&line) {
+          Ok(BinanceMessage {
+            data: BinanceMessageContent::BookTicker(ticker),
+          }) => Some(Ok((ticker.event_time, ticker))),
+          Ok(_) => None,
+          Err(e) => {
+            tracing::error!(
+              ?line,
+              error = ?e,
+              "Replay couldn't deserialize message"
+            );
+            None
+          }
+        }
+      }))
+    }
+    ReplayFormat::TopOfBookCsv { symbol } => {
+      let symbol = symbol.clone();
+      let rows = csv::Reader::from_reader(reader).into_deserialize();
+      Box::new(rows.map(move |row| {
+        let row: TopOfBookRow = row.map_err(invalid_data)?;
+        let time = row
+          .event_time
+          .or(row.transaction_time)
+          .map(|millis| {
+            DateTime::from_timestamp_millis(millis)
+              .ok_or_else(|| invalid_data("timestamp out of range"))
+          })
+          .transpose()?;
+        let ticker = TickerMessage {
+          update_id: row.update_id,
+          symbol: symbol.clone(),
+          best_bid_price: row.best_bid_price,
+          best_bid_qty: row.best_bid_qty,
+          best_ask_price: row.best_ask_price,
+          best_ask_qty: row.best_ask_qty,
+          event_time: time,
+          received: None,
+        };
+        Ok((time, ticker))
+      }))
+    }
+    ReplayFormat::Recording(recorder::Format::JsonLines) => {
+      Box::new(reader.lines().filter_map(|line| {
+        let record = line.and_then(|line| {
+          serde_json::from_str::<Record>(&line).map_err(invalid_data)
+        });
+        ticker_of(record).transpose()
+      }))
+    }
+    ReplayFormat::Recording(recorder::Format::Binary) => {
+      Box::new(BinaryRecords(reader).filter_map(|r| ticker_of(r).transpose()))
+    }
+  })
+}
+
+fn ticker_of(record: io::Result<Record>) -> io::Result<Option<TimedTicker>> {
+  Ok(match record? {
+    Record {
+      received_at,
+      event: MarketEvent::BookTicker(ticker),
+    } => Some((Some(received_at), ticker)),
+    _ => None,
+  })
+}
+
+struct BinaryRecords<R>(R);
+
+impl<R: Read> Iterator for BinaryRecords<R> {
+  type Item = io::Result<Record>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match bincode::deserialize_from(&mut self.0) {
+      Ok(record) => Some(Ok(record)),
+      Err(e) => match *e {
+        bincode::ErrorKind::Io(e)
+          if e.kind() == io::ErrorKind::UnexpectedEof =>
+        {
+          None
+        }
+        e => Some(Err(invalid_data(e))),
+      },
+    }
+  }
+}
+
+/// Delivers the tickers of recorded files, in order, to the same recipients
+/// [`BinanceIngestor::new`](crate::binance_websocket::BinanceIngestor::new)
+/// takes. Tickers without a time are delivered right away.
+pub struct Replay {
+  files: Vec<PathBuf>,
+  format: ReplayFormat,
+  speed: Speed,
+  clock: Option<SimulatedClock>,
+  book_ticker_recipients: Vec<Recipient<TickerMessage>>,
+}
+
+impl Replay {
+  pub fn new(
+    files: impl IntoIterator<Item = impl Into<PathBuf>>,
+    format: ReplayFormat,
+    book_ticker_recipients: Vec<Recipient<TickerMessage>>,
+  ) -> Self {
+    Self {
+      files: files.into_iter().map(Into::into).collect(),
+      format,
+      speed: Speed::AsFastAsPossible,
+      clock: None,
+      book_ticker_recipients,
+    }
+  }
+
+  /// `None` for non-positive scales.
+  pub fn with_speed(mut self, speed: Speed) -> Option<Self> {
+    match speed {
+      Speed::Scaled(scale) if scale.is_nan() || scale <= 0. => None,
+      speed => {
+        self.speed = speed;
+        Some(self)
+      }
+    }
+  }
+
+  /// Advances `clock` to the time of every ticker before delivering it.
+  pub fn with_clock(mut self, clock: SimulatedClock) -> Self {
+    self.clock = Some(clock);
+    self
+  }
+
+  /// Replays every file, returning the number of tickers delivered. Fails
+  /// when a scale is so small the wait until a ticker overflows.
+  pub async fn run(self) -> io::Result<u64> {
+    let scale = match self.speed {
+      Speed::AsFastAsPossible => None,
+      Speed::RealTime => Some(1.),
+      Speed::Scaled(scale) => Some(scale),
+    };
+    let mut start: Option<(Instant, DateTime<Utc>)> = None;
+    let mut delivered = 0;
+
+    for path in &self.files {
+      for ticker in read_tickers(path, &self.format)? {
+        let (time, ticker) = ticker?;
+        if let (Some(scale), Some(time)) = (scale, time) {
+          let (instant, first) = *start.get_or_insert((Instant::now(), time));
+          let elapsed = (time - first).to_std().unwrap_or_default();
+          let deadline = std::time::Duration::try_from_secs_f64(
+            elapsed.as_secs_f64() / scale,
+          )
+          .ok()
+          .and_then(|delay| instant.checked_add(delay))
+          .ok_or_else(|| {
+            io::Error::new(
+              io::ErrorKind::InvalidInput,
+              format!("replaying {elapsed:?} at {scale}x overflows"),
+            )
+          })?;
+          sleep_until(deadline).await;
+        }
+        if let (Some(clock), Some(time)) = (&self.clock, time) {
+          clock.advance(time);
+        }
+        tracing::debug!(
+          correlation_id = ticker.update_id,
+          ?ticker,
+          "Replaying ticker message"
+        );
+        for r in &self.book_ticker_recipients {
+          r.do_send(ticker.clone());
+        }
+        delivered += 1;
+      }
+    }
+    Ok(delivered)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::actors::collector::{collect, Take};
+  use crate::clock::Clock;
+  use crate::recorder::{Format, Recorder};
+  use chrono::{Duration, TimeZone};
+  use std::io::Write;
+  use std::sync::Arc;
+
+  const PAYLOADS: &str = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"10.0","B":"1.0","a":"11.0","A":"2.0","E":1664625600000}}
+{"stream":"btcusdt@bookTicker","data":{"u":2,"s":"BTCUSDT","b":"10.5",
+{"stream":"btcusdt@bookTicker","data":{"u":3,"s":"BTCUSDT","b":"12.0","B":"1.0","a":"13.0","A":"2.0","E":1664625600100}}
+"#;
+
+  fn write(contents: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+  }
+
+  #[actix_rt::test]
+  async fn raw_payloads() {
+    let file = write(PAYLOADS);
+    let (collector, recipient) = collect::<TickerMessage>();
+    let clock = SimulatedClock::default();
+    let replay = Replay::new(
+      [file.path()],
+      ReplayFormat::BinanceJsonLines,
+      vec![recipient],
+    )
+    .with_clock(clock.clone());
+
+    assert_eq!(replay.run().await.unwrap(), 2);
+    let received = collector.send(Take::new()).await.unwrap();
+    let ids: Vec<_> = received.iter().map(|t| t.update_id).collect();
+    assert_eq!(ids, vec![1, 3]);
+    assert_eq!(received[1].best_bid_price, 12.);
+    assert_eq!(
+      clock.now(),
+      Utc.timestamp_millis_opt(1664625600100).unwrap()
+    );
+  }
+
+  #[actix_rt::test]
+  async fn scaled_speed() {
+    let file = write(PAYLOADS);
+    let (collector, recipient) = collect::<TickerMessage>();
+    let replay = Replay::new(
+      [file.path()],
+      ReplayFormat::BinanceJsonLines,
+      vec![recipient],
+    )
+    .with_speed(Speed::Scaled(2.))
+    .unwrap();
+
+    let start = std::time::Instant::now();
+    replay.run().await.unwrap();
+    assert!(start.elapsed() >= std::time::Duration::from_millis(50));
+    assert_eq!(collector.send(Take::new()).await.unwrap().len(), 2);
+  }
+
+  #[test]
+  fn invalid_speed() {
+    let replay = || Replay::new([""], ReplayFormat::BinanceJsonLines, vec![]);
+    assert!(replay().with_speed(Speed::Scaled(0.)).is_none());
+    assert!(replay().with_speed(Speed::RealTime).is_some());
+  }
+
+  #[actix_rt::test]
+  async fn overflowing_speed() {
+    let file = write(PAYLOADS);
+    let (collector, recipient) = collect::<TickerMessage>();
+    let replay = Replay::new(
+      [file.path()],
+      ReplayFormat::BinanceJsonLines,
+      vec![recipient],
+    )
+    .with_speed(Speed::Scaled(1e-20))
+    .unwrap();
+
+    let e = replay.run().await.unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    assert_eq!(collector.send(Take::new()).await.unwrap().len(), 1);
+  }
+
+  #[test]
+  fn top_of_book_csv() {
+    let file = write(
+      "update_id,best_bid_price,best_bid_qty,best_ask_price,best_ask_qty,transaction_time,event_time\n\
+       7,10.5,1,11,2,1664625600000,1664625600001\n\
+       8,10.75,1,11,2,1664625600002,\n",
+    );
+    let format = ReplayFormat::TopOfBookCsv {
+      symbol: "BTCUSDT".to_owned(),
+    };
+    let tickers: Vec<_> = read_tickers(file.path(), &format)
+      .unwrap()
+      .collect::<io::Result<_>>()
+      .unwrap();
+
+    let times: Vec<_> = tickers
+      .iter()
+      .map(|(time, _)| time.unwrap().timestamp_millis())
+      .collect();
+    assert_eq!(times, vec![1664625600001, 1664625600002]);
+    assert_eq!(tickers[1].1.symbol, "BTCUSDT");
+    assert_eq!(tickers[1].1.best_bid_price, 10.75);
+  }
+
+  #[test]
+  fn recordings() {
+    let dir = tempfile::tempdir().unwrap();
+    let start = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+    let clock = SimulatedClock::new(start);
+    for format in [Format::JsonLines, Format::Binary] {
+      let mut recorder = Recorder::new(dir.path(), "replay", format)
+        .unwrap()
+        .with_clock(Arc::new(clock.clone()));
+      for update_id in 0..3 {
+        clock.set(start + Duration::seconds(update_id as i64));
+        recorder
+          .write(
+            TickerMessage {
+              update_id,
+              ..Default::default()
+            }
+            .into(),
+          )
+          .unwrap();
+      }
+      recorder.flush().unwrap();
+      let path = std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.extension().unwrap() == format.extension())
+        .unwrap();
+
+      let tickers: Vec<_> =
+        read_tickers(path, &ReplayFormat::Recording(format))
+          .unwrap()
+          .collect::<io::Result<_>>()
+          .unwrap();
+      assert_eq!(tickers.len(), 3);
+      assert_eq!(tickers[2].0, Some(start + Duration::seconds(2)));
+      assert_eq!(tickers[2].1.update_id, 2);
+    }
+  }
+}
@@ -4,18 +4,34 @@ use actix::Arbiter;
 use actix::Context;
 use actix::Handler;
 use actix::Message;
+use actix::MessageResult;
+use actix::Recipient;
 use binance::account::*;
 use binance::api::*;
 use binance::config::Config;
 use binance::rest_model::Transaction;
-use binance::rest_model::{OrderSide, OrderType, TimeInForce};
+use binance::rest_model::{OrderSide, OrderStatus, OrderType, TimeInForce};
 use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::mpsc::channel;
+use std::time::Instant;
 
+use crate::backtest::exchange::{Fill, Side};
+use crate::correlation::{self, Correlated, Correlation};
+use crate::metrics::{Metrics, Ping};
 use crate::policy_maker::PolicyDecision;
 
 pub struct TradeActor {
   arbiter: Arbiter,
+  config: Config,
+  credentials: Option<(String, String)>,
+  state: TradingState,
+  positions: BTreeMap<String, f64>,
+  open_orders: Vec<Transaction>,
+  subscribers: Vec<Recipient<Fill>>,
+  metrics: Option<Metrics>,
+  limits: RiskLimits,
 }
 
 impl Actor for TradeActor {
@@ -24,49 +40,119 @@ impl Actor for TradeActor {
   fn started(&mut self, _ctx: &mut Context<Self>) {}
 
   fn stopped(&mut self, _ctx: &mut Context<Self>) {
-    println!("Actor is stopped");
+    tracing::info!("Trade actor stopped");
   }
 }
 
-#[derive(Message, Clone, Debug)]
+#[derive(Message, Serialize, Clone, Debug)]
 #[rtype(result = "Result<Transaction, binance::errors::Error>")]
 pub struct Buy {
   pub symbol: String,
   pub quantity: f64,
   pub price: f64,
   pub timestamp: DateTime<Utc>,
+  #[serde(rename = "correlation_id", skip_serializing_if = "Option::is_none")]
+  pub correlation: Option<Correlation>,
 }
 
-#[derive(Message, Debug, Clone)]
+#[derive(Message, Serialize, Debug, Clone)]
 #[rtype(result = "Result<Transaction, binance::errors::Error>")]
 pub struct Sell {
   pub symbol: String,
   pub quantity: f64,
   pub price: f64,
   pub timestamp: DateTime<Utc>,
+  #[serde(rename = "correlation_id", skip_serializing_if = "Option::is_none")]
+  pub correlation: Option<Correlation>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Serialize, Debug, Clone)]
 pub struct Hold {
   pub symbol: String,
   pub timestamp: DateTime<Utc>,
+  #[serde(rename = "correlation_id", skip_serializing_if = "Option::is_none")]
+  pub correlation: Option<Correlation>,
+}
+
+/// Whether the [`TradeActor`] places the orders it is asked to.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TradingState {
+  Active,
+  Paused,
+  /// The kill switch was triggered, trading can't resume.
+  Killed,
+}
+
+/// Caps on the orders placed, `None` for no cap.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RiskLimits {
+  /// Largest quantity of a single order.
+  pub max_order_quantity: Option<f64>,
+  /// Largest absolute position in a symbol once an order fills.
+  pub max_position: Option<f64>,
+}
+
+impl RiskLimits {
+  /// Whether an order of `quantity`, negative when selling, may be placed
+  /// with `position` held.
+  pub fn allow(&self, position: f64, quantity: f64) -> bool {
+    self
+      .max_order_quantity
+      .is_none_or(|max| quantity.abs() <= max)
+      && self
+        .max_position
+        .is_none_or(|max| (position + quantity).abs() <= max)
+  }
+}
+
+/// Stops placing orders until [`Resume`]d.
+#[derive(Message)]
+#[rtype(result = "TradingState")]
+pub struct Pause;
+
+/// Places orders again, unless the kill switch was triggered.
+#[derive(Message)]
+#[rtype(result = "TradingState")]
+pub struct Resume;
+
+/// Kill switch: stops trading for good and cancels the open orders.
+#[derive(Message)]
+#[rtype(result = "TradingStatus")]
+pub struct Kill;
+
+#[derive(Message)]
+#[rtype(result = "TradingStatus")]
+pub struct GetTradingStatus;
+
+/// What the [`TradeActor`] holds, from the orders it placed.
+#[derive(Serialize, Debug, Clone)]
+pub struct TradingStatus {
+  pub state: TradingState,
+  /// Executed quantity per symbol, negative when net sold.
+  pub positions: BTreeMap<String, f64>,
+  /// Orders not completely filled when placed, and not canceled since.
+  pub open_orders: Vec<Transaction>,
 }
 
 impl Handler<PolicyDecision> for TradeActor {
   type Result = ();
 
   fn handle(&mut self, msg: PolicyDecision, _ctx: &mut Context<Self>) {
+    self.handled();
+    let _span = correlation::span("trade", msg.correlation()).entered();
     match msg {
       PolicyDecision::BuyAction(buy) => self
         .buy(buy)
-        .map_err(|e| log::warn!("Error buying: {:?}", e))
+        .map_err(|e| tracing::warn!(error = ?e, "Error buying"))
         .ok(),
       PolicyDecision::SellAction(sell) => self
         .sell(sell)
-        .map_err(|e| log::warn!("Error selling: {:?}", e))
+        .map_err(|e| tracing::warn!(error = ?e, "Error selling"))
         .ok(),
       PolicyDecision::HoldAction(hold) => {
-        println!("Hold: {:?}", hold);
+        tracing::debug!(?hold, "Holding");
         None
       }
     };
@@ -77,62 +163,365 @@ impl Handler<Buy> for TradeActor {
   type Result = Result<Transaction, binance::errors::Error>;
 
   fn handle(&mut self, msg: Buy, _ctx: &mut Context<Self>) -> Self::Result {
+    self.handled();
     self.buy(msg)
   }
 }
 
 impl Default for TradeActor {
   fn default() -> Self {
-    Self {
-      arbiter: Arbiter::new(),
-    }
+    Self::new()
   }
 }
 
 impl TradeActor {
+  /// Trades on the testnet with the credentials of the `BINANCE_API_KEY`
+  /// and `BINANCE_API_SECRET_KEY` environment variables.
   pub fn new() -> Self {
     Self {
       arbiter: Arbiter::new(),
+      config: Config::testnet(),
+      credentials: None,
+      state: TradingState::Active,
+      positions: BTreeMap::new(),
+      open_orders: vec![],
+      subscribers: vec![],
+      metrics: None,
+      limits: RiskLimits::default(),
+    }
+  }
+
+  /// Publishes a [`Fill`] for every trade of the orders placed, with the
+  /// commission converted to the quote asset as fee, see [`quote_fee`].
+  pub fn with_subscribers(mut self, subscribers: Vec<Recipient<Fill>>) -> Self {
+    self.subscribers = subscribers;
+    self
+  }
+
+  /// Sends orders to the endpoints of `config`, e.g. a
+  /// [`crate::mock::rest::MockRest`].
+  pub fn with_config(mut self, config: Config) -> Self {
+    self.config = config;
+    self
+  }
+
+  /// Signs orders with these credentials instead of the environment ones.
+  pub fn with_credentials(
+    mut self,
+    api_key: impl Into<String>,
+    secret: impl Into<String>,
+  ) -> Self {
+    self.credentials = Some((api_key.into(), secret.into()));
+    self
+  }
+
+  /// Counts the messages handled and the orders per side and status, and
+  /// times their acknowledgement.
+  pub fn with_metrics(mut self, metrics: &Metrics) -> Self {
+    self.metrics = Some(metrics.clone());
+    self
+  }
+
+  fn handled(&self) {
+    if let Some(metrics) = &self.metrics {
+      metrics.handled("trade");
+    }
+  }
+
+  /// Logs the acknowledgement, with the tick-to-trade latency of orders
+  /// correlated with a ticker, and records it in the metrics.
+  fn record_order(
+    &self,
+    side: &str,
+    sent: Instant,
+    correlation: Option<Correlation>,
+    res: &Result<Transaction, binance::errors::Error>,
+  ) {
+    let latency = sent.elapsed();
+    let tick_to_trade = correlation.map(|c| c.received.elapsed());
+    let status = match res {
+      Ok(transaction) => serde_json::to_value(&transaction.status)
+        .ok()
+        .and_then(|status| status.as_str().map(str::to_lowercase))
+        .unwrap_or_else(|| "unknown".to_owned()),
+      Err(_) => "rejected".to_owned(),
+    };
+    tracing::info!(
+      side,
+      status,
+      ?latency,
+      ?tick_to_trade,
+      "Order acknowledged"
+    );
+    if let Some(metrics) = &self.metrics {
+      metrics.ordered(side, &status, latency);
+      if let Some(tick_to_trade) = tick_to_trade {
+        metrics.traded(side, tick_to_trade);
+      }
+    }
+  }
+
+  /// Rejects the orders exceeding `limits` before sending them.
+  pub fn with_limits(mut self, limits: RiskLimits) -> Self {
+    self.limits = limits;
+    self
+  }
+
+  #[allow(clippy::result_large_err)]
+  fn ensure_within_limits(
+    &self,
+    symbol: &str,
+    quantity: f64,
+  ) -> Result<(), binance::errors::Error> {
+    let position = self.positions.get(symbol).copied().unwrap_or_default();
+    if self.limits.allow(position, quantity) {
+      Ok(())
+    } else {
+      Err(binance::errors::Error::Msg(format!(
+        "order of {quantity} {symbol} exceeds the risk limits"
+      )))
     }
   }
+
+  fn account(&self) -> Account {
+    match &self.credentials {
+      Some((api_key, secret)) => Binance::new_with_config(
+        Some(api_key.clone()),
+        Some(secret.clone()),
+        &self.config,
+      ),
+      None => Binance::new_with_env(&self.config),
+    }
+  }
+
+  fn status(&self) -> TradingStatus {
+    TradingStatus {
+      state: self.state,
+      positions: self.positions.clone(),
+      open_orders: self.open_orders.clone(),
+    }
+  }
+
+  #[allow(clippy::result_large_err)]
+  fn ensure_active(&self) -> Result<(), binance::errors::Error> {
+    match self.state {
+      TradingState::Active => Ok(()),
+      state => {
+        Err(binance::errors::Error::Msg(format!("trading is {state:?}")))
+      }
+    }
+  }
+
+  fn track(&mut self, transaction: &Transaction) {
+    let quantity = match transaction.side {
+      OrderSide::Buy => transaction.executed_qty,
+      OrderSide::Sell => -transaction.executed_qty,
+    };
+    *self
+      .positions
+      .entry(transaction.symbol.clone())
+      .or_default() += quantity;
+    let timestamp =
+      DateTime::from_timestamp_millis(transaction.transact_time as i64)
+        .unwrap_or_else(Utc::now);
+    let side = match transaction.side {
+      OrderSide::Buy => Side::Buy,
+      OrderSide::Sell => Side::Sell,
+    };
+    for trade in &transaction.fills {
+      let fill = Fill {
+        symbol: transaction.symbol.clone(),
+        side,
+        quantity: trade.qty,
+        price: trade.price,
+        fee: quote_fee(&transaction.symbol, trade).unwrap_or_else(|| {
+          tracing::warn!(
+            symbol = transaction.symbol,
+            commission = trade.commission,
+            commission_asset = trade.commission_asset,
+            "Commission in another asset than the symbol's, left out of the fee"
+          );
+          0.
+        }),
+        timestamp,
+      };
+      for s in &self.subscribers {
+        s.do_send(fill.clone());
+      }
+    }
+    if matches!(
+      transaction.status,
+      OrderStatus::New | OrderStatus::PartiallyFilled
+    ) {
+      self.open_orders.push(transaction.clone());
+    }
+  }
+
+  /// Keeps the orders that couldn't be canceled.
+  fn cancel_open_orders(&mut self) {
+    let account = self.account();
+    let orders = std::mem::take(&mut self.open_orders);
+    let (tx, rx) = channel();
+    let task = async move {
+      let mut remaining = vec![];
+      for order in orders {
+        let cancellation = OrderCancellation {
+          symbol: order.symbol.clone(),
+          order_id: Some(order.order_id),
+          ..OrderCancellation::default()
+        };
+        if let Err(e) = account.cancel_order(cancellation).await {
+          tracing::warn!(
+            order_id = order.order_id,
+            error = ?e,
+            "Error canceling order"
+          );
+          remaining.push(order);
+        }
+      }
+      tx.send(remaining).unwrap();
+    };
+    self.arbiter.spawn(task);
+    self.open_orders = rx.recv().unwrap();
+  }
+
+  #[allow(clippy::result_large_err)]
   fn buy(&mut self, msg: Buy) -> Result<Transaction, binance::errors::Error> {
-    log::info!("ORDER: {:?}", msg);
+    self.ensure_active()?;
+    self.ensure_within_limits(&msg.symbol, msg.quantity)?;
+    tracing::info!(order = ?msg, "Placing buy order");
+    let correlation = msg.correlation;
+    let account = self.account();
     let (tx, rx) = channel();
+    let sent = Instant::now();
     let task = async move {
-      let res = buy(msg.symbol.as_str(), msg.quantity, msg.price).await;
+      let res =
+        buy(&account, msg.symbol.as_str(), msg.quantity, msg.price).await;
       tx.send(res).unwrap();
     };
     self.arbiter.spawn(task);
-    rx.recv().unwrap()
+    let res = rx.recv().unwrap();
+    self.record_order("buy", sent, correlation, &res);
+    if let Ok(transaction) = &res {
+      self.track(transaction);
+    }
+    res
   }
 }
 
 impl Handler<Sell> for TradeActor {
   type Result = Result<Transaction, binance::errors::Error>;
   fn handle(&mut self, msg: Sell, _ctx: &mut Context<Self>) -> Self::Result {
+    self.handled();
     self.sell(msg)
   }
 }
 
 impl TradeActor {
+  #[allow(clippy::result_large_err)]
   fn sell(&mut self, msg: Sell) -> Result<Transaction, binance::errors::Error> {
-    log::info!("ORDER: {:?}", msg);
+    self.ensure_active()?;
+    self.ensure_within_limits(&msg.symbol, -msg.quantity)?;
+    tracing::info!(order = ?msg, "Placing sell order");
+    let correlation = msg.correlation;
+    let account = self.account();
     let (tx, rx) = channel();
+    let sent = Instant::now();
     let task = async move {
-      let res = sell(msg.symbol.as_str(), msg.quantity, msg.price).await;
+      let res =
+        sell(&account, msg.symbol.as_str(), msg.quantity, msg.price).await;
       tx.send(res).unwrap();
     };
     self.arbiter.spawn(task);
-    rx.recv().unwrap()
+    let res = rx.recv().unwrap();
+    self.record_order("sell", sent, correlation, &res);
+    if let Ok(transaction) = &res {
+      self.track(transaction);
+    }
+    res
+  }
+}
+
+impl Handler<Ping> for TradeActor {
+  type Result = ();
+
+  fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+}
+
+impl Handler<Pause> for TradeActor {
+  type Result = MessageResult<Pause>;
+
+  fn handle(&mut self, _: Pause, _ctx: &mut Context<Self>) -> Self::Result {
+    if self.state == TradingState::Active {
+      tracing::info!("Trading paused");
+      self.state = TradingState::Paused;
+    }
+    MessageResult(self.state)
+  }
+}
+
+impl Handler<Resume> for TradeActor {
+  type Result = MessageResult<Resume>;
+
+  fn handle(&mut self, _: Resume, _ctx: &mut Context<Self>) -> Self::Result {
+    if self.state == TradingState::Paused {
+      tracing::info!("Trading resumed");
+      self.state = TradingState::Active;
+    }
+    MessageResult(self.state)
+  }
+}
+
+impl Handler<Kill> for TradeActor {
+  type Result = MessageResult<Kill>;
+
+  fn handle(&mut self, _: Kill, _ctx: &mut Context<Self>) -> Self::Result {
+    tracing::warn!("Kill switch triggered");
+    self.state = TradingState::Killed;
+    self.cancel_open_orders();
+    MessageResult(self.status())
+  }
+}
+
+impl Handler<GetTradingStatus> for TradeActor {
+  type Result = MessageResult<GetTradingStatus>;
+
+  fn handle(
+    &mut self,
+    _: GetTradingStatus,
+    _ctx: &mut Context<Self>,
+  ) -> Self::Result {
+    MessageResult(self.status())
+  }
+}
+
+/// Commission of a trade of `symbol` in its quote asset, converted at the
+/// trade price when charged in its base asset. `None` when charged in
+/// another asset, e.g. BNB, which has no price here.
+pub fn quote_fee(
+  symbol: &str,
+  trade: &binance::rest_model::Fill,
+) -> Option<f64> {
+  let asset = trade.commission_asset.as_str();
+  if trade.commission == 0. {
+    Some(0.)
+  } else if asset.is_empty() {
+    None
+  } else if symbol.ends_with(asset) {
+    Some(trade.commission)
+  } else if symbol.starts_with(asset) {
+    Some(trade.commission * trade.price)
+  } else {
+    None
   }
 }
 
 async fn buy(
+  account: &Account,
   symbol: &str,
   quantity: f64,
   price: f64,
 ) -> Result<Transaction, binance::errors::Error> {
-  let account: Account = Binance::new_with_env(&Config::testnet());
   let market_buy = OrderRequest {
     symbol: symbol.to_string(),
     quantity: Some(quantity),
@@ -147,11 +536,11 @@ async fn buy(
 }
 
 async fn sell(
+  account: &Account,
   symbol: &str,
   quantity: f64,
   price: f64,
 ) -> Result<Transaction, binance::errors::Error> {
-  let account: Account = Binance::new_with_env(&Config::testnet());
   let market_buy = OrderRequest {
     symbol: symbol.to_string(),
     quantity: Some(quantity),
@@ -168,36 +557,142 @@ async fn sell(
 #[cfg(test)]
 mod test {
   use super::*;
-  use dotenv::dotenv;
+  use crate::mock::rest::{MockRest, MockRestServer, Reply};
+
+  fn trade_actor(server: &MockRest) -> TradeActor {
+    TradeActor::new()
+      .with_config(server.config())
+      .with_credentials("key", "secret")
+  }
 
   #[actix_rt::test]
   async fn test_actor_sell() {
-    dotenv().ok();
-    let trade_actor = TradeActor::new().start();
+    let server = MockRestServer::new("key", "secret")
+      .with_replies(vec![Reply::Reject {
+        code: -2010,
+        msg: "Account has insufficient balance.".to_owned(),
+      }])
+      .start()
+      .unwrap();
+    let trade_actor = trade_actor(&server).start();
     let res = trade_actor
       .send(Sell {
         symbol: "BTCUSDT".to_string(),
         quantity: 0.001,
         price: 10000.0,
         timestamp: Utc::now(),
+        correlation: None,
       })
-      .await;
-    println!("{:?}", res);
-    assert!(res.is_ok());
+      .await
+      .unwrap();
+    assert_matches::assert_matches!(
+      res,
+      Err(binance::errors::Error::BinanceError { response })
+        if response.code == -2010
+    );
   }
 
   #[actix_rt::test]
   async fn test_actor_buy() {
-    dotenv().ok();
-    let trade_actor = TradeActor::new().start();
+    let server = MockRestServer::new("key", "secret").start().unwrap();
+    let trade_actor = trade_actor(&server).start();
     let res = trade_actor
       .send(Buy {
         symbol: "BTCUSDT".to_string(),
         quantity: 0.001,
         price: 10000.0,
         timestamp: Utc::now(),
+        correlation: None,
+      })
+      .await
+      .unwrap()
+      .unwrap();
+    assert_eq!(res.status, OrderStatus::Filled);
+    assert_eq!(res.executed_qty, 0.001);
+
+    let orders = server.orders();
+    assert_eq!(orders.len(), 1);
+    assert_eq!(orders[0].side, OrderSide::Buy);
+  }
+
+  #[test]
+  fn converts_commissions_to_the_quote_asset() {
+    let trade = |commission, asset: &str| binance::rest_model::Fill {
+      price: 20_000.,
+      qty: 0.01,
+      commission,
+      commission_asset: asset.to_owned(),
+    };
+    assert_eq!(quote_fee("BTCUSDT", &trade(0.2, "USDT")), Some(0.2));
+    assert_eq!(quote_fee("BTCUSDT", &trade(0.00001, "BTC")), Some(0.2));
+    assert_eq!(quote_fee("BTCUSDT", &trade(0.001, "BNB")), None);
+    assert_eq!(quote_fee("BTCUSDT", &trade(0., "BNB")), Some(0.));
+  }
+
+  #[test]
+  fn risk_limits() {
+    let limits = RiskLimits {
+      max_order_quantity: Some(1.),
+      max_position: Some(2.),
+    };
+    assert!(limits.allow(0., 1.));
+    assert!(!limits.allow(0., 1.5));
+    assert!(limits.allow(1.5, -1.));
+    assert!(!limits.allow(1.5, 1.));
+    assert!(!limits.allow(-1.5, -1.));
+    assert!(RiskLimits::default().allow(100., 100.));
+  }
+
+  #[actix_rt::test]
+  async fn rejects_orders_beyond_limits() {
+    let server = MockRestServer::new("key", "secret").start().unwrap();
+    let trade_actor = trade_actor(&server)
+      .with_limits(RiskLimits {
+        max_order_quantity: Some(0.001),
+        max_position: None,
+      })
+      .start();
+    let res = trade_actor
+      .send(Buy {
+        symbol: "BTCUSDT".to_string(),
+        quantity: 0.01,
+        price: 10000.0,
+        timestamp: Utc::now(),
+        correlation: None,
       })
-      .await;
-    assert!(res.is_ok());
+      .await
+      .unwrap();
+    assert_matches::assert_matches!(res, Err(binance::errors::Error::Msg(_)));
+    assert!(server.orders().is_empty());
+  }
+
+  #[actix_rt::test]
+  async fn test_kill_switch() {
+    let server = MockRestServer::new("key", "secret")
+      .with_replies(vec![Reply::Accept])
+      .start()
+      .unwrap();
+    let trade_actor = trade_actor(&server).start();
+    let buy = Buy {
+      symbol: "BTCUSDT".to_string(),
+      quantity: 0.001,
+      price: 10000.0,
+      timestamp: Utc::now(),
+      correlation: None,
+    };
+    trade_actor.send(buy.clone()).await.unwrap().unwrap();
+
+    assert_eq!(trade_actor.send(Pause).await.unwrap(), TradingState::Paused);
+    assert!(trade_actor.send(buy).await.unwrap().is_err());
+    assert_eq!(server.orders().len(), 1);
+
+    let status = trade_actor.send(Kill).await.unwrap();
+    assert_eq!(status.state, TradingState::Killed);
+    assert!(status.open_orders.is_empty());
+    assert_eq!(server.orders()[0].status, OrderStatus::Canceled);
+    assert_eq!(
+      trade_actor.send(Resume).await.unwrap(),
+      TradingState::Killed
+    );
   }
 }
@@ -1,14 +1,27 @@
 use actix::Message;
-use serde::{de::Error, Deserialize};
+use serde::{de::Error, Deserialize, Serializer};
 
 use awc::Client;
+use chrono::{DateTime, Utc};
 
 use openssl::ssl::{SslConnector, SslMethod};
 
-#[derive(Message)]
-#[rtype(result = "f64")]
+#[derive(Message, Debug, Clone, Copy, PartialEq)]
+#[rtype(result = "()")]
 pub struct Double(pub f64);
 
+impl From<Double> for f64 {
+  fn from(msg: Double) -> Self {
+    msg.0
+  }
+}
+
+impl From<f64> for Double {
+  fn from(value: f64) -> Self {
+    Self(value)
+  }
+}
+
 pub fn deserialize_from_str<'de, D, T>(deserializer: D) -> Result<T, D::Error>
 where
   D: serde::de::Deserializer<'de>,
@@ -21,6 +34,47 @@ where
   })
 }
 
+/// Writes a value as a string, the inverse of [`deserialize_from_str`].
+pub fn serialize_to_string<S, T>(
+  value: &T,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+  T: std::fmt::Display,
+{
+  serializer.collect_str(value)
+}
+
+/// Reads an optional timestamp in milliseconds since the epoch.
+pub fn deserialize_millis<'de, D>(
+  deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+  D: serde::de::Deserializer<'de>,
+{
+  Option::<i64>::deserialize(deserializer)?
+    .map(|millis| {
+      DateTime::from_timestamp_millis(millis)
+        .ok_or_else(|| Error::custom("timestamp out of range"))
+    })
+    .transpose()
+}
+
+/// Writes an optional timestamp in milliseconds since the epoch.
+pub fn serialize_millis<S>(
+  timestamp: &Option<DateTime<Utc>>,
+  serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+  S: Serializer,
+{
+  match timestamp {
+    Some(timestamp) => serializer.serialize_some(&timestamp.timestamp_millis()),
+    None => serializer.serialize_none(),
+  }
+}
+
 pub(crate) fn tls_web_client() -> Client {
   let ssl = SslConnector::builder(SslMethod::tls()).unwrap().build();
   let conn = awc::Connector::new().openssl(ssl);
@@ -1,52 +1,77 @@
-use tactix::actors::risk::Drawdown;
+use tactix::actors::risk::drawdown::Drawdown;
+use tactix::algos::indicators::Indicator;
 use tactix::binance_websocket::{BinanceIngestor, TickerMessage};
-use tactix::util::Double;
+use tactix::mock;
+use tactix::mock::websocket::{MockStreamServer, Step};
 
 use actix::{Actor, Context, Handler, Message};
+use chrono::Utc;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use dotenv::dotenv;
+#[test]
+fn test_drawdown() {
+  let now = Utc::now();
+  let mut drawdown = Drawdown::new();
 
-#[actix_rt::test]
-async fn test_drawdown() {
-  let addr1 = Drawdown::new(vec![]).start();
-
-  let res = addr1.send(Double(0.)).await.unwrap();
+  let res = drawdown.update((now, 0.)).unwrap();
 
-  assert_eq!(res, 0.);
+  assert_eq!(res.max, 0.);
 
-  let res = addr1.send(Double(1.)).await.unwrap();
+  let res = drawdown.update((now, 1.)).unwrap();
 
-  assert_eq!(res, 0.);
+  assert_eq!(res.max, 0.);
 
-  let res = addr1.send(Double(0.5)).await.unwrap();
+  let res = drawdown.update((now, 0.5)).unwrap();
 
-  assert_eq!(res, 0.5);
+  assert_eq!((res.current, res.max), (0.5, 0.5));
+  assert_eq!(res.current_percent, 0.5);
 
-  let res = addr1.send(Double(0.)).await.unwrap();
+  let res = drawdown.update((now, 0.)).unwrap();
 
-  assert_eq!(res, 1.);
+  assert_eq!((res.current, res.max), (1., 1.));
+  assert_eq!(res.max_percent, 1.);
 }
 
 #[actix_rt::test]
 async fn test_ticker() {
-  env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
-  dotenv().ok();
+  let _ = env_logger::builder().is_test(true).try_init();
+
+  let ticker = TickerMessage {
+    update_id: 1,
+    symbol: "BTCUSDT".to_owned(),
+    best_bid_price: 19_000.,
+    best_ask_price: 19_001.,
+    ..Default::default()
+  };
+  let server = MockStreamServer::new(vec![vec![Step::book_ticker(&ticker)]])
+    .start()
+    .unwrap();
 
   let rsa = ReceivedSomethingActor::new().start();
 
-  let st = BinanceIngestor::new(vec![rsa.clone().recipient()], vec![]);
+  let st = BinanceIngestor::new(vec![rsa.clone().recipient()], vec![])
+    .with_url(server.url());
 
   actix::spawn(st.run());
 
-  loop {
-    tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+  let received = async {
+    loop {
+      tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
 
-    let rs = rsa.send(ReceivedSomethingMessage);
+      let rs = rsa.send(ReceivedSomethingMessage);
 
-    if rs.await.unwrap() {
-      break;
+      if rs.await.unwrap() {
+        break;
+      }
     }
-  }
+  };
+  tokio::time::timeout(tokio::time::Duration::from_secs(10), received)
+    .await
+    .expect("no ticker received");
 }
 
 #[derive(Message)]
@@ -68,16 +93,11 @@ impl Actor for ReceivedSomethingActor {
 }
 
 impl Handler<TickerMessage> for ReceivedSomethingActor {
-  type Result = f64;
+  type Result = ();
 
-  fn handle(
-    &mut self,
-    msg: TickerMessage,
-    _ctx: &mut Context<Self>,
-  ) -> Self::Result {
-    log::info!("Ticker msg received: {:?}", msg);
+  fn handle(&mut self, msg: TickerMessage, _ctx: &mut Context<Self>) {
+    tracing::info!(ticker = ?msg, "Ticker msg received");
     self.rcvd = true;
-    0.
   }
 }
 
@@ -92,3 +112,120 @@ impl Handler<ReceivedSomethingMessage> for ReceivedSomethingActor {
     self.rcvd
   }
 }
+
+fn tactix(args: &[&str]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_tactix"))
+    .args(args)
+    .output()
+    .unwrap()
+}
+
+fn tickers() -> tempfile::NamedTempFile {
+  let mut file = tempfile::NamedTempFile::new().unwrap();
+  mock::write_payloads(&mut file, &mock::tickers(&mock::PRICES)).unwrap();
+  file
+}
+
+#[test]
+fn test_check_config() {
+  let output = tactix(&["check-config", "examples/engine.toml"]);
+  assert!(output.status.success());
+
+  let mut config = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+  let example = include_str!("../examples/engine.toml");
+  write!(config, "{}", example.replace("period = 3", "period = 0")).unwrap();
+  let output = tactix(&["check-config", config.path().to_str().unwrap()]);
+  assert!(!output.status.success());
+  let stderr = String::from_utf8(output.stderr).unwrap();
+  assert!(stderr.contains("indicator `moving_average` has invalid parameters"));
+}
+
+#[test]
+fn test_backtest() {
+  let file = tickers();
+  let output = tactix(&[
+    "backtest",
+    "--format",
+    "binance_json_lines",
+    file.path().to_str().unwrap(),
+  ]);
+  assert!(output.status.success());
+  let report: serde_json::Value =
+    serde_json::from_slice(&output.stdout).unwrap();
+  assert_eq!(
+    report["equity"].as_array().unwrap().len(),
+    mock::PRICES.len()
+  );
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shuts_down_on_sigterm() {
+  let file = tickers();
+  let mut config = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+  write!(
+    config,
+    r#"
+symbols = ["BTCUSDT"]
+source = {{ kind = "binance" }}
+strategy = {{ moving_average = "moving_average" }}
+execution = {{ venue = "paper" }}
+
+[[indicators]]
+name = "moving_average"
+kind = "sma"
+period = 3
+"#
+  )
+  .unwrap();
+  // replayed in real time, the tickers span nine seconds
+  let mut child = Command::new(env!("CARGO_BIN_EXE_tactix"))
+    .args([
+      "replay",
+      config.path().to_str().unwrap(),
+      file.path().to_str().unwrap(),
+      "--format",
+      "binance_json_lines",
+      "--speed",
+      "1",
+    ])
+    .env("RUST_LOG", "info,tactix::replay=debug")
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .unwrap();
+  // the signal handlers are installed before the first ticker is replayed
+  let logs = BufReader::new(child.stderr.take().unwrap()).lines();
+  let (ready, replaying) = mpsc::channel();
+  thread::spawn(move || {
+    for line in logs.map_while(Result::ok) {
+      if line.contains("Replaying ticker message") {
+        let _ = ready.send(());
+      }
+    }
+  });
+  if replaying.recv_timeout(Duration::from_secs(10)).is_err() {
+    child.kill().unwrap();
+    panic!("no ticker replayed within 10s");
+  }
+  let killed = Command::new("kill")
+    .args(["-TERM", &child.id().to_string()])
+    .status()
+    .unwrap();
+  assert!(killed.success());
+
+  let deadline = Instant::now() + Duration::from_secs(10);
+  while child.try_wait().unwrap().is_none() {
+    if Instant::now() > deadline {
+      child.kill().unwrap();
+      panic!("not shut down within 10s of SIGTERM");
+    }
+    thread::sleep(Duration::from_millis(10));
+  }
+  let output = child.wait_with_output().unwrap();
+  assert!(output.status.success());
+  let report: serde_json::Value =
+    serde_json::from_slice(&output.stdout).unwrap();
+  let equity = report["equity"].as_array().unwrap().len();
+  assert!(0 < equity && equity < mock::PRICES.len(), "{equity}");
+}